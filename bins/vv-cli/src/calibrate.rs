@@ -0,0 +1,237 @@
+use clap::Args;
+use std::path::PathBuf;
+use std::process::exit;
+use tracing::{error, info, info_span, warn};
+use viking_vision::buffer::Buffer;
+use vv_utils::calib::{CalibrationOptions, CalibrationView, calibrate};
+use vv_utils::common_types::FrameSize;
+
+fn parse_dims(s: &str) -> Result<(usize, usize), String> {
+    let (w, h) = s
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected dimensions like 9x6, got {s:?}"))?;
+    let w = w.parse().map_err(|err| format!("invalid width: {err}"))?;
+    let h = h.parse().map_err(|err| format!("invalid height: {err}"))?;
+    Ok((w, h))
+}
+
+#[derive(Debug, Args)]
+pub struct CalibrateArgs {
+    /// Images of the calibration target, taken from a variety of angles
+    #[arg(required = true)]
+    images: Vec<PathBuf>,
+    /// The name of the camera to print the configuration for
+    #[arg(short, long, default_value = "camera")]
+    camera: String,
+    /// Use a chessboard target with the given number of inner corners, like 9x6
+    #[arg(long, value_parser = parse_dims)]
+    chessboard: Option<(usize, usize)>,
+    /// The side length of a chessboard square
+    ///
+    /// This only affects the scale of the target, not the result.
+    #[arg(long, default_value_t = 1.0)]
+    square: f64,
+    /// Use a grid of AprilTags with the given number of columns and rows, like 6x4
+    ///
+    /// Tags are expected to have sequential IDs in row-major order, starting from the top left.
+    #[cfg(feature = "apriltag")]
+    #[arg(long, value_parser = parse_dims)]
+    tag_grid: Option<(usize, usize)>,
+    /// The size of each tag in the grid
+    #[cfg(feature = "apriltag")]
+    #[arg(long, default_value_t = 1.0)]
+    tag_size: f64,
+    /// The gap between adjacent tags in the grid, in the same units as the tag size
+    #[cfg(feature = "apriltag")]
+    #[arg(long, default_value_t = 0.25)]
+    tag_spacing: f64,
+    /// The tag family used in the grid
+    #[cfg(feature = "apriltag")]
+    #[arg(long, default_value = "tag36h11")]
+    tag_family: vv_apriltag::TagFamilyWithBits,
+    /// The ID of the top-left tag in the grid
+    #[cfg(feature = "apriltag")]
+    #[arg(long, default_value_t = 0)]
+    first_id: i32,
+    /// Also estimate the third radial distortion coefficient
+    ///
+    /// This is only useful for wide-angle lenses, and tends to overfit otherwise.
+    #[arg(long)]
+    k3: bool,
+    /// Don't estimate any distortion
+    #[arg(long, conflicts_with = "k3")]
+    no_distortion: bool,
+}
+
+enum Target {
+    Chessboard {
+        cols: usize,
+        rows: usize,
+        square: f64,
+    },
+    #[cfg(feature = "apriltag")]
+    TagGrid {
+        detector: vv_apriltag::Detector,
+        cols: usize,
+        rows: usize,
+        size: f64,
+        spacing: f64,
+        first_id: i32,
+    },
+}
+impl Target {
+    fn from_args(args: &CalibrateArgs) -> Self {
+        #[cfg(feature = "apriltag")]
+        if let Some((cols, rows)) = args.tag_grid {
+            if args.chessboard.is_some() {
+                error!("only one calibration target can be used");
+                exit(1);
+            }
+            let mut detector = vv_apriltag::Detector::new();
+            detector.add_family(args.tag_family);
+            return Self::TagGrid {
+                detector,
+                cols,
+                rows,
+                size: args.tag_size,
+                spacing: args.tag_spacing,
+                first_id: args.first_id,
+            };
+        }
+        let Some((cols, rows)) = args.chessboard else {
+            #[cfg(feature = "apriltag")]
+            error!("a calibration target must be specified with --chessboard or --tag-grid");
+            #[cfg(not(feature = "apriltag"))]
+            error!("a calibration target must be specified with --chessboard");
+            exit(1);
+        };
+        Self::Chessboard {
+            cols,
+            rows,
+            square: args.square,
+        }
+    }
+    /// Find the target in an image.
+    fn find(&mut self, img: Buffer<'_>) -> Option<CalibrationView> {
+        match self {
+            &mut Self::Chessboard { cols, rows, square } => {
+                let image = viking_vision::vision::find_chessboard_corners(img, cols, rows)?;
+                let object = (0..rows)
+                    .flat_map(|y| (0..cols).map(move |x| [x as f64 * square, y as f64 * square]))
+                    .collect();
+                Some(CalibrationView { object, image })
+            }
+            #[cfg(feature = "apriltag")]
+            Self::TagGrid {
+                detector,
+                cols,
+                rows,
+                size,
+                spacing,
+                first_id,
+            } => {
+                let mut view = CalibrationView::default();
+                let pitch = *size + *spacing;
+                for det in detector.detect(img) {
+                    let idx = det.id() - *first_id;
+                    if idx < 0 || idx as usize >= *cols * *rows {
+                        continue;
+                    }
+                    let x = (idx as usize % *cols) as f64 * pitch;
+                    let y = (idx as usize / *cols) as f64 * pitch;
+                    // detection corners go counter-clockwise, starting from the bottom left
+                    let object = [
+                        [x, y + *size],
+                        [x + *size, y + *size],
+                        [x + *size, y],
+                        [x, y],
+                    ];
+                    view.object.extend(object);
+                    view.image.extend(det.corners());
+                }
+                // a single tag is coplanar with too few points to be useful
+                (view.object.len() >= 8).then_some(view)
+            }
+        }
+    }
+}
+
+pub fn run(args: CalibrateArgs) {
+    let mut target = Target::from_args(&args);
+    let mut size = None;
+    let mut views = Vec::new();
+    let mut found = Vec::new();
+    for path in &args.images {
+        let _guard = info_span!("image", path = %path.display()).entered();
+        let img = match std::fs::read(path) {
+            Ok(data) => match Buffer::decode_img_data(&data) {
+                Ok(img) => img,
+                Err(err) => {
+                    error!(%err, "failed to decode image");
+                    continue;
+                }
+            },
+            Err(err) => {
+                error!(%err, "failed to read image");
+                continue;
+            }
+        };
+        let img_size = FrameSize {
+            width: img.width,
+            height: img.height,
+        };
+        if *size.get_or_insert(img_size) != img_size {
+            warn!(
+                width = img.width,
+                height = img.height,
+                "image size doesn't match the first image, skipping"
+            );
+            continue;
+        }
+        match target.find(img) {
+            Some(view) => {
+                info!(points = view.object.len(), "found calibration target");
+                views.push(view);
+                found.push(path);
+            }
+            None => warn!("couldn't find calibration target"),
+        }
+    }
+    let Some(size) = size else {
+        error!("no images could be loaded");
+        exit(2);
+    };
+    let options = CalibrationOptions {
+        radial: !args.no_distortion,
+        tangential: !args.no_distortion,
+        k3: args.k3,
+        ..Default::default()
+    };
+    let calib = match calibrate(&views, size, options) {
+        Ok(calib) => calib,
+        Err(err) => {
+            error!(%err, "calibration failed");
+            exit(3);
+        }
+    };
+    info!(rms_error = calib.rms_error, "finished calibration");
+    for (path, err) in found.iter().zip(&calib.view_errors) {
+        if *err > calib.rms_error * 3.0 {
+            warn!(path = %path.display(), rms_error = err, "image has a high reprojection error");
+        }
+    }
+    let intrinsics = match toml::Value::try_from(calib.intrinsics) {
+        Ok(v) => v,
+        Err(err) => {
+            error!(%err, "failed to serialize intrinsics");
+            exit(3);
+        }
+    };
+    let mut camera = toml::Table::new();
+    camera.insert("intrinsics".to_string(), intrinsics);
+    let mut cameras = toml::Table::new();
+    cameras.insert(args.camera, camera.into());
+    let mut root = toml::Table::new();
+    root.insert("camera".to_string(), cameras.into());
+    print!("{root}");
+}
@@ -1,14 +1,29 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
 use std::process::exit;
-use tracing::{debug, error, error_span, info};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{Scope, ScopedJoinHandle};
+use std::time::Duration;
+use tracing::{debug, error, error_span, info, warn};
 use tracing_subscriber::fmt::writer as tsfw;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use viking_vision::buffer::Buffer;
+use viking_vision::camera::background::FrameQuerier;
+use viking_vision::camera::group::{GroupContext, GroupMetadata, GroupQuerier};
+use viking_vision::camera::hotplug::{CameraRun, DeviceWatcher, HotplugAction, HotplugState};
+use viking_vision::camera::{CameraConfig, FrameContext, FullCameraMetadata};
 use viking_vision::pipeline::prelude::*;
+use viking_vision::pipeline::runner::{RunError, RunErrorCause, RunErrorWithParams};
+use viking_vision::pipeline::serialized::ComponentChannel;
+use viking_vision::vision_debug::GLOBAL_SENDER;
+
+mod calibrate;
 
 #[cfg(not(windows))]
 fn env_allows_color() -> bool {
@@ -77,9 +92,13 @@ impl Display for Color {
 }
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// The TOML config file to load
-    config: PathBuf,
+    #[arg(required = true)]
+    config: Option<PathBuf>,
     /// The log file to write to
     ///
     /// This can be formatted with strftime placeholders.
@@ -115,6 +134,14 @@ struct Cli {
     allow_noisy_events: bool,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Calibrate a camera from images of a chessboard or AprilTag grid
+    ///
+    /// The resulting intrinsics are printed as TOML, ready to be added to a config file.
+    Calibrate(calibrate::CalibrateArgs),
+}
+
 fn format_log_file(arg: &str, now: time::OffsetDateTime) -> String {
     match time::format_description::parse_strftime_borrowed(arg) {
         Ok(desc) => match now.format(&desc) {
@@ -143,6 +170,316 @@ impl<'a> tsfw::MakeWriter<'a> for Writer {
     }
 }
 
+/// Check the result of starting a pipeline run, returning `false` if the capture loop should stop.
+fn check_run<E>(res: Result<(), RunError<'_, E>>) -> bool {
+    if let Err(RunError::WithParams(RunErrorWithParams { cause, .. })) = res {
+        match cause {
+            RunErrorCause::ArgsMismatch { expected, given } => {
+                error!(expected, given, "argument length mismatch");
+                return false;
+            }
+            RunErrorCause::NoComponent(id) => {
+                error!(%id, "missing component");
+                return false;
+            }
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Find the components that a camera's frames should be sent to.
+fn camera_targets(
+    outputs: Vec<ComponentChannel>,
+    runner: &PipelineRunner,
+) -> Vec<RunnerComponentId> {
+    outputs
+        .into_iter()
+        .filter_map(|ch| {
+            let opt = runner.lookup.get(&ch.component);
+            if let Some(&id) = opt {
+                debug!(name = &*ch.component, %id, "found component to send frames to");
+                let Some(comp) = runner.component(id) else {
+                    error!("lookup table points to a nonexistent component");
+                    return None;
+                };
+                let inputs = comp.component.inputs();
+                if inputs.expecting() > 1 {
+                    error!(expecting = inputs.expecting(), "sending input to a component that doesn't expect one input");
+                    return None;
+                }
+                if !inputs.can_take(ch.channel.as_deref(), Some(&*comp.component)) {
+                    match &inputs {
+                        Inputs::Named(v) | Inputs::MinTree(v) | Inputs::FullTree(v) => {
+                            if let Some(c) = &ch.channel {
+                                error!(component = &*ch.component, channel = &**c, %id, known_expected = ?v, "component can't take input on the given channel");
+                            } else {
+                                error!(component = &*ch.component, %id, "component can't take input on the primary channel because it expects inputs on named channels");
+                            }
+                        }
+                        Inputs::Primary => {
+                            error!(component = &*ch.component, c = ch.channel.as_deref().unwrap(), %id, "component can't take input on a named channel because it expects inputs on the primary channel");
+                        }
+                    }
+                    return None;
+                }
+                Some(id)
+            } else {
+                error!(name = &*ch.component, "couldn't find a component with the given name");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Build a camera and get it ready to read frames.
+fn start_camera(
+    name: String,
+    config: &CameraConfig,
+) -> std::io::Result<(FullCameraMetadata, FrameQuerier)> {
+    let (meta, querier) = config.build_camera(name)?.split();
+    let querier = FrameQuerier::new(querier, &meta)
+        .inspect_err(|err| error!(%err, "failed to start background capture"))?;
+    Ok((meta, querier))
+}
+
+/// A camera from the config file, which may or may not have started.
+struct LoadedCamera {
+    name: String,
+    config: CameraConfig,
+    targets: Vec<RunnerComponentId>,
+    started: Option<(FullCameraMetadata, FrameQuerier)>,
+}
+
+/// State shared between all of the capture threads.
+#[derive(Clone, Copy)]
+struct Shared<'a> {
+    runner: &'a PipelineRunner,
+    pool: &'a rayon::ThreadPool,
+    max_running: usize,
+}
+
+/// Read frames from a camera and run the pipeline on them, until the camera runs out of frames or disconnects, or `stop` is set.
+///
+/// This returns [`CameraRun::Finished`] if the camera shouldn't be started again, and [`CameraRun::Stopped`] if it disconnected or was
+/// stopped.
+fn camera_loop(
+    shared: Shared<'_>,
+    meta: &FullCameraMetadata,
+    querier: &mut FrameQuerier,
+    next: &[RunnerComponentId],
+    stop: &AtomicBool,
+) -> CameraRun {
+    shared.pool.in_place_scope(|rscope| {
+        while !stop.load(Ordering::Relaxed) {
+            if let Err(err) = querier.load_frame(meta) {
+                match err.kind() {
+                    std::io::ErrorKind::UnexpectedEof => {
+                        info!("camera has no more frames");
+                        return CameraRun::Finished;
+                    }
+                    std::io::ErrorKind::NotConnected => {
+                        error!("camera disconnected");
+                        return CameraRun::Stopped;
+                    }
+                    // the querier already waited according to the reconnect policy
+                    _ => continue,
+                }
+            }
+            let arg = ComponentArgs::single(querier.get_frame().into_static());
+            let context = Arc::new(FrameContext::new(meta, querier));
+            for id in next {
+                let res = shared.runner.run(
+                    RunParams::new(*id)
+                        .with_args(arg.clone())
+                        .with_context(context.clone())
+                        .with_max_running(shared.max_running),
+                    rscope,
+                );
+                if !check_run(res) {
+                    return CameraRun::Finished;
+                }
+            }
+        }
+        info!("stopping camera");
+        CameraRun::Stopped
+    })
+}
+
+/// Spawn a thread to read from a camera.
+fn spawn_camera<'scope>(
+    tscope: &'scope Scope<'scope, '_>,
+    shared: Shared<'scope>,
+    meta: FullCameraMetadata,
+    mut querier: FrameQuerier,
+    next: Vec<RunnerComponentId>,
+    stop: Arc<AtomicBool>,
+) -> Option<ScopedJoinHandle<'scope, CameraRun>> {
+    let builder = std::thread::Builder::new().name(format!("camera-{}", meta.name));
+    builder
+        .spawn_scoped(tscope, move || {
+            camera_loop(shared, &meta, &mut querier, &next, &stop)
+        })
+        .inspect_err(|err| error!(%err, "failed to spawn thread"))
+        .ok()
+}
+
+/// Read sets of frames from a camera group and run the pipeline on them, until one of the cameras runs out of frames or disconnects.
+fn group_loop(
+    shared: Shared<'_>,
+    meta: &GroupMetadata,
+    querier: &mut GroupQuerier,
+    next: &[(RunnerComponentId, bool)],
+) {
+    shared.pool.in_place_scope(|rscope| {
+        loop {
+            if let Err(err) = querier.load_frames(meta) {
+                match err.kind() {
+                    std::io::ErrorKind::UnexpectedEof => {
+                        info!("group has no more frames");
+                        return;
+                    }
+                    std::io::ErrorKind::NotConnected => {
+                        error!("camera in group disconnected");
+                        return;
+                    }
+                    _ => continue,
+                }
+            }
+            // the frames are copied once for each way that the targets take them, and shared between all of those targets
+            let list = next.iter().any(|&(_, named)| !named).then(|| {
+                Arc::new(
+                    querier
+                        .frames()
+                        .map(Buffer::into_static)
+                        .collect::<Vec<_>>(),
+                )
+            });
+            let inputs = next.iter().any(|&(_, named)| named).then(|| {
+                meta.cameras
+                    .iter()
+                    .zip(querier.frames())
+                    .map(|(cam, frame)| {
+                        (&*cam.name, Arc::new(frame.into_static()) as Arc<dyn Data>)
+                    })
+                    .collect::<HashMap<_, _>>()
+            });
+            let context = Arc::new(GroupContext::new(meta, querier));
+            for &(id, named) in next {
+                let args = if named && let Some(inputs) = &inputs {
+                    match shared.runner.pack_args(id, inputs) {
+                        Ok(args) => args,
+                        Err(err) => {
+                            error!(%err, %id, "failed to pack group frames, skipping this run");
+                            continue;
+                        }
+                    }
+                } else if let Some(list) = &list {
+                    ComponentArgs::single(list.clone())
+                } else {
+                    continue;
+                };
+                let res = shared.runner.run(
+                    RunParams::new(id)
+                        .with_args(args)
+                        .with_context(context.clone())
+                        .with_max_running(shared.max_running),
+                    rscope,
+                );
+                if !check_run(res) {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// A camera that's started and stopped as its device is plugged in and unplugged.
+struct HotplugCamera<'scope> {
+    name: String,
+    config: CameraConfig,
+    targets: Vec<RunnerComponentId>,
+    running: Option<(Arc<AtomicBool>, ScopedJoinHandle<'scope, CameraRun>)>,
+    state: HotplugState,
+}
+impl HotplugCamera<'_> {
+    /// Check on the camera's thread, joining it if it's finished.
+    fn run_state(&mut self) -> CameraRun {
+        match self.running.take_if(|(_, handle)| handle.is_finished()) {
+            Some((_, handle)) => handle.join().unwrap_or_else(|_| {
+                error!("camera thread panicked");
+                CameraRun::Finished
+            }),
+            None if self.running.is_some() => CameraRun::Running,
+            None => CameraRun::Stopped,
+        }
+    }
+}
+
+/// Poll for devices being plugged in and unplugged, and start and stop the cameras that read from them.
+///
+/// This returns once all of the cameras are finished for good, like when they run out of frames.
+fn watch_hotplug<'scope>(
+    tscope: &'scope Scope<'scope, '_>,
+    shared: Shared<'scope>,
+    mut cameras: Vec<HotplugCamera<'scope>>,
+    interval: Duration,
+) {
+    let mut watcher = DeviceWatcher::v4l();
+    while !cameras.is_empty() {
+        std::thread::sleep(interval);
+        let changes = watcher.poll();
+        for path in &changes.added {
+            info!(path = %path.display(), "device plugged in");
+        }
+        for path in &changes.removed {
+            info!(path = %path.display(), "device unplugged");
+        }
+        cameras.retain_mut(|camera| {
+            let _guard = error_span!("hotplug", name = camera.name).entered();
+            let present = camera.config.factory.device_present().unwrap_or(true);
+            let run = camera.run_state();
+            match camera.state.update(present, !changes.added.is_empty(), run) {
+                HotplugAction::Wait => {}
+                HotplugAction::Stop => {
+                    info!("camera was unplugged, stopping it");
+                    if let Some((stop, _)) = &camera.running {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+                }
+                HotplugAction::Forget => {
+                    info!("camera finished, no longer watching it");
+                    return false;
+                }
+                HotplugAction::Start => match start_camera(camera.name.clone(), &camera.config) {
+                    Ok((meta, querier)) => {
+                        info!("camera was plugged in, starting it");
+                        let stop = Arc::new(AtomicBool::new(false));
+                        camera.running = spawn_camera(
+                            tscope,
+                            shared,
+                            meta,
+                            querier,
+                            camera.targets.clone(),
+                            stop.clone(),
+                        )
+                        .map(|handle| (stop, handle));
+                        camera.state.started();
+                    }
+                    Err(err) => {
+                        if camera.state.start_failed() {
+                            error!(%err, "failed to start camera");
+                        } else {
+                            debug!(%err, "failed to start camera");
+                        }
+                    }
+                },
+            }
+            true
+        });
+    }
+}
+
 fn main() {
     let args = Cli::parse();
     let startup_time =
@@ -189,13 +526,22 @@ fn main() {
 
     info!(path = path.as_deref(), "starting logging at {startup_time}");
 
-    let config_file = match std::fs::read(&args.config) {
+    if let Some(Command::Calibrate(cal)) = args.command {
+        calibrate::run(cal);
+        return;
+    }
+    let config_path = args
+        .config
+        .as_ref()
+        .expect("a config file is required without a subcommand");
+
+    let config_file = match std::fs::read(config_path) {
         Ok(file) => {
-            info!(path = ?args.config, "loaded config file");
+            info!(path = ?config_path, "loaded config file");
             file
         }
         Err(err) => {
-            error!(path = ?args.config, %err, "failed to load config file");
+            error!(path = ?config_path, %err, "failed to load config file");
             exit(2);
         }
     };
@@ -203,6 +549,7 @@ fn main() {
         Ok(config) => {
             info!(
                 cameras = config.cameras.len(),
+                groups = config.groups.len(),
                 components = config.components.0.len(),
                 "loaded config file"
             );
@@ -279,67 +626,98 @@ fn main() {
 
     info!(cameras = ?config.cameras.keys().collect::<Vec<_>>(), "loading cameras");
 
+    let hotplug = config.run.hotplug;
     let mut cameras = config
         .cameras
         .into_iter()
-        .filter_map(|(name, mut config)| {
+        .map(|(name, mut config)| {
             let _guard = error_span!("load", name).entered();
             debug!("loading camera");
-            match config.camera.build_camera(name) {
-                Ok(camera) => {
-                    debug!("loaded camera");
-                    config.outputs.extend(config.output);
-                    let targets = config
-                        .outputs
-                        .into_iter()
-                        .filter_map(|ch| {
-                            let opt = runner.lookup.get(&ch.component);
-                            if let Some(&id) = opt {
-                                debug!(name = &*ch.component, %id, "found component to send frames to");
-                                let Some(comp) = runner.component(id) else {
-                                    error!("lookup table points to a nonexistent component");
-                                    return None;
-                                };
-                                let inputs = comp.component.inputs();
-                                if inputs.expecting() > 1 {
-                                    error!(expecting = inputs.expecting(), "sending input to a component that doesn't expect one input");
-                                    return None;
-                                }
-                                if !inputs.can_take(ch.channel.as_deref(), Some(&*comp.component)) {
-                                    match &inputs {
-                                        Inputs::Named(v) | Inputs::MinTree(v) | Inputs::FullTree(v) => {
-                                            if let Some(c) = &ch.channel {
-                                                error!(component = &*ch.component, channel = &**c, %id, known_expected = ?v, "component can't take input on the given channel");
-                                            } else {
-                                                error!(component = &*ch.component, %id, "component can't take input on the primary channel because it expects inputs on named channels");
-                                            }
-                                        }
-                                        Inputs::Primary => {
-                                            error!(component = &*ch.component, c = ch.channel.as_deref().unwrap(), %id, "component can't take input on a named channel because it expects inputs on the primary channel");
-                                        }
-                                    }
-                                    return None;
-                                }
-                                Some((id, ch.channel))
-                            } else {
-                                error!(name = &*ch.component, "couldn't find a component with the given name");
-                                None
-                            }
-                        })
-                        .collect::<Vec<_>>();
-                    Some((camera.split(), targets))
+            config.outputs.extend(config.output);
+            let targets = camera_targets(config.outputs, &runner);
+            let started = start_camera(name.clone(), &config.camera)
+                .inspect(|_| debug!("loaded camera"))
+                .inspect_err(|err| error!(%err, "failed to load camera"))
+                .ok();
+            if started.is_none() && hotplug && config.camera.factory.device_present().is_some() {
+                info!("camera will be started when it's plugged in");
+            }
+            LoadedCamera {
+                name,
+                config: config.camera,
+                targets,
+                started,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let groups = config
+        .groups
+        .into_iter()
+        .filter_map(|(name, mut config)| {
+            let _guard = error_span!("group", name).entered();
+            debug!("loading group");
+            let members = &config.group.cameras;
+            if members.is_empty() {
+                error!("group doesn't have any cameras");
+                return None;
+            }
+            for (n, cam) in members.iter().enumerate() {
+                if members[..n].contains(cam) {
+                    error!(camera = cam, "camera is listed more than once in the group");
+                    return None;
                 }
-                Err(err) => {
-                    error!(%err, "failed to load camera");
-                    None
+                if !cameras.iter().any(|c| c.name == *cam && c.started.is_some()) {
+                    error!(camera = cam, "couldn't find a loaded camera for the group");
+                    return None;
                 }
             }
+            let (metas, queriers) = members
+                .iter()
+                .map(|cam| {
+                    let idx = cameras.iter().position(|c| c.name == *cam).unwrap();
+                    let camera = cameras.swap_remove(idx);
+                    if !camera.targets.is_empty() {
+                        warn!(camera = cam, "camera is in a group, so its own outputs will be ignored");
+                    }
+                    camera.started.unwrap()
+                })
+                .unzip();
+            config.outputs.extend(config.output);
+            let targets = config
+                .outputs
+                .into_iter()
+                .filter_map(|name| {
+                    let Some(&id) = runner.lookup.get(&*name) else {
+                        error!(name, "couldn't find a component with the given name");
+                        return None;
+                    };
+                    let Some(comp) = runner.component(id) else {
+                        error!("lookup table points to a nonexistent component");
+                        return None;
+                    };
+                    match comp.component.inputs() {
+                        Inputs::Primary => Some((id, false)),
+                        Inputs::Named(v) | Inputs::MinTree(v) | Inputs::FullTree(v) => {
+                            if let Some(ch) = v.iter().find(|ch| !members.iter().any(|c| c == *ch)) {
+                                error!(component = name, channel = &**ch, %id, "component expects an input that isn't a camera in the group");
+                                return None;
+                            }
+                            Some((id, true))
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let meta = config.group.metadata(name, metas);
+            debug!("loaded group");
+            Some((meta, GroupQuerier::new(queriers), targets))
         })
         .collect::<Vec<_>>();
 
-    info!(cameras = ?cameras.iter().map(|c| &c.0.0.name).collect::<Vec<_>>(), "loaded cameras");
-
-    let runner = &runner;
+    info!(cameras = ?cameras.iter().filter(|c| c.started.is_some()).map(|c| &c.name).collect::<Vec<_>>(), "loaded cameras");
+    if !groups.is_empty() {
+        info!(groups = ?groups.iter().map(|g| &g.0.name).collect::<Vec<_>>(), "loaded groups");
+    }
 
     let mut builder = rayon::ThreadPoolBuilder::new();
     if let Some(threads) = args.threads.or(config.run.num_threads) {
@@ -351,49 +729,92 @@ fn main() {
         exit(101);
     });
 
-    pool.in_place_scope(|rscope| {
-        std::thread::scope(|tscope| {
-            for &mut ((ref meta, ref mut querier), ref next) in cameras.iter_mut() {
-                let builder = std::thread::Builder::new().name(format!("camera-{}", meta.name));
-                let res = builder.spawn_scoped(tscope, move || {
-                    use viking_vision::pipeline::runner::{
-                        RunError, RunErrorCause, RunErrorWithParams,
-                    };
-                    loop {
-                        if querier.load_frame(meta).is_err() {
-                            continue;
-                        }
-                        let arg = ComponentArgs::single(querier.get_frame().into_static());
-                        for (id, _chan) in next {
-                            let res = runner.run(
-                                RunParams::new(*id)
-                                    .with_args(arg.clone())
-                                    .with_context(meta)
-                                    .with_max_running(config.run.max_running),
-                                rscope,
-                            );
-                            if let Err(RunError::WithParams(RunErrorWithParams { cause, .. })) = res
-                            {
-                                match cause {
-                                    RunErrorCause::ArgsMismatch { expected, given } => {
-                                        error!(expected, given, "argument length mismatch");
-                                        return;
-                                    }
-                                    RunErrorCause::NoComponent(id) => {
-                                        error!(%id, "missing component");
-                                        return;
-                                    }
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
-                });
-                if let Err(err) = res {
-                    error!(camera = meta.name, %err, "failed to spawn thread");
+    let shared = Shared {
+        runner: &runner,
+        pool: &pool,
+        max_running: config.run.max_running,
+    };
+
+    std::thread::scope(|tscope| {
+        let mut watched = Vec::new();
+        // the threads that have to finish before we can exit
+        let mut camera_threads = Vec::new();
+        let mut threads = Vec::new();
+        for camera in cameras {
+            let supervise = hotplug && camera.config.factory.device_present().is_some();
+            let running = camera.started.and_then(|(meta, querier)| {
+                let stop = Arc::new(AtomicBool::new(false));
+                spawn_camera(
+                    tscope,
+                    shared,
+                    meta,
+                    querier,
+                    camera.targets.clone(),
+                    stop.clone(),
+                )
+                .map(|handle| (stop, handle))
+            });
+            if supervise {
+                let mut state = HotplugState::default();
+                if running.is_none() {
+                    // the error was already logged when it was loaded
+                    state.start_failed();
                 }
+                watched.push(HotplugCamera {
+                    name: camera.name,
+                    config: camera.config,
+                    targets: camera.targets,
+                    running,
+                    state,
+                });
+            } else if let Some((_, handle)) = running {
+                camera_threads.push(handle);
             }
-            handler.run();
-        });
+        }
+        for (meta, mut querier, next) in groups {
+            let builder = std::thread::Builder::new().name(format!("group-{}", meta.name));
+            let res = builder.spawn_scoped(tscope, move || {
+                group_loop(shared, &meta, &mut querier, &next);
+            });
+            match res {
+                Ok(handle) => threads.push(handle),
+                Err(err) => error!(%err, "failed to spawn thread"),
+            }
+        }
+        if !watched.is_empty() {
+            info!(cameras = ?watched.iter().map(|c| &c.name).collect::<Vec<_>>(), "watching for cameras being plugged in and unplugged");
+            let interval = config.run.hotplug_interval;
+            let res = std::thread::Builder::new()
+                .name("hotplug".to_string())
+                .spawn_scoped(tscope, move || {
+                    watch_hotplug(tscope, shared, watched, interval)
+                });
+            match res {
+                Ok(handle) => threads.push(handle),
+                Err(err) => error!(%err, "failed to spawn hotplug thread"),
+            }
+        }
+        let res = std::thread::Builder::new()
+            .name("shutdown".to_string())
+            .spawn_scoped(tscope, move || {
+                for handle in camera_threads {
+                    if handle.join().is_err() {
+                        error!("camera thread panicked");
+                    }
+                }
+                for handle in threads {
+                    if handle.join().is_err() {
+                        error!("thread panicked");
+                    }
+                }
+                info!("all cameras finished, shutting down");
+                if let Some(sender) = GLOBAL_SENDER.get() {
+                    sender.shutdown();
+                }
+            });
+        if let Err(err) = res {
+            error!(%err, "failed to spawn shutdown thread");
+        }
+        handler.run();
     });
 }
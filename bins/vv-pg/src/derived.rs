@@ -31,6 +31,8 @@ enum Transform {
     Resize {
         width: u32,
         height: u32,
+        #[serde(default)]
+        mode: Interpolation,
     },
     BoxBlur {
         format: PixelFormat,
@@ -118,8 +120,12 @@ impl DerivedFrame {
             Transform::Swizzle(ref s) => {
                 swizzle(from, &mut self.frame, s);
             }
-            Transform::Resize { width, height } => {
-                resize(from, &mut self.frame, width, height);
+            Transform::Resize {
+                width,
+                height,
+                mode,
+            } => {
+                resize(from, &mut self.frame, width, height, mode);
             }
             Transform::BoxBlur {
                 format,
@@ -249,8 +255,12 @@ impl DerivedFrame {
             Transform::Swizzle(ref s) => {
                 let _ = write!(self.title, "Swizzle: {s:?}");
             }
-            Transform::Resize { width, height } => {
-                let _ = write!(self.title, "Resize: {width}x{height}");
+            Transform::Resize {
+                width,
+                height,
+                mode,
+            } => {
+                let _ = write!(self.title, "Resize: {width}x{height} ({mode:?})");
             }
             Transform::BoxBlur {
                 format,
@@ -353,6 +363,7 @@ pub fn add_button(ui: &mut egui::Ui, title: &str, id: egui::Id, next: &mut Vec<D
                     Transform::Resize {
                         width: 256,
                         height: 256,
+                        mode: Interpolation::Nearest,
                     },
                     id,
                 )
@@ -636,13 +647,29 @@ pub fn render_frame(ctx: &egui::Context, prev: &str) -> impl Fn(&mut DerivedFram
                             s.push(0);
                         }
                     }
-                    Transform::Resize { width, height } => {
+                    Transform::Resize {
+                        width,
+                        height,
+                        mode,
+                    } => {
                         changed |= ui
                             .add(egui::Slider::new(width, 1..=1024).text("Width"))
                             .changed();
                         changed |= ui
                             .add(egui::Slider::new(height, 1..=1024).text("Height"))
                             .changed();
+                        egui::ComboBox::new("Resize Mode", "Mode")
+                            .selected_text(format!("{mode:?}"))
+                            .show_ui(ui, |ui| {
+                                for m in [
+                                    Interpolation::Nearest,
+                                    Interpolation::Bilinear,
+                                    Interpolation::Area,
+                                ] {
+                                    changed |=
+                                        ui.selectable_value(mode, m, format!("{m:?}")).changed();
+                                }
+                            });
                     }
                     Transform::BoxBlur {
                         format,
@@ -106,6 +106,53 @@ impl TagFamily {
     }
 }
 
+/// A tag rendered with one pixel per cell, as returned by [`TagFamily::render`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagImage {
+    /// The rendered tag in the [`LUMA`](PixelFormat::LUMA) format, including the white border around it.
+    pub image: Buffer<'static>,
+    /// The width of the tag in cells, measured at the outer edge of the black border.
+    ///
+    /// This is the part of the tag that the tag size refers to in pose estimation.
+    pub width_at_border: u32,
+}
+impl TagFamily {
+    /// Render the tag with the given ID, or return `None` if the family doesn't have a tag with that ID.
+    pub fn render(self, id: u32) -> Option<TagImage> {
+        unsafe {
+            let fam = self.create()();
+            let tag = (id < (*fam).ncodes).then(|| {
+                let img = apriltag_to_image(fam, id as _);
+                let image_u8 {
+                    width,
+                    height,
+                    stride,
+                    buf,
+                } = *img;
+                let mut data = Vec::with_capacity(width as usize * height as usize);
+                for y in 0..height as usize {
+                    data.extend_from_slice(std::slice::from_raw_parts(
+                        buf.add(y * stride as usize),
+                        width as usize,
+                    ));
+                }
+                image_u8_destroy(img);
+                TagImage {
+                    image: Buffer {
+                        width: width as _,
+                        height: height as _,
+                        format: PixelFormat::LUMA,
+                        data: data.into(),
+                    },
+                    width_at_border: (*fam).width_at_border as _,
+                }
+            });
+            self.destroy()(fam);
+            tag
+        }
+    }
+}
+
 #[derive(Debug, Clone, Error)]
 pub enum ParseFamilyError {
     #[error("Empty family name")]
@@ -260,3 +260,29 @@ macro_rules! generate_test {
 generate_test!(test_img_1, "33369213973_9d9bb4cc96_c");
 generate_test!(test_img_2, "34085369442_304b6bafd9_c");
 generate_test!(test_img_3, "34139872896_defdb2f8d9_c" ignore);
+
+#[test]
+fn render_and_detect() {
+    assert!(TagFamily::tag36h11.render(u32::MAX).is_none());
+    let TagImage {
+        image,
+        width_at_border,
+    } = TagFamily::tag36h11.render(7).expect("tag 7 should exist");
+    assert_eq!(width_at_border, 8);
+    assert_eq!((image.width, image.height), (10, 10));
+    // scale each cell up and leave a margin, since the detector can't find tags that are too small
+    let scale = 10;
+    let margin = 20;
+    let size = image.width * scale + margin * 2;
+    let mut frame = Buffer::monochrome(size, size, PixelFormat::LUMA, &[255]);
+    for y in 0..image.height * scale {
+        for x in 0..image.width * scale {
+            let px = image.pixel(x / scale, y / scale).unwrap();
+            frame.set_pixel(x + margin, y + margin, px);
+        }
+    }
+    let mut detector = Detector::new();
+    detector.add_family(TagFamily::tag36h11);
+    let ids = detector.detect(frame).map(|d| d.id()).collect::<Vec<_>>();
+    assert_eq!(ids, [7]);
+}
@@ -0,0 +1,286 @@
+//! Capturing frames on a dedicated thread.
+//!
+//! Reading from a camera blocks until the driver has a frame ready, so if frames are read and processed on the same thread, slow processing
+//! means that the frames we process are stale. A [`BackgroundQuerier`] keeps reading on its own thread, and hands off frames according to a
+//! [`BackgroundPolicy`].
+
+#[cfg(feature = "supply")]
+use super::FrameProvider;
+use super::health::{CameraHealth, HealthMonitor};
+use super::{CameraMetadata, CameraQuerier, FrameTimestamp};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{debug, error, info, info_span};
+use vv_vision::buffer::Buffer;
+
+/// The least time to wait after a failed read, in case the reconnect policy doesn't wait at all.
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// How frames captured in the background are handed off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BackgroundPolicy {
+    /// Only keep the newest frame, dropping any that weren't processed in time.
+    Latest,
+    /// Keep up to the given number of frames, dropping the oldest ones once it's full.
+    Queue(NonZeroUsize),
+}
+impl BackgroundPolicy {
+    /// The maximum number of frames waiting to be processed.
+    pub const fn depth(&self) -> usize {
+        match self {
+            Self::Latest => 1,
+            Self::Queue(depth) => depth.get(),
+        }
+    }
+}
+
+/// A frame that's waiting to be processed.
+#[derive(Debug)]
+struct QueuedFrame {
+    buffer: Buffer<'static>,
+    timestamp: Option<FrameTimestamp>,
+    #[cfg(feature = "supply")]
+    context: Option<Arc<dyn FrameProvider>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    queue: VecDeque<QueuedFrame>,
+    /// Buffers that have already been processed, to be reused.
+    free: Vec<Buffer<'static>>,
+    /// An error that stopped the capture thread.
+    error: Option<io::Error>,
+    /// An error from a failed read that hasn't been reported yet, which is cleared once a frame is read.
+    failure: Option<io::Error>,
+    /// Set when the receiving side is dropped, to stop the capture thread.
+    closed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    ready: Condvar,
+}
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Read frames on the capture thread until the receiving side is dropped or the camera runs out of frames.
+fn capture_loop(mut querier: CameraQuerier, meta: CameraMetadata, depth: usize, shared: &Shared) {
+    loop {
+        let res = querier.load_frame(&meta);
+        let mut state = shared.lock();
+        if state.closed {
+            return;
+        }
+        if let Err(err) = res {
+            if matches!(
+                err.kind(),
+                io::ErrorKind::UnexpectedEof | io::ErrorKind::NotConnected
+            ) {
+                state.error = Some(err);
+                drop(state);
+                shared.ready.notify_all();
+                return;
+            }
+            state.failure = Some(err);
+            shared.ready.notify_all();
+            // wait before trying again, unless we're closed in the meantime
+            let (state, _) = shared
+                .ready
+                .wait_timeout_while(state, RETRY_DELAY, |state| !state.closed)
+                .unwrap_or_else(PoisonError::into_inner);
+            if state.closed {
+                return;
+            }
+            continue;
+        }
+        let mut buffer = state.free.pop().unwrap_or_else(Buffer::empty_rgb);
+        drop(state);
+        buffer.copy_from(querier.get_frame());
+        let mut state = shared.lock();
+        while state.queue.len() >= depth
+            && let Some(old) = state.queue.pop_front()
+        {
+            debug!("dropping a frame that wasn't processed in time");
+            state.free.push(old.buffer);
+        }
+        state.failure = None;
+        state.queue.push_back(QueuedFrame {
+            buffer,
+            timestamp: querier.frame_timestamp(),
+            #[cfg(feature = "supply")]
+            context: querier.frame_context().cloned(),
+        });
+        drop(state);
+        shared.ready.notify_one();
+    }
+}
+
+/// A [`CameraQuerier`] that reads frames on a dedicated thread.
+///
+/// Frames are copied into reused buffers, so the only allocations happen while the queue is filling up. Dropping this stops the capture
+/// thread and waits for it to finish, which can take up to one read.
+#[derive(Debug)]
+pub struct BackgroundQuerier {
+    shared: Arc<Shared>,
+    current: Option<QueuedFrame>,
+    health: HealthMonitor,
+    thread: Option<JoinHandle<()>>,
+}
+impl BackgroundQuerier {
+    /// Start reading from a querier on a new thread.
+    pub fn spawn(
+        querier: CameraQuerier,
+        meta: CameraMetadata,
+        policy: BackgroundPolicy,
+    ) -> io::Result<Self> {
+        let shared = Arc::new(Shared::default());
+        let depth = policy.depth();
+        let thread_shared = shared.clone();
+        let health = querier.health_monitor();
+        let thread = std::thread::Builder::new()
+            .name(format!("capture-{}", meta.name))
+            .spawn(move || {
+                let _guard = info_span!("background capture", name = meta.name).entered();
+                info!(?policy, "starting background capture");
+                capture_loop(querier, meta, depth, &thread_shared);
+                debug!("stopping background capture");
+            })
+            .inspect_err(|err| error!(%err, "failed to spawn capture thread"))?;
+        Ok(Self {
+            shared,
+            current: None,
+            health,
+            thread: Some(thread),
+        })
+    }
+    /// Wait for the next frame.
+    ///
+    /// If the capture thread fails to read a frame before one is ready, this returns that error, so the caller can react to a failing
+    /// camera. If the capture thread stopped, either because the camera ran out of frames or gave up on reconnecting, this returns the
+    /// error that stopped it.
+    pub fn load_frame(&mut self) -> io::Result<()> {
+        let mut state = self.shared.lock();
+        let next = loop {
+            if let Some(next) = state.queue.pop_front() {
+                break next;
+            }
+            if let Some(err) = state.failure.take() {
+                return Err(err);
+            }
+            if let Some(err) = &state.error {
+                return Err(io::Error::new(err.kind(), err.to_string()));
+            }
+            state = self
+                .shared
+                .ready
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        };
+        if let Some(old) = self.current.replace(next) {
+            state.free.push(old.buffer);
+        }
+        Ok(())
+    }
+    /// Get the last frame read.
+    ///
+    /// There should've been a call to [`Self::load_frame`] first.
+    pub fn get_frame(&self) -> Buffer<'_> {
+        self.current
+            .as_ref()
+            .map_or_else(Buffer::empty_rgb, |f| f.buffer.borrow())
+    }
+    /// Get the capture time and sequence number of the last frame read.
+    pub fn frame_timestamp(&self) -> Option<FrameTimestamp> {
+        self.current.as_ref().and_then(|f| f.timestamp)
+    }
+    /// Get the extra context that the camera gave for the last frame read.
+    #[cfg(feature = "supply")]
+    pub fn frame_context(&self) -> Option<&Arc<dyn FrameProvider>> {
+        self.current.as_ref().and_then(|f| f.context.as_ref())
+    }
+    /// Get a handle to the camera's health that can be checked from other threads.
+    pub fn health_monitor(&self) -> HealthMonitor {
+        self.health.clone()
+    }
+}
+impl Drop for BackgroundQuerier {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.ready.notify_all();
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            error!("capture thread panicked");
+        }
+    }
+}
+
+/// A querier that reads frames either directly or on a background thread, depending on the camera's configuration.
+#[derive(Debug)]
+pub enum FrameQuerier {
+    Direct(CameraQuerier),
+    Background(BackgroundQuerier),
+}
+impl FrameQuerier {
+    /// Create a querier, spawning a background thread if the metadata requests one.
+    pub fn new(querier: CameraQuerier, meta: &CameraMetadata) -> io::Result<Self> {
+        match meta.background {
+            Some(policy) => {
+                BackgroundQuerier::spawn(querier, meta.clone(), policy).map(Self::Background)
+            }
+            None => Ok(Self::Direct(querier)),
+        }
+    }
+    /// Read a frame, or wait for the next one if capturing in the background.
+    pub fn load_frame(&mut self, meta: &CameraMetadata) -> io::Result<()> {
+        match self {
+            Self::Direct(q) => q.load_frame(meta),
+            Self::Background(q) => q.load_frame(),
+        }
+    }
+    /// Get the last frame read.
+    pub fn get_frame(&self) -> Buffer<'_> {
+        match self {
+            Self::Direct(q) => q.get_frame(),
+            Self::Background(q) => q.get_frame(),
+        }
+    }
+    /// Get the capture time and sequence number of the last frame read.
+    pub fn frame_timestamp(&self) -> Option<FrameTimestamp> {
+        match self {
+            Self::Direct(q) => q.frame_timestamp(),
+            Self::Background(q) => q.frame_timestamp(),
+        }
+    }
+    /// Get the extra context that the camera gave for the last frame read.
+    #[cfg(feature = "supply")]
+    pub fn frame_context(&self) -> Option<&Arc<dyn FrameProvider>> {
+        match self {
+            Self::Direct(q) => q.frame_context(),
+            Self::Background(q) => q.frame_context(),
+        }
+    }
+    /// Get a snapshot of the camera's current health.
+    pub fn health(&self) -> CameraHealth {
+        self.health_monitor().get()
+    }
+    /// Get a handle to the camera's health that can be checked from other threads.
+    pub fn health_monitor(&self) -> HealthMonitor {
+        match self {
+            Self::Direct(q) => q.health_monitor(),
+            Self::Background(q) => q.health_monitor(),
+        }
+    }
+}
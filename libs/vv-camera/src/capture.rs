@@ -1,22 +1,25 @@
 #![cfg(feature = "v4l")]
 
-use super::{CameraFactory, CameraImpl};
+use super::{CameraFactory, CameraImpl, FrameMetadata};
 use polonius_the_crab::{ForLt, Placeholder, PoloniusResult, polonius};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt::{self, Debug, Formatter};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock};
-use tracing::{error, info};
-use v4l::buffer::Type;
-use v4l::control::{Control, Value};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, error, info};
+use v4l::buffer::{Flags as BufferFlags, Metadata, Type};
+use v4l::control::{Control, Description, Flags as ControlFlags, Type as ControlType, Value};
 use v4l::io::traits::CaptureStream;
 use v4l::prelude::MmapStream;
 use v4l::video::Capture;
 use v4l::video::capture::Parameters;
 use v4l::{Device, FourCC, Fraction};
-use vv_vision::buffer::{Buffer, PixelFormat};
+use vv_vision::buffer::raw::RawFormat;
+use vv_vision::buffer::{Buffer, PixelFormat, UnrecognizedFourCC};
 use zune_jpeg::{JpegDecoder, zune_core::options::DecoderOptions};
 
 #[cfg(feature = "serde")]
@@ -142,20 +145,154 @@ pub mod control_ids {
     pub const AUTO_EXPOSURE: u32 = 0x009a0901;
     pub const EXPOSURE_TIME_ABSOLUTE: u32 = 0x009a0902;
     pub const EXPOSURE_DYNAMIC_FRAMERATE: u32 = 0x009a0903;
+
+    /// Look up a well-known control by its name, in snake case.
+    pub fn from_name(name: &str) -> Option<u32> {
+        Some(match name {
+            "brightness" => BRIGHTNESS,
+            "contrast" => CONTRAST,
+            "saturation" => SATURATION,
+            "white_balance_automatic" => WHITE_BALANCE_AUTOMATIC,
+            "gain" => GAIN,
+            "power_line_frequency" => POWER_LINE_FREQUENCY,
+            "white_balance_temperature" => WHITE_BALANCE_TEMPERATURE,
+            "sharpness" => SHARPNESS,
+            "backlight_compensation" => BACKLIGHT_COMPENSATION,
+            "auto_exposure" => AUTO_EXPOSURE,
+            "exposure_time_absolute" => EXPOSURE_TIME_ABSOLUTE,
+            "exposure_dynamic_framerate" => EXPOSURE_DYNAMIC_FRAMERATE,
+            _ => return None,
+        })
+    }
+}
+
+/// A value to set a control to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum ControlValue {
+    /// An integer value, or the index of a menu item.
+    Integer(i64),
+    /// A boolean value.
+    Boolean(bool),
+    /// The name of a menu item.
+    Name(String),
+}
+
+/// Normalize a control's name to snake case, so "White Balance Temperature" becomes `white_balance_temperature`.
+pub(crate) fn normalize_control_name(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            out.push(c.to_ascii_lowercase());
+        } else if !out.is_empty() && !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    if out.ends_with('_') {
+        out.pop();
+    }
+    out
+}
+
+/// Find the description of a control, given either its name or its numeric ID.
+pub(crate) fn find_control<'a>(descs: &'a [Description], key: &str) -> Option<&'a Description> {
+    let id = if let Some(hex) = key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        key.parse().ok()
+    };
+    let id = id.or_else(|| control_ids::from_name(key));
+    if let Some(id) = id {
+        return descs.iter().find(|d| d.id == id);
+    }
+    let key = normalize_control_name(key);
+    descs
+        .iter()
+        .find(|d| normalize_control_name(&d.name) == key)
+}
+
+/// Check a value against a control's description, and convert it to a [`Value`].
+pub(crate) fn validate_control(desc: &Description, value: &ControlValue) -> Result<Value, String> {
+    if desc
+        .flags
+        .intersects(ControlFlags::READ_ONLY | ControlFlags::DISABLED)
+    {
+        return Err("control can't be written to".to_string());
+    }
+    let check_range = |v: i64| {
+        if v < desc.minimum || v > desc.maximum {
+            Err(format!(
+                "value {v} is out of range [{}, {}]",
+                desc.minimum, desc.maximum
+            ))
+        } else if desc.step > 1 && !((v - desc.minimum) as u64).is_multiple_of(desc.step) {
+            Err(format!(
+                "value {v} isn't a multiple of the step size {} from {}",
+                desc.step, desc.minimum
+            ))
+        } else {
+            Ok(v)
+        }
+    };
+    match (desc.typ, value) {
+        (ControlType::Integer | ControlType::Integer64, ControlValue::Integer(v)) => {
+            check_range(*v).map(Value::Integer)
+        }
+        (ControlType::Boolean, ControlValue::Boolean(v)) => Ok(Value::Boolean(*v)),
+        (ControlType::Boolean, ControlValue::Integer(v @ (0 | 1))) => Ok(Value::Boolean(*v == 1)),
+        (ControlType::Menu | ControlType::IntegerMenu, ControlValue::Integer(v)) => {
+            let v = check_range(*v)?;
+            let items = desc.items.as_deref().unwrap_or_default();
+            if items.iter().any(|(idx, _)| *idx as i64 == v) {
+                Ok(Value::Integer(v))
+            } else {
+                Err(format!("value {v} isn't a valid menu index"))
+            }
+        }
+        (ControlType::Menu | ControlType::IntegerMenu, ControlValue::Name(name)) => {
+            let key = normalize_control_name(name);
+            desc.items
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .find(|(_, item)| normalize_control_name(&item.to_string()) == key)
+                .map(|(idx, _)| Value::Integer(*idx as i64))
+                .ok_or_else(|| format!("{name:?} isn't an item in the menu"))
+        }
+        (typ, value) => Err(format!("can't set a {typ} control to {value:?}")),
+    }
 }
 
 #[cfg_attr(feature = "serde", typetag::serde)]
 pub trait CameraSource: Debug + Send + Sync {
-    fn resolve(&self) -> io::Result<Device>;
+    /// Find the path of the device, without opening it.
+    ///
+    /// This is used to check whether the device is connected, so it shouldn't log anything.
+    fn locate(&self) -> io::Result<PathBuf>;
+    /// Open the device.
+    fn resolve(&self) -> io::Result<Device> {
+        let path = self
+            .locate()
+            .inspect_err(|err| error!(%err, "failed to find v4l device"))?;
+        info!(path = %path.display(), "loading device");
+        Device::with_path(path)
+    }
 }
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct V4lPath(pub PathBuf);
 #[cfg_attr(feature = "serde", typetag::serde(name = "path"))]
 impl CameraSource for V4lPath {
-    fn resolve(&self) -> io::Result<Device> {
-        info!(path = %self.0.display(), "loading device");
-        Device::with_path(&self.0)
+    fn locate(&self) -> io::Result<PathBuf> {
+        if self.0.exists() {
+            Ok(self.0.clone())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no v4l device at {}", self.0.display()),
+            ))
+        }
     }
 }
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -163,9 +300,147 @@ impl CameraSource for V4lPath {
 pub struct V4lIndex(pub usize);
 #[cfg_attr(feature = "serde", typetag::serde(name = "index"))]
 impl CameraSource for V4lIndex {
-    fn resolve(&self) -> io::Result<Device> {
-        info!(index = self.0, "loading device");
-        Device::new(self.0)
+    fn locate(&self) -> io::Result<PathBuf> {
+        V4lPath(format!("/dev/video{}", self.0).into()).locate()
+    }
+}
+
+/// Where sysfs is mounted.
+const SYSFS: &str = "/sys";
+/// Where udev links devices by the port they're plugged into.
+const BY_PATH: &str = "/dev/v4l/by-path";
+
+/// Read a sysfs attribute, without the trailing newline.
+fn read_attr(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim_end().to_string())
+}
+
+/// Find the sysfs directories for all primary video nodes, sorted by their number, with sysfs mounted at `sysfs`.
+///
+/// Devices can expose additional nodes (typically for metadata) that share the same name and serial, but these have a nonzero index.
+fn primary_nodes(sysfs: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut nodes = Vec::new();
+    for entry in std::fs::read_dir(sysfs.join("class/video4linux"))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(num) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("video"))
+            .and_then(|n| n.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        if read_attr(&entry.path().join("index")).is_some_and(|i| i != "0") {
+            continue;
+        }
+        nodes.push((num, entry.path()));
+    }
+    nodes.sort_by_key(|n| n.0);
+    Ok(nodes.into_iter().map(|n| n.1).collect())
+}
+
+/// Get the only device in `matches`, or fail if there isn't exactly one.
+fn find_unique(kind: &str, value: &str, mut matches: Vec<PathBuf>) -> io::Result<PathBuf> {
+    match matches.len() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no v4l device with {kind} {value:?}"),
+        )),
+        1 => Ok(matches.swap_remove(0)),
+        _ => Err(io::Error::other(format!(
+            "multiple v4l devices with {kind} {value:?}: {matches:?}"
+        ))),
+    }
+}
+
+/// The device path for a node's sysfs directory.
+fn dev_path(node: &Path) -> PathBuf {
+    Path::new("/dev").join(node.file_name().unwrap_or_default())
+}
+
+/// Select a device by its card name, as shown by `v4l2-ctl --list-devices`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct V4lName(pub String);
+impl V4lName {
+    /// Find the device, with sysfs mounted at `sysfs`.
+    pub(crate) fn locate_in(&self, sysfs: &Path) -> io::Result<PathBuf> {
+        let matches = primary_nodes(sysfs)?
+            .into_iter()
+            .filter(|node| read_attr(&node.join("name")).is_some_and(|n| n == self.0))
+            .map(|node| dev_path(&node))
+            .collect();
+        find_unique("name", &self.0, matches)
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "name"))]
+impl CameraSource for V4lName {
+    fn locate(&self) -> io::Result<PathBuf> {
+        self.locate_in(Path::new(SYSFS))
+    }
+}
+/// Select a device by the serial number of the USB device it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct V4lSerial(pub String);
+impl V4lSerial {
+    /// Find the device, with sysfs mounted at `sysfs`.
+    pub(crate) fn locate_in(&self, sysfs: &Path) -> io::Result<PathBuf> {
+        let devices = sysfs.join("devices");
+        let matches = primary_nodes(sysfs)?
+            .into_iter()
+            .filter(|node| {
+                // the node's device is a USB interface, and the serial is on one of its parents
+                let Ok(dev) = std::fs::canonicalize(node.join("device")) else {
+                    return false;
+                };
+                dev.ancestors()
+                    .take_while(|p| p.starts_with(&devices))
+                    .find_map(|p| read_attr(&p.join("serial")))
+                    .is_some_and(|s| s == self.0)
+            })
+            .map(|node| dev_path(&node))
+            .collect();
+        find_unique("serial", &self.0, matches)
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "serial"))]
+impl CameraSource for V4lSerial {
+    fn locate(&self) -> io::Result<PathBuf> {
+        self.locate_in(Path::new(SYSFS))
+    }
+}
+/// Select a device by the physical port it's plugged into, as it appears in `/dev/v4l/by-path`.
+///
+/// This can be either the full name of the link (without the `-video-index0` suffix), or a suffix of it, like `usb-0:1.2:1.0`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct V4lPort(pub String);
+impl V4lPort {
+    /// Find the device, looking through the links in `by_path`.
+    pub(crate) fn locate_in(&self, by_path: &Path) -> io::Result<PathBuf> {
+        let suffix = format!("-{}", self.0);
+        let mut matches = Vec::new();
+        for entry in std::fs::read_dir(by_path)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let Some(port) = name.to_str().and_then(|n| n.strip_suffix("-video-index0")) else {
+                continue;
+            };
+            if port == self.0 || port.ends_with(&suffix) {
+                matches.push(std::fs::canonicalize(entry.path())?);
+            }
+        }
+        matches.sort();
+        find_unique("port", &self.0, matches)
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "port"))]
+impl CameraSource for V4lPort {
+    fn locate(&self) -> io::Result<PathBuf> {
+        self.locate_in(Path::new(BY_PATH))
     }
 }
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -173,8 +448,7 @@ impl CameraSource for V4lIndex {
 pub struct NoSource {}
 #[cfg_attr(feature = "serde", typetag::serde(name = "unknown"))]
 impl CameraSource for NoSource {
-    fn resolve(&self) -> io::Result<Device> {
-        error!("unknown source");
+    fn locate(&self) -> io::Result<PathBuf> {
         Err(io::Error::other("unknown source type"))
     }
 }
@@ -192,6 +466,9 @@ pub struct CaptureCameraConfig {
     #[cfg_attr(feature = "serde", serde(default))]
     pub decode_jpeg: bool,
     pub exposure: Option<i64>,
+    /// Additional controls to set, keyed by either their name or ID.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub controls: BTreeMap<String, ControlValue>,
     #[cfg_attr(feature = "serde", serde(with = "interval_serde", flatten))]
     pub interval: Option<Fraction>,
     #[cfg_attr(feature = "serde", serde(flatten))]
@@ -206,10 +483,45 @@ impl CameraFactory for CaptureCameraConfig {
             stream: None,
             device,
             frame: Buffer::empty_rgb(),
+            frame_meta: FrameMetadata::default(),
+            stride: 0,
         };
         cam.config_device()?;
         Ok(Box::new(cam))
     }
+    fn device_present(&self) -> Option<bool> {
+        Some(self.source.locate().is_ok())
+    }
+}
+
+/// Convert the metadata for a buffer from the driver.
+fn frame_metadata(meta: &Metadata) -> FrameMetadata {
+    let monotonic = meta.flags & BufferFlags::TIMESTAMP_MASK == BufferFlags::TIMESTAMP_MONOTONIC;
+    FrameMetadata {
+        timestamp: monotonic
+            .then(|| monotonic_to_system(meta.timestamp))
+            .flatten(),
+        sequence: Some(meta.sequence as u64),
+    }
+}
+
+/// Convert a timestamp on the monotonic clock, which V4L uses, to the system clock.
+fn monotonic_to_system(timestamp: v4l::Timestamp) -> Option<SystemTime> {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `now` is valid to write to
+    if unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) } != 0 {
+        return None;
+    }
+    let system_now = SystemTime::now();
+    let now = Duration::new(now.tv_sec.try_into().ok()?, now.tv_nsec.try_into().ok()?);
+    let then = Duration::new(
+        timestamp.sec.try_into().ok()?,
+        u32::try_from(timestamp.usec).ok()?.checked_mul(1000)?,
+    );
+    system_now.checked_sub(now.saturating_sub(then))
 }
 
 pub struct CaptureCamera {
@@ -217,6 +529,9 @@ pub struct CaptureCamera {
     pub device: Device,
     pub stream: Option<MmapStream<'static>>,
     pub frame: Buffer<'static>,
+    pub frame_meta: FrameMetadata,
+    /// The number of bytes in each row of the frame, as reported by the driver.
+    pub stride: u32,
 }
 impl CaptureCamera {
     pub fn from_device(device: Device) -> io::Result<Self> {
@@ -233,6 +548,7 @@ impl CaptureCamera {
             decode_jpeg: &format.fourcc.repr == b"MJPG",
             interval: None,
             exposure: None,
+            controls: BTreeMap::new(),
             source: NO_SOURCE.clone(),
         };
         Ok(Self {
@@ -240,6 +556,8 @@ impl CaptureCamera {
             device,
             stream: None,
             frame: Buffer::empty_rgb(),
+            frame_meta: FrameMetadata::default(),
+            stride: format.stride,
         })
     }
     /// Configure the device based on the configuration.
@@ -268,11 +586,13 @@ impl CaptureCamera {
                 value: Value::Boolean(true),
             })?;
         }
-        self.device.set_format(&v4l::Format::new(
+        self.apply_controls()?;
+        let format = self.device.set_format(&v4l::Format::new(
             self.config.width,
             self.config.height,
             self.config.fourcc,
         ))?;
+        self.stride = format.stride;
         let interval = self.interval_mut()?;
         self.device.set_params(&Parameters::new(interval))?;
         let fmt = self
@@ -284,6 +604,40 @@ impl CaptureCamera {
         self.config.decode_jpeg = &self.config.fourcc.repr == b"MJPG";
         Ok(())
     }
+    /// Validate and set the controls from the configuration.
+    ///
+    /// Menu and boolean controls are set first, since these tend to be automatic modes that would otherwise reject manual values.
+    pub fn apply_controls(&self) -> io::Result<()> {
+        if self.config.controls.is_empty() {
+            return Ok(());
+        }
+        let descs = self.device.query_controls()?;
+        let mut controls = Vec::with_capacity(self.config.controls.len());
+        for (key, value) in &self.config.controls {
+            let Some(desc) = find_control(&descs, key) else {
+                error!(control = key, "the device doesn't have this control");
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown control {key:?}"),
+                ));
+            };
+            let value = validate_control(desc, value).map_err(|err| {
+                error!(control = key, %err, "invalid control value");
+                io::Error::new(io::ErrorKind::InvalidInput, format!("{key}: {err}"))
+            })?;
+            let is_mode = matches!(
+                desc.typ,
+                ControlType::Boolean | ControlType::Menu | ControlType::IntegerMenu
+            );
+            controls.push((!is_mode, key, desc.id, value));
+        }
+        controls.sort_by_key(|c| c.0);
+        for (_, key, id, value) in controls {
+            debug!(control = key, id, ?value, "setting control");
+            self.device.set_control(Control { id, value })?;
+        }
+        Ok(())
+    }
     /// Initialize a stream from a device if it's not available.
     pub fn make_stream<'a>(
         stream: &'a mut Option<MmapStream<'static>>,
@@ -372,9 +726,10 @@ impl CameraImpl for CaptureCamera {
     fn load_frame(&mut self) -> io::Result<()> {
         let width = self.width();
         let height = self.height();
-        let (frame, _meta) = Self::make_stream(&mut self.stream, &self.device)?
+        let (frame, meta) = Self::make_stream(&mut self.stream, &self.device)?
             .next()
             .inspect_err(|err| error!(%err, "failed to read from stream"))?;
+        self.frame_meta = frame_metadata(meta);
         self.frame.width = width;
         self.frame.height = height;
         if self.config.decode_jpeg {
@@ -385,15 +740,27 @@ impl CameraImpl for CaptureCamera {
                 error!(%err, "failed to decode JPEG data");
             }
         } else {
-            self.frame.format = self.config.pixel_format.expect("Unknown pixel format");
-            let px_data = self.frame.resize_data();
-            px_data.copy_from_slice(frame);
+            let raw = RawFormat::from_fourcc(self.config.fourcc).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    UnrecognizedFourCC(self.config.fourcc.repr),
+                )
+            })?;
+            self.frame
+                .copy_from_raw(raw, frame, width, height, self.stride as _)
+                .map_err(|err| {
+                    error!(%err, "frame data is too short");
+                    io::Error::new(io::ErrorKind::InvalidData, err)
+                })?;
         }
         Ok(())
     }
     fn get_frame(&self) -> Buffer<'_> {
         self.frame.borrow()
     }
+    fn frame_metadata(&self) -> FrameMetadata {
+        self.frame_meta
+    }
     fn reload(&mut self) -> bool {
         match self.config.source.resolve() {
             Ok(device) => self.device = device,
@@ -0,0 +1,185 @@
+//! A camera that injects failures into another camera, for testing how cameras recover.
+//!
+//! Real cameras fail in ways that are hard to reproduce on demand: reads that time out, USB disconnects that take a few reloads to recover
+//! from, and drivers that change the frame size. A [`FaultyCamera`] wraps another camera and does all of these on a fixed schedule, so
+//! the recovery logic in [`CameraQuerier`](super::CameraQuerier) can be exercised without hardware.
+
+#[cfg(feature = "supply")]
+use super::FrameProvider;
+use super::{CameraFactory, CameraImpl, FrameMetadata};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::num::NonZeroU32;
+#[cfg(feature = "supply")]
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+use vv_utils::common_types::FrameSize;
+use vv_vision::buffer::Buffer;
+use vv_vision::vision::Interpolation;
+
+/// When to inject failures.
+///
+/// Reads are counted from one, and each `*_every` field triggers on reads that are a multiple of it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FaultSchedule {
+    /// Fail every `n`th read, without affecting the reads after it.
+    pub fail_every: Option<NonZeroU32>,
+    /// Disconnect on every `n`th read, after which reads fail until the camera is reloaded.
+    pub disconnect_every: Option<NonZeroU32>,
+    /// The number of reloads that fail after a disconnect, before one succeeds.
+    pub reload_failures: u32,
+    /// Stall for [`stall_for`](Self::stall_for) on every `n`th read, before reading normally.
+    pub stall_every: Option<NonZeroU32>,
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub stall_for: Duration,
+    /// Switch between the wrapped camera's frame size and [`resize_to`](Self::resize_to) every `n` reads.
+    pub resize_every: Option<NonZeroU32>,
+    pub resize_to: Option<FrameSize>,
+}
+
+/// Check if the `n`th read should trigger an event that happens every `every` reads.
+fn triggers(every: Option<NonZeroU32>, n: u32) -> bool {
+    every.is_some_and(|every| n.is_multiple_of(every.get()))
+}
+
+/// Configuration for a [`FaultyCamera`].
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FaultyCameraConfig {
+    /// The camera to wrap.
+    pub camera: Box<dyn CameraFactory>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub faults: FaultSchedule,
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "faulty"))]
+impl CameraFactory for FaultyCameraConfig {
+    fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>> {
+        let inner = self.camera.build_camera()?;
+        Ok(Box::new(FaultyCamera::new(inner, self.faults)))
+    }
+    fn device_present(&self) -> Option<bool> {
+        self.camera.device_present()
+    }
+}
+
+/// A camera that wraps another one and injects failures according to a [`FaultSchedule`].
+pub struct FaultyCamera {
+    inner: Box<dyn CameraImpl>,
+    faults: FaultSchedule,
+    /// The number of reads so far.
+    reads: u32,
+    /// Set after an injected disconnect, until the camera is reloaded.
+    disconnected: bool,
+    /// The number of reloads that have failed since the last disconnect.
+    failed_reloads: u32,
+    /// Whether frames are currently being resized.
+    resized: bool,
+    frame: Buffer<'static>,
+}
+impl FaultyCamera {
+    /// Wrap a camera.
+    pub fn new(inner: Box<dyn CameraImpl>, faults: FaultSchedule) -> Self {
+        Self {
+            inner,
+            faults,
+            reads: 0,
+            disconnected: false,
+            failed_reloads: 0,
+            resized: false,
+            frame: Buffer::empty_rgb(),
+        }
+    }
+    /// Get the wrapped camera.
+    pub fn inner(&self) -> &dyn CameraImpl {
+        &*self.inner
+    }
+    /// Get the number of reads so far, including failed ones.
+    pub fn reads(&self) -> u32 {
+        self.reads
+    }
+    /// Get the size that frames are resized to, if they currently are.
+    fn resize(&self) -> Option<FrameSize> {
+        self.faults.resize_to.filter(|_| self.resized)
+    }
+}
+impl CameraImpl for FaultyCamera {
+    fn frame_size(&self) -> FrameSize {
+        self.resize().unwrap_or_else(|| self.inner.frame_size())
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        self.reads += 1;
+        let n = self.reads;
+        if self.disconnected {
+            return Err(io::Error::other("injected disconnect"));
+        }
+        if triggers(self.faults.disconnect_every, n) {
+            warn!(read = n, "injecting a disconnect");
+            self.disconnected = true;
+            self.failed_reloads = 0;
+            return Err(io::Error::other("injected disconnect"));
+        }
+        if triggers(self.faults.fail_every, n) {
+            warn!(read = n, "injecting a read failure");
+            return Err(io::Error::other("injected read failure"));
+        }
+        if triggers(self.faults.stall_every, n) {
+            warn!(read = n, duration = ?self.faults.stall_for, "injecting a stall");
+            std::thread::sleep(self.faults.stall_for);
+        }
+        if triggers(self.faults.resize_every, n) {
+            self.resized = !self.resized;
+            debug!(read = n, size = ?self.frame_size(), "changing frame size");
+        }
+        self.inner.load_frame()?;
+        if let Some(size) = self.resize() {
+            vv_vision::vision::resize(
+                self.inner.get_frame(),
+                &mut self.frame,
+                size.width,
+                size.height,
+                Interpolation::Nearest,
+            );
+        }
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        if self.resize().is_some() {
+            self.frame.borrow()
+        } else {
+            self.inner.get_frame()
+        }
+    }
+    fn frame_metadata(&self) -> FrameMetadata {
+        self.inner.frame_metadata()
+    }
+    #[cfg(feature = "supply")]
+    fn frame_context(&self) -> Option<Arc<dyn FrameProvider>> {
+        self.inner.frame_context()
+    }
+    fn reload(&mut self) -> bool {
+        if !self.disconnected {
+            return self.inner.reload();
+        }
+        if self.failed_reloads < self.faults.reload_failures {
+            self.failed_reloads += 1;
+            warn!(failed = self.failed_reloads, "injecting a reload failure");
+        } else {
+            debug!("recovering from an injected disconnect");
+            self.disconnected = false;
+        }
+        true
+    }
+    fn debug(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("FaultyCamera")
+            .field("inner", &self.inner)
+            .field("faults", &self.faults)
+            .field("reads", &self.reads)
+            .field("disconnected", &self.disconnected)
+            .finish_non_exhaustive()
+    }
+}
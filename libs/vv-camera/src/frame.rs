@@ -2,8 +2,9 @@ use super::{CameraFactory, CameraImpl};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::io;
-use std::path::PathBuf;
-use tracing::{error, info_span};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info_span};
 use vv_vision::buffer::{Buffer, PixelFormat};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -95,3 +96,193 @@ impl CameraImpl for FrameCamera {
         self.buffer.borrow()
     }
 }
+
+/// Where to find the images for a [`SequenceCameraConfig`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum SequenceSource {
+    /// Every PNG or JPEG file in a directory, sorted by name.
+    Dir { dir: PathBuf },
+    /// Every file matching a glob pattern, sorted by name.
+    Glob { glob: String },
+}
+impl SequenceSource {
+    /// Collect the paths for this source, in playback order.
+    pub fn paths(&self) -> io::Result<Vec<PathBuf>> {
+        let mut paths = match self {
+            Self::Dir { dir } => {
+                let mut paths = Vec::new();
+                for entry in std::fs::read_dir(dir)? {
+                    let path = entry?.path();
+                    if path.is_file() && is_image_path(&path) {
+                        paths.push(path);
+                    }
+                }
+                paths
+            }
+            Self::Glob { glob } => glob::glob(glob)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?
+                .map(|res| res.map_err(glob::GlobError::into_error))
+                .collect::<io::Result<Vec<_>>>()?,
+        };
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+fn is_image_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            ["png", "jpg", "jpeg"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        })
+}
+
+/// How a [`SequenceCamera`] should play back its frames.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PlaybackMode {
+    /// Play in real time, starting over after the last frame.
+    #[default]
+    Loop,
+    /// Play in real time, and stop after the last frame.
+    Once,
+    /// Advance exactly one frame per read, never skipping any, and stop after the last frame.
+    Step,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SequenceCameraConfig {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub source: SequenceSource,
+    /// The rate that the sequence was recorded at.
+    ///
+    /// If this isn't set, frames are played back as fast as they're read.
+    pub fps: Option<f64>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mode: PlaybackMode,
+}
+
+#[cfg_attr(feature = "serde", typetag::serde(name = "sequence"))]
+impl CameraFactory for SequenceCameraConfig {
+    fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>> {
+        if let Some(fps) = self.fps
+            && !(fps.is_finite() && fps > 0.0)
+        {
+            error!(fps, "sequence FPS must be positive");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("sequence FPS must be positive, not {fps}"),
+            ));
+        }
+        let paths = self
+            .source
+            .paths()
+            .inspect_err(|err| error!(%err, "failed to list images"))?;
+        if paths.is_empty() {
+            error!("no images found for sequence");
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no images found for sequence",
+            ));
+        }
+        let mut camera = SequenceCamera {
+            paths,
+            frame_time: self.fps.map(|fps| Duration::from_secs_f64(fps.recip())),
+            mode: self.mode,
+            index: 0,
+            loaded: None,
+            start: None,
+            buffer: Buffer::empty_rgb(),
+        };
+        camera.decode(0)?;
+        Ok(Box::new(camera))
+    }
+}
+
+/// A camera that plays back a sequence of images.
+#[derive(Debug, Clone)]
+pub struct SequenceCamera {
+    pub paths: Vec<PathBuf>,
+    pub frame_time: Option<Duration>,
+    pub mode: PlaybackMode,
+    /// The index of the last frame shown, not wrapped around for looping.
+    index: usize,
+    /// The index into `paths` of the frame currently in `buffer`.
+    loaded: Option<usize>,
+    /// When the first frame was shown.
+    start: Option<Instant>,
+    buffer: Buffer<'static>,
+}
+impl SequenceCamera {
+    /// Get the index into [`Self::paths`] of the current frame.
+    pub fn current(&self) -> usize {
+        self.index % self.paths.len()
+    }
+    /// Decode the frame at the given index into the buffer, unless it's already loaded.
+    fn decode(&mut self, idx: usize) -> io::Result<()> {
+        if self.loaded == Some(idx) {
+            return Ok(());
+        }
+        let path = &self.paths[idx];
+        let _guard = info_span!("loading image", path = %path.display()).entered();
+        let buf = std::fs::read(path).inspect_err(|err| error!(%err, "failed to open file"))?;
+        self.buffer = Buffer::decode_img_data(&buf)?;
+        self.loaded = Some(idx);
+        Ok(())
+    }
+    /// Figure out the next frame to show, sleeping if it isn't due yet.
+    fn advance(&mut self) -> usize {
+        let Some(start) = self.start else {
+            self.start = Some(Instant::now());
+            return 0;
+        };
+        let next = self.index + 1;
+        let Some(frame_time) = self.frame_time else {
+            return next;
+        };
+        let due = start + frame_time.mul_f64(next as f64);
+        let now = Instant::now();
+        if let Some(to_sleep) = due.checked_duration_since(now) {
+            std::thread::sleep(to_sleep);
+            return next;
+        }
+        if self.mode == PlaybackMode::Step {
+            // we're behind, but we can't skip frames, so shift the timeline back instead
+            self.start = Some(now - frame_time.mul_f64(next as f64));
+            return next;
+        }
+        let behind = ((now - start).as_secs_f64() / frame_time.as_secs_f64()) as usize;
+        if behind > next {
+            debug!(skipped = behind - next, "skipping frames to keep up");
+        }
+        behind.max(next)
+    }
+}
+impl CameraImpl for SequenceCamera {
+    fn frame_size(&self) -> super::FrameSize {
+        super::FrameSize {
+            width: self.buffer.width,
+            height: self.buffer.height,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        let index = self.advance();
+        if self.mode != PlaybackMode::Loop && index >= self.paths.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "reached the end of the sequence",
+            ));
+        }
+        self.index = index;
+        self.decode(self.current())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.buffer.borrow()
+    }
+}
@@ -0,0 +1,180 @@
+//! Capturing from several cameras at once.
+//!
+//! A camera group reads a frame from each of its cameras and lines them up by capture time, so that stereo or multi-view processing sees
+//! frames that were taken at (nearly) the same moment. Whichever camera has the oldest frame is read again until all of the frames are
+//! within the group's maximum skew.
+
+use super::background::FrameQuerier;
+use super::{FrameContext, FrameTimestamp, FullCameraMetadata};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::time::Duration;
+#[cfg(feature = "supply")]
+use supply::prelude::*;
+use tracing::{debug, info_span};
+use vv_utils::common_types::PipelineId;
+#[cfg(feature = "supply")]
+use vv_utils::common_types::PipelineName;
+use vv_vision::buffer::Buffer;
+
+/// The number of times each camera can be read again while trying to line the frames up, before giving up on that set.
+const MAX_SKIPS: usize = 4;
+
+#[cfg(feature = "serde")]
+fn default_max_skew() -> Duration {
+    Duration::from_millis(10)
+}
+
+/// Serializable configuration for a camera group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CameraGroupConfig {
+    /// The names of the cameras in the group, in the order that their frames are passed on.
+    pub cameras: Vec<String>,
+    /// The largest difference in capture times between frames in the same set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_max_skew", with = "humantime_serde")
+    )]
+    pub max_skew: Duration,
+}
+impl CameraGroupConfig {
+    /// Get the metadata for this configuration, given the metadata of the cameras in the group.
+    pub fn metadata(&self, name: String, cameras: Vec<FullCameraMetadata>) -> GroupMetadata {
+        GroupMetadata {
+            id: PipelineId::from_hash(("group", &name)),
+            name,
+            cameras,
+            max_skew: self.max_skew,
+        }
+    }
+}
+
+/// Metadata for a camera group.
+#[derive(Debug, Clone)]
+pub struct GroupMetadata {
+    /// The name to be used for logging.
+    pub name: String,
+    /// The pipeline ID shared by runs from this group.
+    pub id: PipelineId,
+    /// The metadata for each camera, in the same order as the queriers in the [`GroupQuerier`].
+    pub cameras: Vec<FullCameraMetadata>,
+    pub max_skew: Duration,
+}
+
+/// The part of a camera group that reads from the cameras.
+#[derive(Debug)]
+pub struct GroupQuerier {
+    queriers: Vec<FrameQuerier>,
+}
+impl GroupQuerier {
+    /// Create a group querier from the queriers for each camera, in the same order as the metadata.
+    pub fn new(queriers: Vec<FrameQuerier>) -> Self {
+        Self { queriers }
+    }
+    /// Get the queriers for each camera.
+    pub fn queriers(&self) -> &[FrameQuerier] {
+        &self.queriers
+    }
+    /// Get the capture times of the last frame from each camera.
+    fn captured(&self) -> impl Iterator<Item = (usize, FrameTimestamp)> {
+        self.queriers
+            .iter()
+            .enumerate()
+            .filter_map(|(n, q)| q.frame_timestamp().map(|t| (n, t)))
+    }
+    /// Get the oldest camera and the difference between the oldest and newest frames.
+    fn oldest(&self) -> Option<(usize, Duration)> {
+        let (oldest, first) = self.captured().min_by_key(|(_, t)| t.captured)?;
+        let (_, last) = self.captured().max_by_key(|(_, t)| t.captured)?;
+        let skew = last
+            .captured
+            .duration_since(first.captured)
+            .unwrap_or_default();
+        Some((oldest, skew))
+    }
+    /// Get the difference in capture times between the oldest and newest frames in the current set.
+    pub fn skew(&self) -> Duration {
+        self.oldest().map_or(Duration::ZERO, |(_, skew)| skew)
+    }
+    /// Read a frame from every camera, then keep reading from whichever camera is furthest behind until the frames line up.
+    ///
+    /// If the frames can't be lined up within the maximum skew after a few frames from each camera, the last set read is used. As with
+    /// [`FrameQuerier::load_frame`], this returns the first error from any camera, and the next call starts over with a new set.
+    pub fn load_frames(&mut self, meta: &GroupMetadata) -> io::Result<()> {
+        let _guard = info_span!("reading frames", group = meta.name).entered();
+        for (querier, cam) in self.queriers.iter_mut().zip(&meta.cameras) {
+            querier.load_frame(cam)?;
+        }
+        for _ in 0..(self.queriers.len() * MAX_SKIPS) {
+            let Some((oldest, skew)) = self.oldest() else {
+                return Ok(());
+            };
+            if skew <= meta.max_skew {
+                return Ok(());
+            }
+            debug!(
+                camera = meta.cameras[oldest].name,
+                ?skew,
+                "skipping a frame to line up the group"
+            );
+            self.queriers[oldest].load_frame(&meta.cameras[oldest])?;
+        }
+        debug!(skew = ?self.skew(), "couldn't line up frames within the maximum skew");
+        Ok(())
+    }
+    /// Get the last frames read from each camera.
+    ///
+    /// There should've been a call to [`Self::load_frames`] first.
+    pub fn frames(&self) -> impl Iterator<Item = Buffer<'_>> {
+        self.queriers.iter().map(FrameQuerier::get_frame)
+    }
+}
+
+/// Context for a set of frames from a camera group.
+///
+/// This provides the group's name and pipeline ID, and the timestamp of the oldest frame in the set. Metadata for the individual cameras
+/// is available through [`Self::cameras`].
+#[derive(Debug, Clone)]
+pub struct GroupContext<'a> {
+    pub meta: &'a GroupMetadata,
+    /// The context for each camera's frame.
+    pub cameras: Vec<FrameContext<'a>>,
+}
+impl<'a> GroupContext<'a> {
+    /// Create a context for the last set of frames that the querier read.
+    pub fn new(meta: &'a GroupMetadata, querier: &GroupQuerier) -> Self {
+        Self {
+            meta,
+            cameras: meta
+                .cameras
+                .iter()
+                .zip(&querier.queriers)
+                .map(|(cam, q)| FrameContext::new(cam, q))
+                .collect(),
+        }
+    }
+    /// Get the timestamp of the oldest frame in the set.
+    pub fn timestamp(&self) -> Option<FrameTimestamp> {
+        self.cameras
+            .iter()
+            .filter_map(|c| c.timestamp)
+            .min_by_key(|t| t.captured)
+            .map(|t| FrameTimestamp {
+                captured: t.captured,
+                sequence: None,
+            })
+    }
+}
+#[cfg(feature = "supply")]
+impl<'r> Provider<'r> for GroupContext<'_> {
+    type Lifetimes = l!['r];
+    fn provide(&'r self, want: &mut dyn Want<Self::Lifetimes>) {
+        want.provide_value(PipelineName(&self.meta.name))
+            .provide_value(self.meta.id);
+        if let Some(timestamp) = self.timestamp() {
+            want.provide_value(timestamp);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Tracking a camera's connection, and deciding when to reconnect it.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How a camera tries to reconnect after it fails to read frames.
+///
+/// After a failure, the camera waits before trying again, doubling the delay after every failure, up to a maximum. The camera is reloaded
+/// after failures, but no more often than the minimum reload interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ReconnectPolicy {
+    /// The delay after the first failure.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub initial_delay: Duration,
+    /// The maximum delay between attempts.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub max_delay: Duration,
+    /// Give up after this many consecutive reloads fail to get a frame.
+    pub give_up_after: Option<u32>,
+    /// The minimum time between reloads.
+    #[cfg_attr(feature = "serde", serde(with = "humantime_serde"))]
+    pub min_reload_interval: Duration,
+}
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(50),
+            max_delay: Duration::from_secs(2),
+            give_up_after: None,
+            min_reload_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// The state of a camera's connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CameraStatus {
+    /// No frames have been read yet.
+    #[default]
+    Starting,
+    /// The last frame was read successfully.
+    Connected,
+    /// Reading frames is failing, and the camera will be reloaded.
+    Failing,
+    /// The camera is being reloaded.
+    Reloading,
+    /// The camera has stopped trying to reconnect.
+    GaveUp,
+}
+impl CameraStatus {
+    /// Get the name of this status, as it would be serialized.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::Connected => "connected",
+            Self::Failing => "failing",
+            Self::Reloading => "reloading",
+            Self::GaveUp => "gave-up",
+        }
+    }
+}
+
+/// A snapshot of a camera's health.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CameraHealth {
+    pub status: CameraStatus,
+    /// The number of consecutive failed reads.
+    pub fail_count: usize,
+    /// The number of reloads since the last frame was read.
+    pub reload_count: u32,
+    /// The last error from reading a frame, which is kept after the camera recovers.
+    pub last_error: Option<String>,
+    /// When the last frame was successfully read.
+    pub last_frame: Option<Instant>,
+}
+impl CameraHealth {
+    /// Get the time since the last frame was read, or `None` if no frames have been read.
+    pub fn since_last_frame(&self) -> Option<Duration> {
+        self.last_frame.map(|t| t.elapsed())
+    }
+}
+
+/// A shared handle to a camera's health, which can be checked from any thread.
+#[derive(Debug, Default, Clone)]
+pub struct HealthMonitor(Arc<Mutex<CameraHealth>>);
+impl HealthMonitor {
+    /// Get the current health.
+    pub fn get(&self) -> CameraHealth {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+    /// Update the health.
+    pub fn update<R>(&self, f: impl FnOnce(&mut CameraHealth) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
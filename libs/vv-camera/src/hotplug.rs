@@ -0,0 +1,151 @@
+//! Watching for devices being plugged in and unplugged.
+//!
+//! There's no portable way to be notified when a device is connected, so a [`DeviceWatcher`] polls a directory for device nodes instead.
+//! Whether a particular camera's device is connected is checked with [`CameraFactory::device_present`](super::CameraFactory::device_present),
+//! and a [`HotplugState`] uses that to decide when the camera should be started and stopped.
+
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The devices that were added or removed between two polls.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DeviceChanges {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+impl DeviceChanges {
+    /// Check if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Polls a directory for device nodes being added or removed.
+#[derive(Debug, Clone)]
+pub struct DeviceWatcher {
+    dir: PathBuf,
+    prefix: String,
+    devices: BTreeSet<OsString>,
+}
+impl DeviceWatcher {
+    /// Watch for entries in a directory whose names start with a prefix.
+    ///
+    /// The devices that are already there aren't reported as added on the first poll.
+    pub fn new(dir: impl Into<PathBuf>, prefix: impl Into<String>) -> Self {
+        let mut this = Self {
+            dir: dir.into(),
+            prefix: prefix.into(),
+            devices: BTreeSet::new(),
+        };
+        this.devices = this.scan();
+        this
+    }
+    /// Watch for V4L devices, which show up as `/dev/video*`.
+    pub fn v4l() -> Self {
+        Self::new("/dev", "video")
+    }
+    /// List the matching entries in the directory.
+    ///
+    /// If the directory can't be read, it's treated as empty.
+    fn scan(&self) -> BTreeSet<OsString> {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return BTreeSet::new();
+        };
+        dir.filter_map(|e| e.ok())
+            .map(|e| e.file_name())
+            .filter(|n| n.to_str().is_some_and(|n| n.starts_with(&*self.prefix)))
+            .collect()
+    }
+    /// Get the devices that are currently present, as of the last poll.
+    pub fn devices(&self) -> impl Iterator<Item = PathBuf> {
+        self.devices.iter().map(|n| self.dir.join(n))
+    }
+    /// Check for devices that were added or removed since the last poll.
+    pub fn poll(&mut self) -> DeviceChanges {
+        let devices = self.scan();
+        let changes = DeviceChanges {
+            added: devices
+                .difference(&self.devices)
+                .map(|n| self.dir.join(n))
+                .collect(),
+            removed: self
+                .devices
+                .difference(&devices)
+                .map(|n| self.dir.join(n))
+                .collect(),
+        };
+        self.devices = devices;
+        changes
+    }
+}
+
+/// How a camera's thread is doing, as far as a [`HotplugState`] is concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraRun {
+    /// The camera isn't running, either because it was never started, or because it was stopped or disconnected.
+    Stopped,
+    /// The camera's thread is still running.
+    Running,
+    /// The camera's thread stopped on its own, like when it ran out of frames, so it shouldn't be started again.
+    Finished,
+}
+
+/// What to do with a camera after checking on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotplugAction {
+    /// Leave the camera as it is.
+    Wait,
+    /// Start the camera, since its device is present and it isn't running.
+    Start,
+    /// Tell the camera to stop, since its device was unplugged.
+    Stop,
+    /// Stop watching the camera, since it's done for good.
+    Forget,
+}
+
+/// Decides when to start and stop a camera whose device can be plugged in and unplugged.
+///
+/// This only keeps track of what it's already asked for, so whatever's running the camera needs to check on it periodically with
+/// [`update`](Self::update), and do what it says.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HotplugState {
+    /// Set once the camera's been told to stop, until it does.
+    stopping: bool,
+    /// Set when starting the camera failed, until it starts or a device is plugged in.
+    failed: bool,
+}
+impl HotplugState {
+    /// Decide what to do with a camera, given whether its device is present, whether any device was plugged in since the last update,
+    /// and how its thread is doing.
+    pub fn update(&mut self, present: bool, plugged_in: bool, run: CameraRun) -> HotplugAction {
+        if plugged_in {
+            self.failed = false;
+        }
+        match run {
+            CameraRun::Finished => HotplugAction::Forget,
+            CameraRun::Running if !present && !self.stopping => {
+                self.stopping = true;
+                HotplugAction::Stop
+            }
+            CameraRun::Running => HotplugAction::Wait,
+            CameraRun::Stopped => {
+                self.stopping = false;
+                if present {
+                    HotplugAction::Start
+                } else {
+                    HotplugAction::Wait
+                }
+            }
+        }
+    }
+    /// Record that the camera started.
+    pub fn started(&mut self) {
+        self.failed = false;
+    }
+    /// Record that the camera failed to start, returning `true` if it's the first failure since it last started or a device was
+    /// plugged in, so that retries don't keep reporting the same error.
+    pub fn start_failed(&mut self) -> bool {
+        !std::mem::replace(&mut self.failed, true)
+    }
+}
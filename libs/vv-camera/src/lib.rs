@@ -1,18 +1,54 @@
+use background::{BackgroundPolicy, FrameQuerier};
+use health::{CameraHealth, CameraStatus, HealthMonitor, ReconnectPolicy};
+use record::RecordWriter;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::fmt::{self, Debug, Formatter};
-use std::io;
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::ops::{Deref, DerefMut};
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+#[cfg(feature = "supply")]
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 #[cfg(feature = "supply")]
 use supply::prelude::*;
 use tracing::{debug, error, info, info_span};
 use vv_utils::common_types::*;
 use vv_vision::buffer::Buffer;
+use vv_vision::vision::{CropRect, Flip, Interpolation, Orientation, Rotation};
 
+pub mod background;
 pub mod capture;
+pub mod fault;
 pub mod frame;
+pub mod group;
+pub mod health;
+pub mod hotplug;
+pub mod mjpeg;
+pub mod record;
+pub mod synthetic;
+
+#[cfg(test)]
+mod tests;
+
+/// Extra context for a single frame, as reported by a [`CameraImpl`].
+///
+/// This is provided to components along with the camera's metadata, which takes precedence if both provide the same type.
+#[cfg(feature = "supply")]
+pub trait FrameProvider: for<'a> ProviderDyn<'a> + Debug + Send + Sync {}
+#[cfg(feature = "supply")]
+impl<T: for<'a> ProviderDyn<'a> + Debug + Send + Sync> FrameProvider for T {}
+
+/// Metadata for a single frame, as reported by a [`CameraImpl`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameMetadata {
+    /// When the frame was captured, if it's known more precisely than when it was read.
+    pub timestamp: Option<SystemTime>,
+    /// The sequence number of the frame.
+    pub sequence: Option<u64>,
+}
 
 pub trait CameraImpl: Any + Send + Sync {
     /// Get the expected size of the frame.
@@ -21,8 +57,20 @@ pub trait CameraImpl: Any + Send + Sync {
     fn load_frame(&mut self) -> io::Result<()>;
     /// Get the frame loaded with [`Self::load_frame`].
     fn get_frame(&self) -> Buffer<'_>;
+    /// Get the metadata for the frame loaded with [`Self::load_frame`], if any is available.
+    fn frame_metadata(&self) -> FrameMetadata {
+        FrameMetadata::default()
+    }
+    /// Get extra context for the frame loaded with [`Self::load_frame`], if there is any.
+    #[cfg(feature = "supply")]
+    fn frame_context(&self) -> Option<Arc<dyn FrameProvider>> {
+        None
+    }
 
-    /// Try to reload the camera. Return false if we should stop trying.
+    /// Try to reload the camera.
+    ///
+    /// Return false if this camera can't be reloaded. It'll still be read from after waiting, but it won't be reloaded again until a
+    /// read succeeds.
     fn reload(&mut self) -> bool {
         false
     }
@@ -39,11 +87,17 @@ impl Debug for dyn CameraImpl {
 
 /// Serializable configuration for a camera.
 #[cfg_attr(feature = "serde", typetag::serde(tag = "type"))]
-pub trait CameraFactory {
+pub trait CameraFactory: Send + Sync {
     fn debug(&self, f: &mut Formatter<'_>) -> fmt::Result {
         disqualified::ShortName::of::<Self>().fmt(f)
     }
     fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>>;
+    /// Check whether the device that the camera reads from is connected.
+    ///
+    /// This is `None` for cameras that don't read from a device that can be plugged in and unplugged.
+    fn device_present(&self) -> Option<bool> {
+        None
+    }
 }
 impl Debug for dyn CameraFactory {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
@@ -51,14 +105,57 @@ impl Debug for dyn CameraFactory {
     }
 }
 
+#[cfg(feature = "serde")]
+const fn nearest() -> Interpolation {
+    Interpolation::Nearest
+}
+
+/// The size to resize frames to, and how.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResizeConfig {
+    pub width: u32,
+    pub height: u32,
+    /// How to sample frames, which is [`Nearest`](Interpolation::Nearest) by default, since it's the fastest.
+    #[cfg_attr(feature = "serde", serde(default = "nearest"))]
+    pub mode: Interpolation,
+}
+impl ResizeConfig {
+    /// Get the size that frames are resized to.
+    pub const fn size(&self) -> FrameSize {
+        FrameSize {
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct CameraConfig {
     #[cfg_attr(feature = "serde", serde(flatten))]
     pub factory: Box<dyn CameraFactory>,
+    /// The horizontal field of view of captured frames, before they're oriented.
     pub fov: Option<Fov>,
-    pub resize: Option<FrameSize>,
+    /// Calibrated intrinsics for captured frames, before they're oriented, which take priority over the FOV if present.
+    pub intrinsics: Option<Intrinsics>,
+    /// Rotate frames clockwise by 90, 180, or 270 degrees.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotate: Rotation,
+    /// Mirror frames, after rotating them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub flip: Flip,
+    /// Crop frames to a region, after rotating and flipping them.
+    pub crop: Option<CropRect>,
+    /// Resize frames, after orienting them.
+    pub resize: Option<ResizeConfig>,
     pub max_fps: Option<f64>,
+    /// If set, every frame read is recorded to this path, as it was captured before orienting or resizing it.
+    pub record: Option<PathBuf>,
+    /// If set, frames are captured on a dedicated thread and handed off with this policy.
+    pub background: Option<BackgroundPolicy>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub reconnect: ReconnectPolicy,
 }
 impl CameraConfig {
     /// Get the metadata for this configuration.
@@ -69,17 +166,139 @@ impl CameraConfig {
                 .max_fps
                 .map_or(Duration::ZERO, |f| Duration::from_secs_f64(f.recip())),
             fov: self.fov,
-            resize: self.resize,
+            intrinsics: self.intrinsics,
+            orientation: self.orientation(),
+            resize: self.resize.map(|r| r.size()),
+            resize_mode: self.resize.map(|r| r.mode).unwrap_or_default(),
+            background: self.background,
+        }
+    }
+    /// Get the FOV and intrinsics of frames after they're oriented, given the size of the frames that the camera captures.
+    ///
+    /// If only an FOV is given, it's turned into intrinsics with the principal point at the center of the captured frames, since a
+    /// crop can move it off-center. A size of zero means that it's unknown, in which case intrinsics are assumed to be for the
+    /// captured frame size, and an FOV can't be oriented.
+    pub fn oriented_optics(
+        &self,
+        size: FrameSize,
+    ) -> io::Result<(Option<Fov>, Option<Intrinsics>)> {
+        let orientation = self.orientation();
+        if orientation.is_identity() {
+            return Ok((self.fov, self.intrinsics));
+        }
+        let known = size.width > 0 && size.height > 0;
+        let intrinsics = match (self.intrinsics, self.fov) {
+            (Some(intrinsics), _) if known => intrinsics.scaled_to(size),
+            (Some(intrinsics), _) => intrinsics,
+            (None, Some(fov)) if known => fov_intrinsics(fov, size),
+            (None, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "can't orient the FOV of a camera with an unknown frame size, set its intrinsics instead",
+                ));
+            }
+            (None, None) => return Ok((None, None)),
+        };
+        let intrinsics = orient_intrinsics(&intrinsics, orientation);
+        let fov = Fov((intrinsics.width as f64 * 0.5 / intrinsics.fx)
+            .atan()
+            .to_degrees()
+            * 2.0);
+        Ok((Some(fov), Some(intrinsics)))
+    }
+    /// Get the orientation applied to frames.
+    fn orientation(&self) -> Orientation {
+        Orientation {
+            rotate: self.rotate,
+            flip: self.flip,
+            crop: self.crop,
         }
     }
     /// Build a [`Camera`].
     pub fn build_camera(&self, name: String) -> io::Result<Camera> {
         let _guard = tracing::error_span!("build_camera", name).entered();
         let inner = self.factory.build_camera()?;
-        Ok(Camera {
-            meta: self.metadata(name),
-            querier: CameraQuerier::new(inner),
-        })
+        let mut meta = self.metadata(name);
+        (meta.fov, meta.intrinsics) = self
+            .oriented_optics(inner.frame_size())
+            .inspect_err(|err| error!(%err, "failed to orient intrinsics"))?;
+        let mut querier = CameraQuerier::new(inner);
+        querier.set_reconnect_policy(self.reconnect);
+        if let Some(path) = &self.record {
+            let recorder = RecordWriter::create(path).inspect_err(
+                |err| error!(%err, path = %path.display(), "failed to create recording"),
+            )?;
+            querier.set_recorder(Some(recorder));
+        }
+        Ok(Camera { meta, querier })
+    }
+}
+
+/// Get the intrinsics implied by a horizontal field of view, in degrees, with the principal point at the center of the frame.
+fn fov_intrinsics(Fov(fov): Fov, size: FrameSize) -> Intrinsics {
+    let cx = size.width as f64 * 0.5;
+    let f = cx / (fov.to_radians() * 0.5).tan();
+    Intrinsics {
+        width: size.width,
+        height: size.height,
+        fx: f,
+        fy: f,
+        cx,
+        cy: size.height as f64 * 0.5,
+        distortion: Distortion::default(),
+    }
+}
+
+/// Transform intrinsics for a camera's frames to match the frames after they've been oriented.
+///
+/// Rotating swaps the focal lengths and moves the principal point, and rotating or flipping changes the signs of the tangential
+/// distortion coefficients, since they depend on the direction of the axes. Cropping moves the principal point by the crop's origin.
+pub fn orient_intrinsics(intrinsics: &Intrinsics, orientation: Orientation) -> Intrinsics {
+    let Intrinsics {
+        width,
+        height,
+        fx,
+        fy,
+        cx,
+        cy,
+        mut distortion,
+    } = *intrinsics;
+    let (w, h) = (width as f64 - 1.0, height as f64 - 1.0);
+    let Distortion { p1, p2, .. } = distortion;
+    let (fx, fy, cx, cy, [p1, p2]) = match orientation.rotate {
+        Rotation::None => (fx, fy, cx, cy, [p1, p2]),
+        Rotation::Cw90 => (fy, fx, h - cy, cx, [p2, -p1]),
+        Rotation::Cw180 => (fx, fy, w - cx, h - cy, [-p1, -p2]),
+        Rotation::Cw270 => (fy, fx, cy, w - cx, [-p2, p1]),
+    };
+    let (rw, rh) = orientation.rotated_size(width, height);
+    let (flip_x, flip_y) = match orientation.flip {
+        Flip::None => (false, false),
+        Flip::Horizontal => (true, false),
+        Flip::Vertical => (false, true),
+        Flip::Both => (true, true),
+    };
+    let (cx, p2) = if flip_x {
+        (rw as f64 - 1.0 - cx, -p2)
+    } else {
+        (cx, p2)
+    };
+    let (cy, p1) = if flip_y {
+        (rh as f64 - 1.0 - cy, -p1)
+    } else {
+        (cy, p1)
+    };
+    let crop = orientation.clipped_crop(rw, rh);
+    distortion.p1 = p1;
+    distortion.p2 = p2;
+    Intrinsics {
+        width: crop.width,
+        height: crop.height,
+        fx,
+        fy,
+        cx: cx - crop.x as f64,
+        cy: cy - crop.y as f64,
+        distortion,
     }
 }
 
@@ -87,22 +306,56 @@ impl CameraConfig {
 #[derive(Debug)]
 pub struct CameraQuerier {
     inner: Box<dyn CameraImpl>,
+    oriented: Option<Buffer<'static>>,
     resized: Option<Buffer<'static>>,
-    fail_count: usize,
-    backoff: usize,
+    policy: ReconnectPolicy,
+    health: HealthMonitor,
+    /// The delay before the next attempt after a failure.
+    delay: Duration,
+    /// Cleared when the implementation can't be reloaded, until it reads a frame again.
+    reloadable: bool,
+    last_reload: Option<Instant>,
     last_frame: Instant,
+    timestamp: Option<FrameTimestamp>,
+    #[cfg(feature = "supply")]
+    context: Option<Arc<dyn FrameProvider>>,
+    recorder: Option<RecordWriter<BufWriter<File>>>,
 }
 impl CameraQuerier {
     /// Create a new querier.
     pub fn new(inner: Box<dyn CameraImpl>) -> Self {
         Self {
             inner,
-            fail_count: 0,
-            backoff: 1,
+            policy: ReconnectPolicy::default(),
+            health: HealthMonitor::default(),
+            delay: Duration::ZERO,
+            reloadable: true,
+            last_reload: None,
+            oriented: None,
             resized: None,
             last_frame: Instant::now(),
+            timestamp: None,
+            #[cfg(feature = "supply")]
+            context: None,
+            recorder: None,
         }
     }
+    /// Set a writer to record every successfully read frame to.
+    pub fn set_recorder(&mut self, recorder: Option<RecordWriter<BufWriter<File>>>) {
+        self.recorder = recorder;
+    }
+    /// Set the policy for reconnecting after failures.
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.policy = policy;
+    }
+    /// Get a snapshot of the camera's current health.
+    pub fn health(&self) -> CameraHealth {
+        self.health.get()
+    }
+    /// Get a handle to the camera's health that can be checked from other threads.
+    pub fn health_monitor(&self) -> HealthMonitor {
+        self.health.clone()
+    }
     /// Get a reference to the implementation.
     pub fn inner(&self) -> &dyn CameraImpl {
         &*self.inner
@@ -121,11 +374,71 @@ impl CameraQuerier {
         let any = self.inner_mut() as &mut dyn Any;
         any.downcast_mut()
     }
+    /// Handle a failure to read a frame, waiting and reloading the camera according to the reconnect policy.
+    fn handle_failure(&mut self, err: &io::Error) {
+        let fail_count = self.health.update(|h| {
+            h.status = CameraStatus::Failing;
+            h.fail_count += 1;
+            h.last_error = Some(err.to_string());
+            h.fail_count
+        });
+        if fail_count == 1 {
+            self.delay = self.policy.initial_delay;
+            self.reloadable = true;
+        }
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            // the camera ran out of frames, so there's nothing to wait for
+            info!("camera reached the end of its frames");
+            return;
+        }
+        error!(%err, fail_count, "failed to read frame");
+        std::thread::sleep(self.delay);
+        self.delay = (self.delay * 2).min(self.policy.max_delay);
+        if !self.reloadable
+            || self
+                .last_reload
+                .is_some_and(|t| t.elapsed() < self.policy.min_reload_interval)
+        {
+            return;
+        }
+        info!("reloading camera");
+        let _guard = info_span!("reloading").entered();
+        self.health.update(|h| h.status = CameraStatus::Reloading);
+        let reloaded = self.inner.reload();
+        self.last_reload = Some(Instant::now());
+        let reload_count = self.health.update(|h| {
+            h.reload_count += 1;
+            h.reload_count
+        });
+        if !reloaded {
+            // some cameras can't be reloaded, but their reads can still recover on their own
+            debug!("camera can't be reloaded, retrying reads until one succeeds");
+            self.reloadable = false;
+        }
+        if self.policy.give_up_after.is_some_and(|n| reload_count >= n) {
+            error!(reload_count, "giving up on reconnecting to camera");
+            self.health.update(|h| h.status = CameraStatus::GaveUp);
+        } else {
+            self.health.update(|h| h.status = CameraStatus::Failing);
+        }
+    }
     /// Read a frame, reloading the camera if necessary.
     ///
+    /// If reading fails, this waits before returning according to the [`ReconnectPolicy`], so it can be called again immediately. The
+    /// exception is an [`UnexpectedEof`](io::ErrorKind::UnexpectedEof) error, which means the camera has no more frames, and returns
+    /// right away.
+    /// Once the camera gives up on reconnecting, this returns an error with the [`NotConnected`](io::ErrorKind::NotConnected) kind
+    /// without trying to read.
+    ///
     /// The name is needed for logging.
     pub fn load_frame(&mut self, meta: &CameraMetadata) -> io::Result<()> {
         let _guard = info_span!("reading frame", name = meta.name);
+        if self.health.get().status == CameraStatus::GaveUp {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "gave up on reconnecting to the camera",
+            ));
+        }
         let now = Instant::now();
         if let Some(to_sleep) = meta.min_frame.checked_sub(now - self.last_frame) {
             debug!(?to_sleep, "sleeping to throttle framerate");
@@ -133,33 +446,68 @@ impl CameraQuerier {
             self.last_frame = now;
         }
         let res = self.inner.load_frame();
+        let captured = SystemTime::now();
         if let Err(err) = &res {
-            self.fail_count += 1;
-            error!(%err, fail_count = self.fail_count, "failed to read frame");
-            if self.fail_count == self.backoff {
-                info!("reloading camera");
-                let _guard = info_span!("reloading");
-                let retry = self.inner.reload();
-                if retry {
-                    self.backoff *= 2;
-                } else {
-                    self.backoff = 0;
-                }
-            }
+            self.handle_failure(err);
         } else {
-            self.backoff = 0;
+            self.health.update(|h| {
+                h.status = CameraStatus::Connected;
+                h.fail_count = 0;
+                h.reload_count = 0;
+                h.last_frame = Some(Instant::now());
+            });
+            let frame_meta = self.inner.frame_metadata();
+            if let (Some(last), Some(seq)) =
+                (self.timestamp.and_then(|t| t.sequence), frame_meta.sequence)
+                && seq > last + 1
+            {
+                debug!(dropped = seq - last - 1, "camera dropped frames");
+            }
+            self.timestamp = Some(FrameTimestamp {
+                captured: frame_meta.timestamp.unwrap_or(captured),
+                sequence: frame_meta.sequence,
+            });
+            #[cfg(feature = "supply")]
+            {
+                self.context = self.inner.frame_context();
+            }
+            if meta.orientation.is_identity() {
+                self.oriented = None;
+            } else {
+                vv_vision::vision::orient(
+                    self.inner.get_frame(),
+                    self.oriented.get_or_insert_default(),
+                    meta.orientation,
+                );
+            }
+            let oriented = self
+                .oriented
+                .as_ref()
+                .map_or_else(|| self.inner.get_frame(), Buffer::borrow);
             if let Some(size) = meta.resize
-                && size != self.inner.frame_size()
+                && (size.width, size.height) != (oriented.width, oriented.height)
             {
                 vv_vision::vision::resize(
-                    self.inner.get_frame(),
+                    oriented,
                     self.resized.get_or_insert_default(),
                     size.width,
                     size.height,
+                    meta.resize_mode,
                 );
             } else {
                 self.resized = None;
             }
+            if let Some(recorder) = &mut self.recorder {
+                // record the frame as it was captured, so a replay camera can orient and resize it again
+                let captured = frame_meta.timestamp.unwrap_or(captured);
+                if let Err(err) = recorder
+                    .write_frame(&meta.name, captured, self.inner.get_frame())
+                    .and_then(|()| recorder.flush())
+                {
+                    error!(%err, "failed to record frame, stopping recording");
+                    self.recorder = None;
+                }
+            }
         }
         res
     }
@@ -169,8 +517,18 @@ impl CameraQuerier {
     pub fn get_frame(&self) -> Buffer<'_> {
         self.resized
             .as_ref()
+            .or(self.oriented.as_ref())
             .map_or_else(|| self.inner.get_frame(), Buffer::borrow)
     }
+    /// Get the capture time and sequence number of the last frame read.
+    pub fn frame_timestamp(&self) -> Option<FrameTimestamp> {
+        self.timestamp
+    }
+    /// Get the extra context that the implementation gave for the last frame read.
+    #[cfg(feature = "supply")]
+    pub fn frame_context(&self) -> Option<&Arc<dyn FrameProvider>> {
+        self.context.as_ref()
+    }
     /// Fetch a frame, and if it was successful, return it.
     ///
     /// The name is needed for logging.
@@ -190,7 +548,13 @@ pub struct CameraMetadata {
     pub name: String,
     pub min_frame: Duration,
     pub fov: Option<Fov>,
+    pub intrinsics: Option<Intrinsics>,
+    /// The rotation, flip, and crop applied to frames before they're resized.
+    pub orientation: Orientation,
     pub resize: Option<FrameSize>,
+    /// How frames are resized, if they are.
+    pub resize_mode: Interpolation,
+    pub background: Option<BackgroundPolicy>,
 }
 impl CameraMetadata {
     pub fn new(name: String) -> Self {
@@ -198,7 +562,11 @@ impl CameraMetadata {
             name,
             min_frame: Duration::ZERO,
             fov: None,
+            intrinsics: None,
+            orientation: Orientation::default(),
             resize: None,
+            resize_mode: Interpolation::Nearest,
+            background: None,
         }
     }
 }
@@ -210,6 +578,9 @@ impl<'r> Provider<'r> for CameraMetadata {
         if let Some(fov) = self.fov {
             want.provide_value(fov);
         }
+        if let Some(intrinsics) = self.intrinsics {
+            want.provide_value(intrinsics);
+        }
     }
 }
 
@@ -243,9 +614,45 @@ impl<'r> Provider<'r> for FullCameraMetadata {
     }
 }
 
+/// Context for a single frame, combining the camera's metadata with the frame's timestamp.
+///
+/// This can be passed as the context for a pipeline run so components can request a [`FrameTimestamp`], or anything else that the
+/// camera gave for the frame.
+#[derive(Debug, Clone)]
+pub struct FrameContext<'a> {
+    pub meta: &'a FullCameraMetadata,
+    pub timestamp: Option<FrameTimestamp>,
+    #[cfg(feature = "supply")]
+    pub extra: Option<Arc<dyn FrameProvider>>,
+}
+impl<'a> FrameContext<'a> {
+    /// Create a context for the last frame that the querier read.
+    pub fn new(meta: &'a FullCameraMetadata, querier: &FrameQuerier) -> Self {
+        Self {
+            meta,
+            timestamp: querier.frame_timestamp(),
+            #[cfg(feature = "supply")]
+            extra: querier.frame_context().cloned(),
+        }
+    }
+}
+#[cfg(feature = "supply")]
+impl<'r> Provider<'r> for FrameContext<'_> {
+    type Lifetimes = l!['r];
+    fn provide(&'r self, want: &mut dyn Want<Self::Lifetimes>) {
+        if let Some(timestamp) = self.timestamp {
+            want.provide_value(timestamp);
+        }
+        if let Some(extra) = &self.extra {
+            extra.provide(want);
+        }
+        self.meta.provide(want);
+    }
+}
+
 /// A source of frames.
 ///
-/// A [`Camera`] wraps a [`CameraImpl`] and handles framerate throttling and reloading with exponential backoff.
+/// A [`Camera`] wraps a [`CameraImpl`] and handles framerate throttling and reconnecting with a [`ReconnectPolicy`].
 #[derive(Debug)]
 pub struct Camera {
     pub meta: CameraMetadata,
@@ -293,13 +700,18 @@ impl Camera {
     pub fn read(&mut self) -> io::Result<Buffer<'_>> {
         self.load_frame().map(|_| self.get_frame())
     }
+    /// Get the size of the frames returned after they've been oriented and resized.
+    fn output_size(&self) -> FrameSize {
+        self.meta.resize.unwrap_or_else(|| {
+            let FrameSize { width, height } = self.inner().frame_size();
+            let (width, height) = self.meta.orientation.output_size(width, height);
+            FrameSize { width, height }
+        })
+    }
     /// Split this camera into full metadata and the querier.
     pub fn split(self) -> (FullCameraMetadata, CameraQuerier) {
         let full = FullCameraMetadata {
-            size: self
-                .meta
-                .resize
-                .unwrap_or_else(|| self.inner().frame_size()),
+            size: self.output_size(),
             id: self.querier.id(),
             meta: self.meta,
         };
@@ -308,10 +720,7 @@ impl Camera {
     /// Clone the metadata and fill it in with metadata supplied from the querier.
     pub fn clone_full_metadata(&self) -> FullCameraMetadata {
         FullCameraMetadata {
-            size: self
-                .meta
-                .resize
-                .unwrap_or_else(|| self.inner().frame_size()),
+            size: self.output_size(),
             id: self.querier.id(),
             meta: self.meta.clone(),
         }
@@ -324,6 +733,12 @@ impl<'r> Provider<'r> for Camera {
 
     fn provide(&'r self, want: &mut dyn Want<Self::Lifetimes>) {
         want.provide_value(self.querier.id());
+        if let Some(timestamp) = self.querier.frame_timestamp() {
+            want.provide_value(timestamp);
+        }
+        if let Some(extra) = self.querier.frame_context() {
+            extra.provide(want);
+        }
         self.meta.provide(want);
     }
 }
@@ -0,0 +1,402 @@
+//! Cameras that read from an MJPEG stream over HTTP.
+//!
+//! These streams are served as a `multipart/x-mixed-replace` response, with each part containing a single JPEG frame. This is what IP
+//! cameras and WPILib's `CameraServer` use.
+
+use super::FrameSize;
+use super::{CameraFactory, CameraImpl};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, info_span};
+use vv_vision::buffer::Buffer;
+
+/// The largest frame we'll accept, to avoid allocating unbounded memory for a bad `Content-Length`.
+const MAX_FRAME_SIZE: usize = 64 << 20;
+/// The longest header line we'll accept.
+const MAX_LINE_LENGTH: u64 = 8 << 10;
+
+#[cfg(feature = "serde")]
+const fn default_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+/// The parts of an `http` URL that we need to connect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+impl HttpUrl {
+    fn parse(url: &str) -> io::Result<Self> {
+        let invalid =
+            |msg: &str| io::Error::new(io::ErrorKind::InvalidInput, format!("{msg}: {url:?}"));
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("only http URLs are supported"))?;
+        let (authority, path) = rest.find('/').map_or((rest, "/"), |i| rest.split_at(i));
+        let (host, port) = if let Some(v6) = authority.strip_prefix('[') {
+            let (host, rest) = v6
+                .split_once(']')
+                .ok_or_else(|| invalid("unterminated IPv6 address"))?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            }
+        };
+        if host.is_empty() {
+            return Err(invalid("missing host"));
+        }
+        let port = port
+            .map(str::parse)
+            .transpose()
+            .map_err(|_| invalid("invalid port"))?
+            .unwrap_or(80);
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+/// Read a line, without its line ending.
+fn read_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> io::Result<()> {
+    line.clear();
+    reader.take(MAX_LINE_LENGTH).read_until(b'\n', line)?;
+    if line.last() != Some(&b'\n') {
+        return Err(if line.len() as u64 == MAX_LINE_LENGTH {
+            io::Error::new(io::ErrorKind::InvalidData, "line is too long")
+        } else {
+            io::Error::new(io::ErrorKind::ConnectionAborted, "stream ended")
+        });
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(())
+}
+
+/// Read header lines until an empty line, calling `f` with each name and value.
+fn read_headers(
+    reader: &mut impl BufRead,
+    line: &mut Vec<u8>,
+    mut f: impl FnMut(&str, &str),
+) -> io::Result<()> {
+    loop {
+        read_line(reader, line)?;
+        if line.is_empty() {
+            return Ok(());
+        }
+        if let Some((name, value)) = std::str::from_utf8(line)
+            .ok()
+            .and_then(|l| l.split_once(':'))
+        {
+            f(name.trim(), value.trim());
+        }
+    }
+}
+
+/// Get the boundary from a `multipart/x-mixed-replace` content type.
+fn parse_boundary(content_type: &str) -> Option<&str> {
+    let mut params = content_type.split(';');
+    let mime = params.next()?.trim();
+    if !mime.eq_ignore_ascii_case("multipart/x-mixed-replace") {
+        return None;
+    }
+    params.find_map(|p| {
+        let (name, value) = p.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+fn trim_dashes(s: &[u8]) -> &[u8] {
+    let start = s.iter().position(|&b| b != b'-').unwrap_or(s.len());
+    &s[start..]
+}
+
+/// Check if a line is a part boundary, or the closing boundary at the end of the stream.
+///
+/// Some servers include the leading dashes in the boundary parameter and some don't, so they're ignored on both sides.
+fn is_boundary(line: &[u8], boundary: &str) -> bool {
+    let Some(line) = line.trim_ascii_end().strip_prefix(b"--") else {
+        return false;
+    };
+    let line = line.strip_suffix(b"--").unwrap_or(line);
+    trim_dashes(line) == boundary.trim_start_matches('-').as_bytes()
+}
+
+/// Convert a timeout from the socket to a [`TimedOut`](io::ErrorKind::TimedOut) error.
+fn map_timeout(err: io::Error) -> io::Error {
+    if err.kind() == io::ErrorKind::WouldBlock {
+        io::Error::new(io::ErrorKind::TimedOut, "timed out waiting for the stream")
+    } else {
+        err
+    }
+}
+
+/// A stream that times out once a deadline passes, rather than after a single read takes too long.
+///
+/// A server that trickles in a byte at a time would otherwise never time out.
+#[derive(Debug)]
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out waiting for the stream",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Configuration for an [`MjpegCamera`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MjpegCameraConfig {
+    /// The URL of the stream. Only `http` is supported.
+    pub url: String,
+    /// How long to wait when connecting or for the next frame before failing.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_timeout", with = "humantime_serde")
+    )]
+    pub timeout: Duration,
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "mjpeg"))]
+impl CameraFactory for MjpegCameraConfig {
+    fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>> {
+        let _guard = info_span!("connecting to stream", url = self.url).entered();
+        let url = HttpUrl::parse(&self.url).inspect_err(|err| error!(%err, "invalid URL"))?;
+        let mut camera = MjpegCamera {
+            config: self.clone(),
+            url,
+            stream: None,
+            jpeg: Vec::new(),
+            line: Vec::new(),
+            frame: Buffer::empty_rgb(),
+        };
+        camera.connect()?;
+        // we don't know the frame size until we've read one
+        camera.load_frame()?;
+        Ok(Box::new(camera))
+    }
+}
+
+/// An open connection to a stream.
+#[derive(Debug)]
+struct Connection {
+    reader: BufReader<DeadlineStream>,
+    /// The boundary between parts, from the response's content type.
+    boundary: String,
+    /// Set if the last part had no length and we already read the boundary after it.
+    at_boundary: bool,
+}
+
+/// A camera that reads JPEG frames from a `multipart/x-mixed-replace` HTTP stream.
+///
+/// If reading a frame fails, the connection is dropped, and it's reopened when the camera is reloaded.
+#[derive(Debug)]
+pub struct MjpegCamera {
+    config: MjpegCameraConfig,
+    url: HttpUrl,
+    stream: Option<Connection>,
+    jpeg: Vec<u8>,
+    line: Vec<u8>,
+    frame: Buffer<'static>,
+}
+impl MjpegCamera {
+    /// Connect to the stream, replacing the current connection.
+    pub fn connect(&mut self) -> io::Result<()> {
+        self.stream = None;
+        let HttpUrl { host, port, path } = &self.url;
+        let mut last_err = None;
+        let mut stream = None;
+        for addr in (host.as_str(), *port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, self.config.timeout) {
+                Ok(s) => {
+                    stream = Some(s);
+                    break;
+                }
+                Err(err) => last_err = Some(err),
+            }
+        }
+        let Some(stream) = stream else {
+            let err = last_err.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "host didn't resolve to any address",
+                )
+            });
+            error!(%err, "failed to connect");
+            return Err(map_timeout(err));
+        };
+        stream.set_write_timeout(Some(self.config.timeout))?;
+        let host_header = if *port == 80 {
+            host.clone()
+        } else if host.contains(':') {
+            format!("[{host}]:{port}")
+        } else {
+            format!("{host}:{port}")
+        };
+        // HTTP/1.0 keeps servers from using chunked encoding
+        write!(
+            &stream,
+            "GET {path} HTTP/1.0\r\nHost: {host_header}\r\nAccept: multipart/x-mixed-replace\r\n\r\n"
+        )
+        .map_err(map_timeout)?;
+        let mut reader = BufReader::new(DeadlineStream {
+            stream,
+            deadline: Instant::now() + self.config.timeout,
+        });
+        read_line(&mut reader, &mut self.line).map_err(map_timeout)?;
+        let status = String::from_utf8_lossy(&self.line).into_owned();
+        if !status.starts_with("HTTP/") || status.split_ascii_whitespace().nth(1) != Some("200") {
+            error!(status, "unexpected response from server");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected response: {status}"),
+            ));
+        }
+        let mut content_type = None;
+        read_headers(&mut reader, &mut self.line, |name, value| {
+            if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.to_string());
+            }
+        })
+        .map_err(map_timeout)?;
+        let Some(boundary) = content_type.as_deref().and_then(parse_boundary) else {
+            error!(?content_type, "response isn't a multipart stream");
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "response isn't a multipart stream",
+            ));
+        };
+        info!(boundary, "connected to stream");
+        self.stream = Some(Connection {
+            reader,
+            boundary: boundary.to_string(),
+            at_boundary: false,
+        });
+        Ok(())
+    }
+    /// Read the next part from the stream into `self.jpeg`.
+    fn read_part(&mut self) -> io::Result<()> {
+        let Some(conn) = &mut self.stream else {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "not connected to the stream",
+            ));
+        };
+        conn.reader.get_mut().deadline = Instant::now() + self.config.timeout;
+        // skip anything before the boundary, like the previous part's trailing line break
+        if !std::mem::take(&mut conn.at_boundary) {
+            loop {
+                read_line(&mut conn.reader, &mut self.line)?;
+                if is_boundary(&self.line, &conn.boundary) {
+                    break;
+                }
+            }
+        }
+        let mut length = None;
+        read_headers(&mut conn.reader, &mut self.line, |name, value| {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<usize>().ok();
+            }
+        })?;
+        self.jpeg.clear();
+        if let Some(length) = length {
+            if length > MAX_FRAME_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("frame is too large ({length} bytes)"),
+                ));
+            }
+            self.jpeg.resize(length, 0);
+            conn.reader.read_exact(&mut self.jpeg).map_err(|err| {
+                if err.kind() == io::ErrorKind::UnexpectedEof {
+                    io::Error::new(io::ErrorKind::ConnectionAborted, "stream ended")
+                } else {
+                    err
+                }
+            })?;
+        } else {
+            // without a length, the data runs until the line break before the next boundary
+            loop {
+                let len = self.jpeg.len();
+                conn.reader
+                    .by_ref()
+                    .take((MAX_FRAME_SIZE - len) as u64 + 1)
+                    .read_until(b'\n', &mut self.jpeg)?;
+                let line = &self.jpeg[len..];
+                if line.last() != Some(&b'\n') {
+                    return Err(if self.jpeg.len() > MAX_FRAME_SIZE {
+                        io::Error::new(io::ErrorKind::InvalidData, "frame is too large")
+                    } else {
+                        io::Error::new(io::ErrorKind::ConnectionAborted, "stream ended")
+                    });
+                }
+                if is_boundary(line, &conn.boundary) {
+                    self.jpeg.truncate(len);
+                    if self.jpeg.ends_with(b"\r\n") {
+                        self.jpeg.truncate(len - 2);
+                    } else if self.jpeg.ends_with(b"\n") {
+                        self.jpeg.truncate(len - 1);
+                    }
+                    conn.at_boundary = true;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Decode the part that was just read into the frame buffer.
+    fn decode(&mut self) -> io::Result<()> {
+        self.frame = Buffer::decode_img_data(&self.jpeg)?;
+        Ok(())
+    }
+}
+impl CameraImpl for MjpegCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: self.frame.width,
+            height: self.frame.height,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        let res = self.read_part().map_err(map_timeout);
+        if let Err(err) = res {
+            error!(%err, "failed to read from stream");
+            // we don't know where we are in the stream, so start over when we're reloaded
+            self.stream = None;
+            return Err(err);
+        }
+        debug!(len = self.jpeg.len(), "read frame");
+        self.decode()
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.frame.borrow()
+    }
+    fn reload(&mut self) -> bool {
+        let _guard = info_span!("reconnecting to stream", url = self.config.url).entered();
+        if let Err(err) = self.connect() {
+            error!(%err, "failed to reconnect");
+        }
+        true
+    }
+}
@@ -0,0 +1,352 @@
+//! A lossless on-disk format for recorded frames, and a camera to replay them.
+//!
+//! A recording starts with the magic bytes `VVREC` and a version byte, followed by any number of frames.
+//! Each frame has a header, with all integers in little-endian:
+//!
+//! | Field     | Type                | Description                                     |
+//! | --------- | ------------------- | ----------------------------------------------- |
+//! | timestamp | `u64`               | Capture time in nanoseconds since the Unix epoch |
+//! | width     | `u32`               | Width of the frame                              |
+//! | height    | `u32`               | Height of the frame                             |
+//! | format    | `u8`                | The raw [`PixelFormat`] value                   |
+//! | name_len  | `u16`               | Length of the camera name                       |
+//! | name      | `[u8; name_len]`    | Camera name, in UTF-8                           |
+//!
+//! The header is followed by the raw frame data, which is exactly as long as [`Buffer::expected_size`] says it should be.
+
+use super::frame::PlaybackMode;
+use super::{CameraFactory, CameraImpl, FrameSize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::num::NonZero;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, error, info_span};
+use vv_vision::buffer::{Buffer, PixelFormat};
+
+/// The magic bytes at the start of every recording.
+pub const MAGIC: &[u8; 5] = b"VVREC";
+/// The current version of the format.
+pub const VERSION: u8 = 1;
+
+/// Metadata stored alongside each recorded frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// The time the frame was captured.
+    pub timestamp: SystemTime,
+    /// The name of the camera that captured the frame.
+    pub name: String,
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Writes frames to a recording.
+#[derive(Debug)]
+pub struct RecordWriter<W: Write> {
+    inner: W,
+}
+impl RecordWriter<BufWriter<File>> {
+    /// Create a new recording at the given path, overwriting any existing file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+impl<W: Write> RecordWriter<W> {
+    /// Create a new writer, writing the file header.
+    pub fn new(mut inner: W) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[VERSION])?;
+        Ok(Self { inner })
+    }
+    /// Write a single frame.
+    pub fn write_frame(
+        &mut self,
+        name: &str,
+        timestamp: SystemTime,
+        frame: Buffer<'_>,
+    ) -> io::Result<()> {
+        let nanos = timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "timestamp before epoch"))?
+            .as_nanos();
+        let nanos = u64::try_from(nanos)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "timestamp out of range"))?;
+        let name_len = u16::try_from(name.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "camera name too long"))?;
+        if frame.data.len() != frame.expected_size() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer size mismatch",
+            ));
+        }
+        self.inner.write_all(&nanos.to_le_bytes())?;
+        self.inner.write_all(&frame.width.to_le_bytes())?;
+        self.inner.write_all(&frame.height.to_le_bytes())?;
+        self.inner.write_all(&[frame.format.0.get()])?;
+        self.inner.write_all(&name_len.to_le_bytes())?;
+        self.inner.write_all(name.as_bytes())?;
+        self.inner.write_all(&frame.data)
+    }
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+    /// Get the underlying writer back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads frames from a recording.
+#[derive(Debug)]
+pub struct RecordReader<R: Read> {
+    inner: R,
+}
+impl RecordReader<BufReader<File>> {
+    /// Open a recording at the given path.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+impl<R: Read> RecordReader<R> {
+    /// Create a new reader, checking the file header.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let mut header = [0u8; MAGIC.len() + 1];
+        inner.read_exact(&mut header)?;
+        if header[..MAGIC.len()] != *MAGIC {
+            return Err(invalid_data("not a frame recording"));
+        }
+        let version = header[MAGIC.len()];
+        if version != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported recording version {version}"
+            )));
+        }
+        Ok(Self { inner })
+    }
+    /// Read the next frame into a buffer, returning its header.
+    ///
+    /// Returns `Ok(None)` at the end of the recording.
+    pub fn read_frame(&mut self, buffer: &mut Buffer<'_>) -> io::Result<Option<FrameHeader>> {
+        let mut fixed = [0u8; 19];
+        // only a clean end-of-file before a frame is the end of the recording, anything else is truncation
+        let read = read_fully(&mut self.inner, &mut fixed)?;
+        if read == 0 {
+            return Ok(None);
+        } else if read < fixed.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated frame header",
+            ));
+        }
+        let nanos = u64::from_le_bytes(fixed[0..8].try_into().unwrap());
+        let width = u32::from_le_bytes(fixed[8..12].try_into().unwrap());
+        let height = u32::from_le_bytes(fixed[12..16].try_into().unwrap());
+        let format = fixed[16];
+        let name_len = u16::from_le_bytes(fixed[17..19].try_into().unwrap());
+        let format = NonZero::new(format)
+            .map(PixelFormat)
+            .filter(|f| f.is_anon() || PixelFormat::NAMED.contains(f))
+            .ok_or_else(|| invalid_data(format!("invalid pixel format {format}")))?;
+        let mut name = vec![0u8; name_len as usize];
+        self.inner.read_exact(&mut name)?;
+        let name = String::from_utf8(name).map_err(|_| invalid_data("invalid camera name"))?;
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|n| n.checked_mul(format.pixel_size()))
+            .ok_or_else(|| invalid_data(format!("invalid frame size {width}x{height}")))?;
+        // read only what's actually in the file, so a corrupt header can't make us allocate more than that
+        let data = buffer.data.to_mut();
+        data.clear();
+        if (&mut self.inner).take(len as u64).read_to_end(data)? < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated frame data",
+            ));
+        }
+        buffer.width = width;
+        buffer.height = height;
+        buffer.format = format;
+        Ok(Some(FrameHeader {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos),
+            name,
+        }))
+    }
+    /// Get the underlying reader back.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+impl<R: Read + Seek> RecordReader<R> {
+    /// Go back to the first frame of the recording.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.inner
+            .seek(SeekFrom::Start(MAGIC.len() as u64 + 1))
+            .map(drop)
+    }
+}
+
+/// Like `read_exact`, but returns the number of bytes read if the reader ran out.
+fn read_fully(reader: &mut impl Read, mut buf: &mut [u8]) -> io::Result<usize> {
+    let len = buf.len();
+    while !buf.is_empty() {
+        match reader.read(buf) {
+            Ok(0) => break,
+            Ok(n) => buf = &mut buf[n..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len - buf.len())
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ReplayCameraConfig {
+    /// The recording to play back.
+    pub path: PathBuf,
+    /// Only play frames recorded from the camera with this name.
+    pub camera: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mode: PlaybackMode,
+}
+
+#[cfg_attr(feature = "serde", typetag::serde(name = "replay"))]
+impl CameraFactory for ReplayCameraConfig {
+    fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>> {
+        let _guard = info_span!("opening recording", path = %self.path.display()).entered();
+        let reader = RecordReader::open(&self.path)
+            .inspect_err(|err| error!(%err, "failed to open recording"))?;
+        let mut camera = ReplayCamera {
+            reader,
+            camera: self.camera.clone(),
+            mode: self.mode,
+            buffer: Buffer::empty_rgb(),
+            header: None,
+            next: None,
+            origin: None,
+        };
+        camera.next = camera.read_next(false)?;
+        let Some(next) = &camera.next else {
+            error!("recording has no frames");
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "recording has no frames",
+            ));
+        };
+        camera.buffer = Buffer::zeroed(next.buffer.width, next.buffer.height, next.buffer.format);
+        Ok(Box::new(camera))
+    }
+}
+
+/// A frame that's been read but not shown yet.
+#[derive(Debug)]
+struct PendingFrame {
+    header: FrameHeader,
+    buffer: Buffer<'static>,
+    /// This frame starts a new loop, so the timeline should restart with it.
+    restart: bool,
+}
+
+/// A camera that plays back a recording with its original timing.
+#[derive(Debug)]
+pub struct ReplayCamera {
+    reader: RecordReader<BufReader<File>>,
+    camera: Option<String>,
+    mode: PlaybackMode,
+    buffer: Buffer<'static>,
+    header: Option<FrameHeader>,
+    next: Option<PendingFrame>,
+    /// The real time that playback started, and the recorded time it corresponds to.
+    origin: Option<(Instant, SystemTime)>,
+}
+impl ReplayCamera {
+    /// Get the header of the current frame.
+    pub fn header(&self) -> Option<&FrameHeader> {
+        self.header.as_ref()
+    }
+    /// Read the next frame that matches our camera filter, looping if necessary.
+    fn read_next(&mut self, mut restart: bool) -> io::Result<Option<PendingFrame>> {
+        let mut buffer = Buffer::empty_rgb();
+        let mut looped = false;
+        loop {
+            let Some(header) = self.reader.read_frame(&mut buffer)? else {
+                if self.mode != PlaybackMode::Loop || looped {
+                    return Ok(None);
+                }
+                self.reader.rewind()?;
+                looped = true;
+                restart = true;
+                continue;
+            };
+            if self.camera.as_ref().is_none_or(|c| *c == header.name) {
+                return Ok(Some(PendingFrame {
+                    header,
+                    buffer,
+                    restart,
+                }));
+            }
+        }
+    }
+    /// Get the real time that a frame recorded at the given time should be shown.
+    fn due(&self, timestamp: SystemTime) -> Option<Instant> {
+        let (start, recorded) = self.origin?;
+        Some(start + timestamp.duration_since(recorded).unwrap_or_default())
+    }
+}
+impl CameraImpl for ReplayCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: self.buffer.width,
+            height: self.buffer.height,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        loop {
+            let Some(frame) = self.next.take() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "reached the end of the recording",
+                ));
+            };
+            self.next = self.read_next(false)?;
+            if frame.restart {
+                self.origin = None;
+            }
+            let due = self.due(frame.header.timestamp);
+            if self.mode != PlaybackMode::Step
+                && let Some(next) = &self.next
+                && !next.restart
+                && self
+                    .due(next.header.timestamp)
+                    .is_some_and(|d| d <= Instant::now())
+            {
+                debug!("skipping frame to keep up");
+                continue;
+            }
+            if let Some(due) = due {
+                let now = Instant::now();
+                if let Some(to_sleep) = due.checked_duration_since(now) {
+                    std::thread::sleep(to_sleep);
+                } else if self.mode == PlaybackMode::Step {
+                    // we can't skip frames, so shift the timeline to match reality
+                    if let Some((start, _)) = &mut self.origin {
+                        *start += now - due;
+                    }
+                }
+            } else {
+                self.origin = Some((Instant::now(), frame.header.timestamp));
+            }
+            self.buffer = frame.buffer;
+            self.header = Some(frame.header);
+            return Ok(());
+        }
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.buffer.borrow()
+    }
+}
@@ -0,0 +1,650 @@
+//! Cameras that render synthetic scenes, along with the ground truth for what's in them.
+//!
+//! Since the exact position of everything in the scene is known, these are useful for testing how accurately a pipeline finds things,
+//! without needing a physical camera or checked-in images.
+
+#[cfg(feature = "supply")]
+use super::FrameProvider;
+use super::{CameraFactory, CameraImpl, FrameMetadata, FrameSize};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+#[cfg(feature = "supply")]
+use supply::prelude::*;
+use tracing::{error, info_span};
+use vv_utils::common_types::Intrinsics;
+use vv_utils::geom::{EulerXYZ, Mat3, Vec3};
+use vv_vision::buffer::{Buffer, PixelFormat};
+
+/// An RGB color.
+pub type Rgb = [u8; 3];
+
+/// A value that can be linearly interpolated.
+pub trait Lerp: Copy {
+    /// Interpolate between `self` and `other`, where `t` goes from 0 to 1.
+    fn lerp(self, other: Self, t: f64) -> Self;
+}
+impl Lerp for f64 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+impl<const N: usize> Lerp for [f64; N] {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        std::array::from_fn(|i| self[i].lerp(other[i], t))
+    }
+}
+
+/// How a [`Trajectory`] continues after reaching its last waypoint.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum TrajectoryMode {
+    /// Move back to the first waypoint and start over.
+    #[default]
+    Loop,
+    /// Move back through the waypoints in reverse.
+    Bounce,
+    /// Stay at the last waypoint.
+    Once,
+}
+
+/// A value that can move over time.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum Trajectory<T> {
+    /// A value that doesn't move.
+    Fixed(T),
+    /// Move linearly between waypoints, taking the given number of frames between each one.
+    Waypoints {
+        waypoints: Vec<T>,
+        frames: NonZeroU32,
+        #[cfg_attr(feature = "serde", serde(default))]
+        mode: TrajectoryMode,
+    },
+}
+impl<T: Lerp> Trajectory<T> {
+    /// Get the value at a given frame.
+    ///
+    /// Panics if there are no waypoints.
+    pub fn at(&self, frame: u64) -> T {
+        let (waypoints, frames, mode) = match self {
+            Self::Fixed(value) => return *value,
+            Self::Waypoints {
+                waypoints,
+                frames,
+                mode,
+            } => (waypoints, frames, mode),
+        };
+        let last = waypoints.len() - 1;
+        if last == 0 {
+            return waypoints[0];
+        }
+        let pos = frame as f64 / frames.get() as f64;
+        let pos = match mode {
+            TrajectoryMode::Loop => pos % waypoints.len() as f64,
+            TrajectoryMode::Bounce => {
+                let period = (2 * last) as f64;
+                let pos = pos % period;
+                if pos > last as f64 { period - pos } else { pos }
+            }
+            TrajectoryMode::Once => pos.min(last as f64),
+        };
+        let i = pos.floor() as usize;
+        let next = if i == last { 0 } else { i + 1 };
+        waypoints[i].lerp(waypoints[next], pos.fract())
+    }
+    /// Check that this trajectory has at least one waypoint.
+    fn validate(&self) -> io::Result<()> {
+        if let Self::Waypoints { waypoints, .. } = self
+            && waypoints.is_empty()
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "trajectory has no waypoints",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The pose of a tag relative to the camera.
+///
+/// This uses the same coordinate system as AprilTag pose estimation: X is right, Y is down, and Z is forward from the camera. With no
+/// rotation, the tag faces the camera upright.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TagPose {
+    /// The position of the tag's center, in meters.
+    pub translation: [f64; 3],
+    /// The rotation of the tag, as XYZ Euler angles (roll, pitch, yaw) in degrees.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rotation: [f64; 3],
+}
+impl TagPose {
+    /// Get the rotation as a matrix.
+    pub fn rotation_matrix(&self) -> Mat3 {
+        Mat3::from_euler(EulerXYZ(self.rotation.map(f64::to_radians)))
+    }
+}
+impl Lerp for TagPose {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        Self {
+            translation: self.translation.lerp(other.translation, t),
+            rotation: self.rotation.lerp(other.rotation, t),
+        }
+    }
+}
+
+/// A shape to draw in a synthetic scene.
+///
+/// Positions and sizes are in pixels, except for tags, which are placed in 3D.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "shape", rename_all = "kebab-case"))]
+pub enum Shape {
+    /// A filled rectangle.
+    Rect {
+        center: Trajectory<[f64; 2]>,
+        width: f64,
+        height: f64,
+        /// The clockwise rotation of the rectangle, in degrees.
+        #[cfg_attr(feature = "serde", serde(default))]
+        angle: f64,
+        color: Rgb,
+    },
+    /// A filled circle.
+    Circle {
+        center: Trajectory<[f64; 2]>,
+        radius: f64,
+        color: Rgb,
+    },
+    /// An AprilTag, rendered in perspective.
+    ///
+    /// This requires the `apriltag` feature.
+    Tag {
+        /// The name of the tag family, like `tag36h11`.
+        family: String,
+        id: u32,
+        /// The width of the tag at the outer edge of its black border, in meters.
+        size: f64,
+        pose: Trajectory<TagPose>,
+    },
+}
+
+/// What kind of object an [`ObjectTruth`] describes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectKind {
+    Rect,
+    Circle {
+        radius: f64,
+    },
+    Tag {
+        family: String,
+        id: u32,
+        /// The translation of the tag's center from the camera, in meters.
+        translation: Vec3,
+        /// The rotation of the tag relative to the camera.
+        rotation: Mat3,
+    },
+}
+
+/// The ground truth for a single shape in a frame.
+///
+/// Positions are in pixel coordinates, where each pixel covers a unit square, so the center of the top-left pixel is at `(0.5, 0.5)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectTruth {
+    /// The index of the shape in the camera's configuration.
+    pub index: usize,
+    pub kind: ObjectKind,
+    /// The center of the object.
+    pub center: [f64; 2],
+    /// The bounding box of the object, as `[min_x, min_y, max_x, max_y]`.
+    ///
+    /// This isn't clipped to the frame.
+    pub bounds: [f64; 4],
+    /// The corners of the object, if it has any.
+    ///
+    /// For rectangles, these go clockwise from what would be the top-left corner without rotation. For tags, these are in the same order
+    /// as AprilTag detections: counter-clockwise from the bottom-left corner.
+    pub corners: Vec<[f64; 2]>,
+    /// Whether any of the object was drawn in the frame.
+    pub visible: bool,
+}
+
+/// The ground truth for a frame from a [`SyntheticCamera`].
+///
+/// This is provided as context for each frame, along with the camera's [`Intrinsics`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroundTruth {
+    /// The index of the frame, starting from zero.
+    pub frame: u64,
+    /// The intrinsics that the frame was rendered with.
+    pub intrinsics: Intrinsics,
+    /// Every shape in the scene, in the order that they were drawn.
+    pub objects: Vec<ObjectTruth>,
+}
+#[cfg(feature = "supply")]
+impl<'r> Provider<'r> for GroundTruth {
+    type Lifetimes = l!['r];
+    fn provide(&'r self, want: &mut dyn Want<Self::Lifetimes>) {
+        want.provide_value(self.intrinsics)
+            .provide::<GroundTruthTag, _>(|| self.clone());
+    }
+}
+
+/// Type tag for [`GroundTruth`].
+#[cfg(feature = "supply")]
+#[ty_tag::tag]
+pub type GroundTruthTag = GroundTruth;
+
+/// Configuration for a [`SyntheticCamera`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SyntheticCameraConfig {
+    pub width: u32,
+    pub height: u32,
+    /// The color behind all of the shapes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub background_color: Rgb,
+    /// The shapes to draw, from back to front.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub shapes: Vec<Shape>,
+    /// The intrinsics to render tags with.
+    ///
+    /// If this isn't set, the focal length is the frame width and the principal point is the center of the frame. These are provided
+    /// with the ground truth, but the camera's own `intrinsics` take priority if they're set.
+    pub render_intrinsics: Option<Intrinsics>,
+    /// The standard deviation of a Gaussian blur, in pixels.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub blur: f32,
+    /// The standard deviation of Gaussian noise added to each channel.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub noise: f64,
+    /// The seed for the noise, so that runs are reproducible.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: u64,
+}
+impl SyntheticCameraConfig {
+    /// Get the intrinsics that frames are rendered with.
+    pub fn intrinsics(&self) -> Intrinsics {
+        let size = FrameSize {
+            width: self.width,
+            height: self.height,
+        };
+        self.render_intrinsics.map_or_else(
+            || Intrinsics {
+                width: self.width,
+                height: self.height,
+                fx: self.width as f64,
+                fy: self.width as f64,
+                cx: self.width as f64 / 2.0,
+                cy: self.height as f64 / 2.0,
+                distortion: Default::default(),
+            },
+            |i| i.scaled_to(size),
+        )
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "synthetic"))]
+impl CameraFactory for SyntheticCameraConfig {
+    fn build_camera(&self) -> io::Result<Box<dyn CameraImpl>> {
+        let _guard = info_span!("building synthetic camera").entered();
+        let tags = self
+            .shapes
+            .iter()
+            .map(|shape| match shape {
+                Shape::Rect { center, .. } | Shape::Circle { center, .. } => {
+                    center.validate().map(|_| None)
+                }
+                Shape::Tag {
+                    family, id, pose, ..
+                } => {
+                    pose.validate()?;
+                    TagPattern::new(family, *id).map(Some)
+                }
+            })
+            .collect::<io::Result<Vec<_>>>()
+            .inspect_err(|err| error!(%err, "invalid scene"))?;
+        Ok(Box::new(SyntheticCamera {
+            intrinsics: self.intrinsics(),
+            config: self.clone(),
+            tags,
+            frame: Buffer::empty_rgb(),
+            aux: Buffer::empty_rgb(),
+            index: 0,
+            truth: None,
+        }))
+    }
+}
+
+/// The cells of a tag, with one byte per cell.
+#[derive(Debug, Clone, PartialEq)]
+struct TagPattern {
+    cells: Vec<u8>,
+    total_width: u32,
+    width_at_border: u32,
+}
+impl TagPattern {
+    #[cfg(feature = "apriltag")]
+    fn new(family: &str, id: u32) -> io::Result<Self> {
+        let parsed = family
+            .parse::<vv_apriltag::TagFamily>()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let tag = parsed.render(id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{family} has no tag with ID {id}"),
+            )
+        })?;
+        Ok(Self {
+            total_width: tag.image.width,
+            width_at_border: tag.width_at_border,
+            cells: tag.image.data.into_owned(),
+        })
+    }
+    #[cfg(not(feature = "apriltag"))]
+    fn new(_family: &str, _id: u32) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "rendering tags requires the apriltag feature",
+        ))
+    }
+}
+
+/// A small, fast, deterministic random number generator for noise.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// Get a uniform value in `(0, 1]`.
+    fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+    /// Get a pair of values from a standard normal distribution, using the Box-Muller transform.
+    fn next_gaussian_pair(&mut self) -> [f64; 2] {
+        let r = (-2.0 * self.next_f64().ln()).sqrt();
+        let (s, c) = (std::f64::consts::TAU * self.next_f64()).sin_cos();
+        [r * c, r * s]
+    }
+}
+
+/// Call `f` with every pixel whose center is in a bounding box, clipped to the frame.
+fn for_pixels_in(
+    frame: &mut Buffer<'_>,
+    [min_x, min_y, max_x, max_y]: [f64; 4],
+    mut f: impl FnMut(f64, f64) -> Option<Rgb>,
+) -> bool {
+    let clip = |v: f64, max: u32| (v - 0.5).ceil().clamp(0.0, max as f64) as u32;
+    let x0 = clip(min_x, frame.width);
+    let x1 = clip(max_x, frame.width);
+    let y0 = clip(min_y, frame.height);
+    let y1 = clip(max_y, frame.height);
+    let mut drawn = false;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            if let Some(color) = f(x as f64 + 0.5, y as f64 + 0.5) {
+                frame.set_pixel(x, y, &color);
+                drawn = true;
+            }
+        }
+    }
+    drawn
+}
+
+/// Get the bounding box of a set of points.
+fn bounds_of(points: &[[f64; 2]]) -> [f64; 4] {
+    points.iter().fold(
+        [
+            f64::INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NEG_INFINITY,
+        ],
+        |[x0, y0, x1, y1], &[x, y]| [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+    )
+}
+
+/// A camera that renders a synthetic scene.
+///
+/// Each frame is rendered when it's loaded, and the scene advances by one frame each time.
+#[derive(Debug)]
+pub struct SyntheticCamera {
+    config: SyntheticCameraConfig,
+    intrinsics: Intrinsics,
+    /// The tag pattern for each shape, if it's a tag.
+    tags: Vec<Option<TagPattern>>,
+    frame: Buffer<'static>,
+    aux: Buffer<'static>,
+    /// The index of the next frame.
+    index: u64,
+    truth: Option<Arc<GroundTruth>>,
+}
+impl SyntheticCamera {
+    /// Get the ground truth for the last frame rendered.
+    pub fn ground_truth(&self) -> Option<&Arc<GroundTruth>> {
+        self.truth.as_ref()
+    }
+    /// Render a frame into `self.frame`, returning the ground truth for it.
+    pub fn render(&mut self, index: u64) -> GroundTruth {
+        let SyntheticCameraConfig {
+            width,
+            height,
+            background_color,
+            ref shapes,
+            blur,
+            noise,
+            seed,
+            ..
+        } = self.config;
+        self.frame = Buffer::monochrome(width, height, PixelFormat::RGB, &background_color);
+        let objects = shapes
+            .iter()
+            .zip(&self.tags)
+            .enumerate()
+            .map(|(i, (shape, tag))| {
+                render_shape(
+                    &mut self.frame,
+                    &self.intrinsics,
+                    i,
+                    shape,
+                    tag.as_ref(),
+                    index,
+                )
+            })
+            .collect();
+        if blur > 0.0 {
+            let window = 2 * (blur * 3.0).ceil() as usize + 1;
+            vv_vision::vision::gaussian_blur(&mut self.frame, &mut self.aux, blur, window, window);
+        }
+        if noise > 0.0 {
+            let mut rng = SplitMix64(seed ^ index.wrapping_mul(0x2545f4914f6cdd1d));
+            for px in self.frame.resize_data().chunks_mut(2) {
+                let pair = rng.next_gaussian_pair();
+                for (v, n) in px.iter_mut().zip(pair) {
+                    *v = (*v as f64 + n * noise).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        GroundTruth {
+            frame: index,
+            intrinsics: self.intrinsics,
+            objects,
+        }
+    }
+}
+
+/// Draw a shape into a frame, returning the ground truth for it.
+fn render_shape(
+    image: &mut Buffer<'_>,
+    intrinsics: &Intrinsics,
+    index: usize,
+    shape: &Shape,
+    tag: Option<&TagPattern>,
+    frame: u64,
+) -> ObjectTruth {
+    match shape {
+        Shape::Rect {
+            center,
+            width,
+            height,
+            angle,
+            color,
+        } => {
+            let center @ [cx, cy] = center.at(frame);
+            let (s, c) = angle.to_radians().sin_cos();
+            let [hw, hh] = [width / 2.0, height / 2.0];
+            let corners = [[-hw, -hh], [hw, -hh], [hw, hh], [-hw, hh]]
+                .map(|[x, y]| [cx + x * c - y * s, cy + x * s + y * c])
+                .to_vec();
+            let bounds = bounds_of(&corners);
+            let visible = for_pixels_in(image, bounds, |x, y| {
+                let [dx, dy] = [x - cx, y - cy];
+                let lx = dx * c + dy * s;
+                let ly = dy * c - dx * s;
+                (-hw <= lx && lx < hw && -hh <= ly && ly < hh).then_some(*color)
+            });
+            ObjectTruth {
+                index,
+                kind: ObjectKind::Rect,
+                center,
+                bounds,
+                corners,
+                visible,
+            }
+        }
+        Shape::Circle {
+            center,
+            radius,
+            color,
+        } => {
+            let center @ [cx, cy] = center.at(frame);
+            let bounds = [cx - radius, cy - radius, cx + radius, cy + radius];
+            let r2 = radius * radius;
+            let visible = for_pixels_in(image, bounds, |x, y| {
+                ((x - cx).powi(2) + (y - cy).powi(2) < r2).then_some(*color)
+            });
+            ObjectTruth {
+                index,
+                kind: ObjectKind::Circle { radius: *radius },
+                center,
+                bounds,
+                corners: Vec::new(),
+                visible,
+            }
+        }
+        Shape::Tag {
+            family,
+            id,
+            size,
+            pose,
+        } => {
+            let pattern = tag.expect("tag shapes should have a pattern");
+            let pose = pose.at(frame);
+            let rotation = pose.rotation_matrix();
+            let translation = Vec3(pose.translation);
+            let Intrinsics { fx, fy, cx, cy, .. } = *intrinsics;
+            let to_camera = |u: f64, v: f64| rotation.mul_vec(Vec3([u, v, 0.0])) + translation;
+            let project = |p: Vec3| [fx * p.x() / p.z() + cx, fy * p.y() / p.z() + cy];
+            let half = size / 2.0;
+            let points = [[-half, half], [half, half], [half, -half], [-half, -half]]
+                .map(|[u, v]| to_camera(u, v));
+            let corners = points.map(project).to_vec();
+            // the tag's Z axis points away from the front of the tag
+            let facing = rotation.mul_vec(Vec3([0.0, 0.0, 1.0])).dot(translation) > 0.0;
+            let mut visible = false;
+            if facing && points.iter().all(|p| p.z() > 0.0) {
+                // tag plane coordinates to pixels, and back
+                let [r0, r1, _, r3, r4, _, r6, r7, _] = rotation.0;
+                let Vec3([tx, ty, tz]) = translation;
+                let homography = Mat3([
+                    fx * r0 + cx * r6,
+                    fx * r1 + cx * r7,
+                    fx * tx + cx * tz,
+                    fy * r3 + cy * r6,
+                    fy * r4 + cy * r7,
+                    fy * ty + cy * tz,
+                    r6,
+                    r7,
+                    tz,
+                ]);
+                let inverse = homography.inverse();
+                let TagPattern {
+                    cells,
+                    total_width,
+                    width_at_border,
+                } = pattern;
+                let cell_size = size / *width_at_border as f64;
+                let border_start = (total_width - width_at_border) / 2;
+                let outer = half + border_start as f64 * cell_size;
+                let outer_corners = [
+                    [-outer, outer],
+                    [outer, outer],
+                    [outer, -outer],
+                    [-outer, -outer],
+                ]
+                .map(|[u, v]| project(to_camera(u, v)));
+                visible = for_pixels_in(image, bounds_of(&outer_corners), |x, y| {
+                    let Vec3([u, v, w]) = inverse.mul_vec(Vec3([x, y, 1.0]));
+                    let gx = ((u / w + outer) / cell_size).floor();
+                    let gy = ((v / w + outer) / cell_size).floor();
+                    let tw = *total_width as f64;
+                    if !(0.0..tw).contains(&gx) || !(0.0..tw).contains(&gy) {
+                        return None;
+                    }
+                    let value = cells[gy as usize * *total_width as usize + gx as usize];
+                    Some([value; 3])
+                });
+            }
+            ObjectTruth {
+                index,
+                kind: ObjectKind::Tag {
+                    family: family.clone(),
+                    id: *id,
+                    translation,
+                    rotation,
+                },
+                center: project(translation),
+                bounds: bounds_of(&corners),
+                corners,
+                visible,
+            }
+        }
+    }
+}
+impl CameraImpl for SyntheticCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: self.config.width,
+            height: self.config.height,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        let truth = self.render(self.index);
+        self.truth = Some(Arc::new(truth));
+        self.index += 1;
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.frame.borrow()
+    }
+    fn frame_metadata(&self) -> FrameMetadata {
+        FrameMetadata {
+            timestamp: None,
+            sequence: self.truth.as_ref().map(|t| t.frame),
+        }
+    }
+    #[cfg(feature = "supply")]
+    fn frame_context(&self) -> Option<Arc<dyn FrameProvider>> {
+        self.truth.clone().map(|t| t as _)
+    }
+}
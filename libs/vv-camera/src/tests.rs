@@ -0,0 +1,1515 @@
+use super::background::*;
+use super::fault::*;
+use super::frame::{PlaybackMode, SequenceCameraConfig, SequenceSource};
+use super::group::*;
+use super::health::*;
+use super::hotplug::*;
+use super::mjpeg::*;
+use super::record::*;
+use super::synthetic::*;
+use super::{
+    Camera, CameraConfig, CameraFactory, CameraImpl, CameraMetadata, CameraQuerier, FrameContext,
+    FrameMetadata, FullCameraMetadata, orient_intrinsics,
+};
+use std::io::{self, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+use vv_utils::common_types::{Distortion, Fov, FrameSize, FrameTimestamp, Intrinsics, PipelineId};
+use vv_vision::buffer::{Buffer, PixelFormat};
+use vv_vision::vision::{BlobsIterator, CropRect, Flip, Orientation, Rotation, orient};
+use zune_png::PngEncoder;
+use zune_png::zune_core::bit_depth::BitDepth;
+use zune_png::zune_core::colorspace::ColorSpace;
+use zune_png::zune_core::options::EncoderOptions;
+
+#[test]
+fn record_round_trip() {
+    let frames = [
+        Buffer::monochrome(4, 3, PixelFormat::RGB, &[1, 2, 3]),
+        Buffer::monochrome(6, 2, PixelFormat::YUYV, &[10, 20]),
+        Buffer::monochrome(2, 2, PixelFormat::anon(5).unwrap(), &[5, 4, 3, 2, 1]),
+    ];
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut writer = RecordWriter::new(Vec::new()).unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        let timestamp = start + Duration::from_millis(33 * i as u64);
+        writer
+            .write_frame("front", timestamp, frame.borrow())
+            .unwrap();
+    }
+    let data = writer.into_inner();
+
+    let mut reader = RecordReader::new(Cursor::new(data)).unwrap();
+    let mut buffer = Buffer::empty_rgb();
+    for (i, frame) in frames.iter().enumerate() {
+        let header = reader.read_frame(&mut buffer).unwrap().unwrap();
+        assert_eq!(header.name, "front");
+        assert_eq!(
+            header.timestamp,
+            start + Duration::from_millis(33 * i as u64)
+        );
+        assert_eq!(&buffer, frame);
+    }
+    assert!(reader.read_frame(&mut buffer).unwrap().is_none());
+    reader.rewind().unwrap();
+    assert!(reader.read_frame(&mut buffer).unwrap().is_some());
+    assert_eq!(buffer, frames[0]);
+}
+
+#[test]
+fn record_truncated() {
+    let mut writer = RecordWriter::new(Vec::new()).unwrap();
+    let frame = Buffer::monochrome(4, 4, PixelFormat::LUMA, &[7]);
+    writer
+        .write_frame("front", SystemTime::now(), frame.borrow())
+        .unwrap();
+    let mut data = writer.into_inner();
+    data.truncate(data.len() - 3);
+    let mut reader = RecordReader::new(Cursor::new(data)).unwrap();
+    let mut buffer = Buffer::empty_rgb();
+    assert!(reader.read_frame(&mut buffer).is_err());
+    assert!(RecordReader::new(Cursor::new(b"VVRAW\x01".to_vec())).is_err());
+
+    // a corrupt header claiming a huge frame shouldn't allocate it
+    let mut data = MAGIC.to_vec();
+    data.push(VERSION);
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.extend_from_slice(&65535u32.to_le_bytes());
+    data.extend_from_slice(&65535u32.to_le_bytes());
+    data.push(PixelFormat::LUMA.0.get());
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data.extend_from_slice(&[0; 16]);
+    let mut reader = RecordReader::new(Cursor::new(data)).unwrap();
+    let err = reader.read_frame(&mut buffer).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(buffer.data.to_mut().capacity() < 1 << 20);
+}
+
+/// Write a recording of 1x1 frames at the given times in milliseconds, with each frame's value being its index.
+fn write_recording(name: &str, times: &[u64]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("vv-{name}-{}.vvrec", std::process::id()));
+    let mut writer = RecordWriter::create(&path).unwrap();
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    for (i, &ms) in times.iter().enumerate() {
+        let frame = Buffer::monochrome(1, 1, PixelFormat::LUMA, &[i as u8]);
+        writer
+            .write_frame("front", start + Duration::from_millis(ms), frame.borrow())
+            .unwrap();
+    }
+    writer.flush().unwrap();
+    path
+}
+
+fn replay_camera(path: &Path, mode: PlaybackMode) -> Box<dyn CameraImpl> {
+    ReplayCameraConfig {
+        path: path.to_path_buf(),
+        camera: None,
+        mode,
+    }
+    .build_camera()
+    .unwrap()
+}
+
+fn next_value(camera: &mut dyn CameraImpl) -> io::Result<u8> {
+    camera.load_frame()?;
+    Ok(camera.get_frame().data[0])
+}
+
+#[test]
+fn replay_timing() {
+    let path = write_recording("replay-timing", &[0, 50, 100]);
+    let mut camera = replay_camera(&path, PlaybackMode::Once);
+    let start = Instant::now();
+    assert_eq!(next_value(&mut *camera).unwrap(), 0);
+    assert_eq!(next_value(&mut *camera).unwrap(), 1);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(next_value(&mut *camera).unwrap(), 2);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    let err = next_value(&mut *camera).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replay_loops_and_skips() {
+    let path = write_recording("replay-loop", &[0, 10, 20, 300]);
+    let mut camera = replay_camera(&path, PlaybackMode::Loop);
+    assert_eq!(next_value(&mut *camera).unwrap(), 0);
+    std::thread::sleep(Duration::from_millis(50));
+    // the second frame is overdue and the third is due, so it skips to the third
+    assert_eq!(next_value(&mut *camera).unwrap(), 2);
+    assert_eq!(next_value(&mut *camera).unwrap(), 3);
+    // looping restarts the timeline, so the first frame is shown right away
+    let start = Instant::now();
+    assert_eq!(next_value(&mut *camera).unwrap(), 0);
+    assert!(start.elapsed() < Duration::from_millis(250));
+    assert_eq!(next_value(&mut *camera).unwrap(), 1);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn replay_step() {
+    let path = write_recording("replay-step", &[0, 10, 20]);
+    let mut camera = replay_camera(&path, PlaybackMode::Step);
+    assert_eq!(next_value(&mut *camera).unwrap(), 0);
+    std::thread::sleep(Duration::from_millis(50));
+    // stepping never skips frames, even when they're overdue
+    assert_eq!(next_value(&mut *camera).unwrap(), 1);
+    assert_eq!(next_value(&mut *camera).unwrap(), 2);
+    let err = next_value(&mut *camera).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    std::fs::remove_file(path).unwrap();
+}
+
+/// Write PNGs for a sequence into a new directory, with each image being one pixel tall and as wide as its index.
+///
+/// They're named so that sorting by name puts them in order, but listing the directory might not.
+fn write_sequence(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vv-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    for (file, width) in [("c.png", 3), ("a.png", 1), ("b.png", 2)] {
+        let data = vec![width as u8; width];
+        let options = EncoderOptions::new(width, 1, ColorSpace::Luma, BitDepth::Eight);
+        let png = PngEncoder::new(&data, options).encode();
+        std::fs::write(dir.join(file), png).unwrap();
+    }
+    std::fs::write(dir.join("notes.txt"), "not an image").unwrap();
+    dir
+}
+
+fn sequence_camera(source: SequenceSource, mode: PlaybackMode) -> Box<dyn CameraImpl> {
+    SequenceCameraConfig {
+        source,
+        fps: None,
+        mode,
+    }
+    .build_camera()
+    .unwrap()
+}
+
+/// Read the next frame from a sequence made by [`write_sequence`], returning its width.
+fn next_width(camera: &mut dyn CameraImpl) -> io::Result<u32> {
+    camera.load_frame()?;
+    Ok(camera.get_frame().width)
+}
+
+#[test]
+fn sequence_dir_order() {
+    let dir = write_sequence("sequence-dir");
+    let source = SequenceSource::Dir { dir: dir.clone() };
+    assert_eq!(
+        source.paths().unwrap(),
+        ["a.png", "b.png", "c.png"].map(|f| dir.join(f))
+    );
+    for fps in [0.0, -30.0, f64::NAN, f64::INFINITY] {
+        let config = SequenceCameraConfig {
+            source: source.clone(),
+            fps: Some(fps),
+            mode: PlaybackMode::Once,
+        };
+        let err = config.build_camera().err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{fps}");
+    }
+    let mut camera = sequence_camera(source, PlaybackMode::Once);
+    assert_eq!(next_width(&mut *camera).unwrap(), 1);
+    assert_eq!(next_width(&mut *camera).unwrap(), 2);
+    assert_eq!(next_width(&mut *camera).unwrap(), 3);
+    let err = next_width(&mut *camera).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sequence_glob_loops() {
+    let dir = write_sequence("sequence-glob");
+    let source = SequenceSource::Glob {
+        glob: dir.join("[bc].*").to_str().unwrap().to_string(),
+    };
+    assert_eq!(
+        source.paths().unwrap(),
+        ["b.png", "c.png"].map(|f| dir.join(f))
+    );
+    let mut camera = sequence_camera(source, PlaybackMode::Loop);
+    let widths = (0..5)
+        .map(|_| next_width(&mut *camera).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(widths, [2, 3, 2, 3, 2]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// A camera that counts up, with each frame's value being its sequence number.
+struct CountingCamera {
+    frame: Buffer<'static>,
+    next: u8,
+    limit: u8,
+    /// Signaled when the camera runs out of frames, and disconnected when it's dropped.
+    done: Option<mpsc::Sender<()>>,
+}
+impl CountingCamera {
+    fn new(limit: u8) -> Self {
+        Self {
+            frame: Buffer::empty_rgb(),
+            next: 0,
+            limit,
+            done: None,
+        }
+    }
+    fn querier(limit: u8) -> CameraQuerier {
+        CameraQuerier::new(Box::new(Self::new(limit)))
+    }
+}
+impl CameraImpl for CountingCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: 1,
+            height: 1,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        if self.next == self.limit {
+            if let Some(done) = &self.done {
+                let _ = done.send(());
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.frame = Buffer::monochrome(1, 1, PixelFormat::LUMA, &[self.next]);
+        self.next += 1;
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.frame.borrow()
+    }
+    fn frame_metadata(&self) -> FrameMetadata {
+        FrameMetadata {
+            timestamp: None,
+            sequence: Some(self.next as u64 - 1),
+        }
+    }
+}
+
+#[test]
+fn background_queue() {
+    let meta = CameraMetadata::new("counter".to_string());
+    let policy = BackgroundPolicy::Queue(NonZeroUsize::new(8).unwrap());
+    let mut querier = BackgroundQuerier::spawn(CountingCamera::querier(5), meta, policy).unwrap();
+    for i in 0..5 {
+        querier.load_frame().unwrap();
+        assert_eq!(*querier.get_frame().data, [i]);
+        assert_eq!(querier.frame_timestamp().unwrap().sequence, Some(i as u64));
+    }
+    let err = querier.load_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn background_latest() {
+    let meta = CameraMetadata::new("counter".to_string());
+    let (done_tx, done_rx) = mpsc::channel();
+    let camera = CountingCamera {
+        done: Some(done_tx),
+        ..CountingCamera::new(5)
+    };
+    let mut querier = BackgroundQuerier::spawn(
+        CameraQuerier::new(Box::new(camera)),
+        meta,
+        BackgroundPolicy::Latest,
+    )
+    .unwrap();
+    // wait for the capture thread to read every frame
+    done_rx.recv().unwrap();
+    querier.load_frame().unwrap();
+    assert_eq!(*querier.get_frame().data, [4]);
+    assert!(querier.load_frame().is_err());
+}
+
+#[test]
+fn background_stops_on_drop() {
+    let meta = CameraMetadata::new("counter".to_string());
+    let (done_tx, done_rx) = mpsc::channel::<()>();
+    let camera = CountingCamera {
+        done: Some(done_tx),
+        ..CountingCamera::new(u8::MAX)
+    };
+    let faults = FaultSchedule {
+        fail_every: NonZeroU32::new(1),
+        ..Default::default()
+    };
+    let mut querier = CameraQuerier::new(Box::new(FaultyCamera::new(Box::new(camera), faults)));
+    querier.set_reconnect_policy(NO_DELAY);
+    let mut querier = BackgroundQuerier::spawn(querier, meta, BackgroundPolicy::Latest).unwrap();
+    // every read fails, and the capture thread keeps retrying without a delay from the policy
+    assert!(querier.load_frame().is_err());
+    assert!(querier.load_frame().is_err());
+    drop(querier);
+    // the capture thread was joined, so the camera and its sender are gone
+    assert_eq!(done_rx.try_recv(), Err(mpsc::TryRecvError::Disconnected));
+}
+
+/// A camera that reports the given metadata for each of its frames.
+struct StampedCamera {
+    frames: Vec<FrameMetadata>,
+    next: usize,
+}
+impl CameraImpl for StampedCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: 1,
+            height: 1,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        if self.next == self.frames.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.next += 1;
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        Buffer::monochrome(1, 1, PixelFormat::LUMA, &[0])
+    }
+    fn frame_metadata(&self) -> FrameMetadata {
+        self.frames[self.next - 1]
+    }
+}
+
+#[test]
+fn frame_timestamps() {
+    let captured = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let frames = vec![
+        FrameMetadata {
+            timestamp: Some(captured),
+            sequence: Some(7),
+        },
+        FrameMetadata {
+            timestamp: None,
+            sequence: Some(9),
+        },
+        FrameMetadata::default(),
+    ];
+    let meta = CameraMetadata::new("stamped".to_string());
+    let camera = StampedCamera { frames, next: 0 };
+    let mut querier = FrameQuerier::new(CameraQuerier::new(Box::new(camera)), &meta).unwrap();
+    let full_meta = FullCameraMetadata {
+        meta: meta.clone(),
+        id: PipelineId::from_hash("stamped"),
+        size: FrameSize {
+            width: 1,
+            height: 1,
+        },
+    };
+    let mut next = || {
+        querier.load_frame(&meta).unwrap();
+        let context = FrameContext::new(&full_meta, &querier);
+        #[cfg(feature = "supply")]
+        {
+            use supply::prelude::*;
+            use vv_utils::common_types::FrameTimestampTag;
+            assert_eq!(
+                context.request::<FrameTimestampTag>(),
+                context.timestamp,
+                "the timestamp should be provided to components"
+            );
+        }
+        assert_eq!(context.timestamp, querier.frame_timestamp());
+        querier.frame_timestamp().unwrap()
+    };
+    // the camera's own capture time and sequence number are kept
+    assert_eq!(
+        next(),
+        FrameTimestamp {
+            captured,
+            sequence: Some(7),
+        }
+    );
+    // without a capture time from the camera, it's when the frame was read
+    let before = SystemTime::now();
+    let stamp = next();
+    assert!(stamp.captured >= before && stamp.captured <= SystemTime::now());
+    assert_eq!(stamp.sequence, Some(9));
+    assert_eq!(next().sequence, None);
+}
+
+/// A camera that fails a given number of times before producing frames.
+struct FlakyCamera {
+    failures: usize,
+    frame: Buffer<'static>,
+}
+impl CameraImpl for FlakyCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: self.frame.width,
+            height: self.frame.height,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(io::Error::other("unplugged"));
+        }
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.frame.borrow()
+    }
+    fn reload(&mut self) -> bool {
+        true
+    }
+}
+
+fn flaky_querier(failures: usize, give_up_after: Option<u32>) -> CameraQuerier {
+    let mut querier = CameraQuerier::new(Box::new(FlakyCamera {
+        failures,
+        frame: Buffer::monochrome(1, 1, PixelFormat::LUMA, &[0]),
+    }));
+    querier.set_reconnect_policy(ReconnectPolicy {
+        initial_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(4),
+        give_up_after,
+        min_reload_interval: Duration::ZERO,
+    });
+    querier
+}
+
+#[test]
+fn reconnect_recovers() {
+    let meta = CameraMetadata::new("flaky".to_string());
+    let mut querier = flaky_querier(2, None);
+    assert_eq!(querier.health().status, CameraStatus::Starting);
+    assert!(querier.load_frame(&meta).is_err());
+    assert!(querier.load_frame(&meta).is_err());
+    let health = querier.health();
+    assert_eq!(health.status, CameraStatus::Failing);
+    assert_eq!(health.fail_count, 2);
+    assert_eq!(health.reload_count, 2);
+    querier.load_frame(&meta).unwrap();
+    let health = querier.health();
+    assert_eq!(health.status, CameraStatus::Connected);
+    assert_eq!(health.fail_count, 0);
+    assert_eq!(health.last_error.as_deref(), Some("unplugged"));
+    assert!(health.since_last_frame().is_some());
+}
+
+#[test]
+fn reconnect_gives_up() {
+    let meta = CameraMetadata::new("flaky".to_string());
+    let mut querier = flaky_querier(10, Some(3));
+    for _ in 0..3 {
+        assert!(querier.load_frame(&meta).is_err());
+    }
+    assert_eq!(querier.health().status, CameraStatus::GaveUp);
+    let err = querier.load_frame(&meta).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    assert_eq!(querier.health().fail_count, 3);
+}
+
+#[test]
+fn reconnect_without_reload() {
+    // the counting camera can't be reloaded, like the frame and replay cameras
+    let meta = CameraMetadata::new("counter".to_string());
+    let mut querier = faulty_querier(
+        FaultSchedule {
+            fail_every: NonZeroU32::new(1),
+            ..Default::default()
+        },
+        NO_DELAY,
+    );
+    for _ in 0..3 {
+        assert!(querier.load_frame(&meta).is_err());
+    }
+    let health = querier.health();
+    assert_eq!(health.status, CameraStatus::Failing);
+    assert_eq!(health.fail_count, 3);
+    // it's only asked to reload once per outage
+    assert_eq!(health.reload_count, 1);
+}
+
+#[test]
+fn reconnect_eof_returns_immediately() {
+    let meta = CameraMetadata::new("counter".to_string());
+    let mut querier = CountingCamera::querier(1);
+    querier.set_reconnect_policy(ReconnectPolicy {
+        initial_delay: Duration::from_secs(10),
+        ..NO_DELAY
+    });
+    querier.load_frame(&meta).unwrap();
+    let start = Instant::now();
+    let err = querier.load_frame(&meta).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(querier.health().reload_count, 0);
+}
+
+/// Wrap a counting camera in a faulty one.
+fn faulty_querier(faults: FaultSchedule, policy: ReconnectPolicy) -> CameraQuerier {
+    let inner = CountingCamera::new(u8::MAX);
+    let mut querier = CameraQuerier::new(Box::new(FaultyCamera::new(Box::new(inner), faults)));
+    querier.set_reconnect_policy(policy);
+    querier
+}
+
+const NO_DELAY: ReconnectPolicy = ReconnectPolicy {
+    initial_delay: Duration::ZERO,
+    max_delay: Duration::ZERO,
+    give_up_after: None,
+    min_reload_interval: Duration::ZERO,
+};
+
+#[test]
+fn fault_read_failures() {
+    let meta = CameraMetadata::new("faulty".to_string());
+    let faults = FaultSchedule {
+        fail_every: NonZeroU32::new(3),
+        ..Default::default()
+    };
+    let mut querier = faulty_querier(faults, NO_DELAY);
+    let mut next = 0;
+    for read in 1..=6 {
+        if read % 3 == 0 {
+            let err = querier.load_frame(&meta).unwrap_err();
+            assert_eq!(err.to_string(), "injected read failure");
+            let health = querier.health();
+            assert_eq!(health.status, CameraStatus::Failing);
+            assert_eq!(health.fail_count, 1);
+            assert_eq!(health.reload_count, 1);
+        } else {
+            assert_eq!(*querier.read(&meta).unwrap().data, [next]);
+            assert_eq!(querier.health().fail_count, 0);
+            next += 1;
+        }
+    }
+}
+
+#[test]
+fn fault_reload_failures() {
+    let meta = CameraMetadata::new("faulty".to_string());
+    let faults = FaultSchedule {
+        disconnect_every: NonZeroU32::new(4),
+        reload_failures: 2,
+        ..Default::default()
+    };
+    let mut querier = faulty_querier(faults, NO_DELAY);
+    for i in 0..3 {
+        assert_eq!(*querier.read(&meta).unwrap().data, [i]);
+    }
+    // the disconnect and the two reads after it fail, and the reload after the third one succeeds
+    for _ in 0..3 {
+        let err = querier.load_frame(&meta).unwrap_err();
+        assert_eq!(err.to_string(), "injected disconnect");
+    }
+    let health = querier.health();
+    assert_eq!(health.status, CameraStatus::Failing);
+    assert_eq!(health.fail_count, 3);
+    assert_eq!(health.reload_count, 3);
+    assert_eq!(*querier.read(&meta).unwrap().data, [3]);
+    let health = querier.health();
+    assert_eq!(health.status, CameraStatus::Connected);
+    assert_eq!(health.reload_count, 0);
+}
+
+#[test]
+fn fault_gives_up() {
+    let meta = CameraMetadata::new("faulty".to_string());
+    let faults = FaultSchedule {
+        disconnect_every: NonZeroU32::new(1),
+        reload_failures: 10,
+        ..Default::default()
+    };
+    let policy = ReconnectPolicy {
+        give_up_after: Some(3),
+        ..NO_DELAY
+    };
+    let mut querier = faulty_querier(faults, policy);
+    for _ in 0..3 {
+        assert!(querier.load_frame(&meta).is_err());
+    }
+    assert_eq!(querier.health().status, CameraStatus::GaveUp);
+    let err = querier.load_frame(&meta).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+}
+
+#[test]
+fn fault_backoff() {
+    let meta = CameraMetadata::new("faulty".to_string());
+    let faults = FaultSchedule {
+        fail_every: NonZeroU32::new(1),
+        ..Default::default()
+    };
+    let policy = ReconnectPolicy {
+        initial_delay: Duration::from_millis(5),
+        max_delay: Duration::from_millis(10),
+        give_up_after: None,
+        min_reload_interval: Duration::from_secs(3600),
+    };
+    let mut querier = faulty_querier(faults, policy);
+    let start = Instant::now();
+    for _ in 0..3 {
+        assert!(querier.load_frame(&meta).is_err());
+    }
+    // 5ms, then 10ms twice since the delay is capped
+    assert!(start.elapsed() >= Duration::from_millis(25));
+    let health = querier.health();
+    assert_eq!(health.fail_count, 3);
+    // only the first failure reloads, the rest are within the minimum interval
+    assert_eq!(health.reload_count, 1);
+}
+
+#[test]
+fn fault_stalls() {
+    let meta = CameraMetadata::new("faulty".to_string());
+    let faults = FaultSchedule {
+        stall_every: NonZeroU32::new(2),
+        stall_for: Duration::from_millis(20),
+        ..Default::default()
+    };
+    let mut querier = faulty_querier(faults, NO_DELAY);
+    querier.load_frame(&meta).unwrap();
+    let start = Instant::now();
+    querier.load_frame(&meta).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(querier.health().status, CameraStatus::Connected);
+}
+
+#[test]
+fn fault_resizes() {
+    let inner = FlakyCamera {
+        failures: 0,
+        frame: Buffer::monochrome(4, 2, PixelFormat::LUMA, &[9]),
+    };
+    let faults = FaultSchedule {
+        resize_every: NonZeroU32::new(2),
+        resize_to: Some(FrameSize {
+            width: 2,
+            height: 1,
+        }),
+        ..Default::default()
+    };
+    let mut querier = CameraQuerier::new(Box::new(FaultyCamera::new(Box::new(inner), faults)));
+    let mut meta = CameraMetadata::new("faulty".to_string());
+    let sizes = [(4, 2), (2, 1), (2, 1), (4, 2)];
+    for size in sizes {
+        let frame = querier.read(&meta).unwrap();
+        assert_eq!((frame.width, frame.height), size);
+        assert_eq!(frame.data.len(), size.0 as usize * size.1 as usize);
+    }
+    // frames that change size are resized back to the configured size
+    meta.resize = Some(FrameSize {
+        width: 4,
+        height: 2,
+    });
+    for _ in sizes {
+        let frame = querier.read(&meta).unwrap();
+        assert_eq!((frame.width, frame.height), (4, 2));
+        assert!(frame.data.iter().all(|&b| b == 9));
+    }
+}
+
+#[test]
+fn orient_frames() {
+    let frame = Buffer {
+        width: 4,
+        height: 2,
+        format: PixelFormat::LUMA,
+        data: vec![0, 1, 2, 3, 4, 5, 6, 7].into(),
+    };
+    let mut camera = Camera::new(
+        "front".to_string(),
+        Box::new(FlakyCamera { failures: 0, frame }),
+    );
+    camera.meta.orientation = Orientation {
+        rotate: Rotation::Cw90,
+        flip: Flip::Horizontal,
+        crop: Some(CropRect {
+            x: 0,
+            y: 1,
+            width: 2,
+            height: 5,
+        }),
+    };
+    let size = camera.clone_full_metadata().size;
+    assert_eq!((size.width, size.height), (2, 3));
+    let frame = camera.read().unwrap();
+    assert_eq!((frame.width, frame.height), (2, 3));
+    assert_eq!(&*frame.data, &[1, 5, 2, 6, 3, 7]);
+    camera.meta.resize = Some(FrameSize {
+        width: 1,
+        height: 1,
+    });
+    let frame = camera.read().unwrap();
+    assert_eq!((frame.width, frame.height), (1, 1));
+}
+
+#[test]
+fn orient_intrinsics_matches_frames() {
+    // every pixel has a different value, so we can find where each one ends up
+    let (width, height) = (8, 6);
+    let frame = Buffer {
+        width,
+        height,
+        format: PixelFormat::LUMA,
+        data: (0..(width * height) as u8).collect::<Vec<_>>().into(),
+    };
+    let intrinsics = Intrinsics {
+        width,
+        height,
+        fx: 5.0,
+        fy: 6.0,
+        cx: 3.3,
+        cy: 2.7,
+        distortion: Distortion {
+            k1: 0.1,
+            k2: -0.02,
+            p1: 0.01,
+            p2: -0.03,
+            k3: 0.0,
+        },
+    };
+    let crop = CropRect {
+        x: 1,
+        y: 2,
+        width: 4,
+        height: 3,
+    };
+    let rotations = [
+        Rotation::None,
+        Rotation::Cw90,
+        Rotation::Cw180,
+        Rotation::Cw270,
+    ];
+    let flips = [Flip::None, Flip::Horizontal, Flip::Vertical, Flip::Both];
+    for rotate in rotations {
+        for flip in flips {
+            // the pixel mapping is affine, so three pixels are enough to find it
+            let mut out = Buffer::empty_rgb();
+            let uncropped = Orientation {
+                rotate,
+                flip,
+                crop: None,
+            };
+            orient(frame.borrow(), &mut out, uncropped);
+            let find = |x: u32, y: u32| {
+                let idx = out
+                    .data
+                    .iter()
+                    .position(|&v| v as u32 == y * width + x)
+                    .unwrap() as u32;
+                [(idx % out.width) as f64, (idx / out.width) as f64]
+            };
+            let origin = find(0, 0);
+            let [right, down] =
+                [find(1, 0), find(0, 1)].map(|p| [p[0] - origin[0], p[1] - origin[1]]);
+            let map = |[x, y]: [f64; 2]| {
+                [
+                    origin[0] + right[0] * x + down[0] * y,
+                    origin[1] + right[1] * x + down[1] * y,
+                ]
+            };
+            let orientation = Orientation {
+                crop: Some(crop),
+                ..uncropped
+            };
+            let oriented = orient_intrinsics(&intrinsics, orientation);
+            assert_eq!((oriented.width, oriented.height), (crop.width, crop.height));
+            for point in [[0.0, 0.0], [0.3, -0.2], [-0.25, 0.4]] {
+                let [x, y] = intrinsics.distort(point);
+                let [u, v] = map([
+                    intrinsics.fx * x + intrinsics.cx,
+                    intrinsics.fy * y + intrinsics.cy,
+                ]);
+                let expected = [u - crop.x as f64, v - crop.y as f64];
+                // the axes of normalized coordinates move the same way as the pixel axes
+                let point = [
+                    right[0] * point[0] + down[0] * point[1],
+                    right[1] * point[0] + down[1] * point[1],
+                ];
+                let [x, y] = oriented.distort(point);
+                let found = [oriented.fx * x + oriented.cx, oriented.fy * y + oriented.cy];
+                for (found, expected) in found.into_iter().zip(expected) {
+                    assert!(
+                        (found - expected).abs() < 1e-9,
+                        "{rotate:?} {flip:?}: {found} != {expected}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn orient_fov() {
+    let mut config = CameraConfig {
+        factory: Box::new(synthetic_scene()),
+        fov: Some(Fov(90.0)),
+        intrinsics: None,
+        rotate: Rotation::Cw90,
+        flip: Flip::None,
+        crop: None,
+        resize: None,
+        max_fps: None,
+        record: None,
+        background: None,
+        reconnect: ReconnectPolicy::default(),
+    };
+    let size = FrameSize {
+        width: 80,
+        height: 60,
+    };
+    let (fov, intrinsics) = config.oriented_optics(size).unwrap();
+    // the new horizontal FOV is the old vertical one
+    let expected = (30.0f64 / 40.0).atan().to_degrees() * 2.0;
+    assert!((fov.unwrap().0 - expected).abs() < 1e-9, "{fov:?}");
+    let intrinsics = intrinsics.unwrap();
+    assert_eq!((intrinsics.width, intrinsics.height), (60, 80));
+    assert!((intrinsics.fx - 40.0).abs() < 1e-9 && intrinsics.fx == intrinsics.fy);
+    // the FOV can't be oriented without knowing the frame size
+    let unknown = FrameSize {
+        width: 0,
+        height: 0,
+    };
+    assert!(config.oriented_optics(unknown).is_err());
+    // and it's left alone without an orientation
+    config.rotate = Rotation::None;
+    assert_eq!(
+        config.oriented_optics(size).unwrap(),
+        (Some(Fov(90.0)), None)
+    );
+}
+
+/// A camera whose frames were captured at the given times, in milliseconds.
+struct TimedCamera {
+    times: &'static [u64],
+    next: usize,
+    frame: Buffer<'static>,
+}
+impl CameraImpl for TimedCamera {
+    fn frame_size(&self) -> FrameSize {
+        FrameSize {
+            width: 1,
+            height: 1,
+        }
+    }
+    fn load_frame(&mut self) -> io::Result<()> {
+        if self.next >= self.times.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.frame = Buffer::monochrome(1, 1, PixelFormat::LUMA, &[self.next as u8]);
+        self.next += 1;
+        Ok(())
+    }
+    fn get_frame(&self) -> Buffer<'_> {
+        self.frame.borrow()
+    }
+    fn frame_metadata(&self) -> FrameMetadata {
+        FrameMetadata {
+            timestamp: Some(
+                SystemTime::UNIX_EPOCH + Duration::from_millis(self.times[self.next - 1]),
+            ),
+            sequence: Some(self.next as u64 - 1),
+        }
+    }
+}
+
+fn timed_group(times: &[&'static [u64]]) -> (GroupMetadata, GroupQuerier) {
+    let config = CameraGroupConfig {
+        cameras: (0..times.len()).map(|n| n.to_string()).collect(),
+        max_skew: Duration::from_millis(10),
+    };
+    let (cameras, queriers) = times
+        .iter()
+        .zip(&config.cameras)
+        .map(|(&times, name)| {
+            let camera = Camera::new(
+                name.clone(),
+                Box::new(TimedCamera {
+                    times,
+                    next: 0,
+                    frame: Buffer::empty_rgb(),
+                }),
+            );
+            let (meta, querier) = camera.split();
+            (meta, FrameQuerier::Direct(querier))
+        })
+        .unzip();
+    (
+        config.metadata("stereo".to_string(), cameras),
+        GroupQuerier::new(queriers),
+    )
+}
+
+fn group_sequences(querier: &GroupQuerier) -> Vec<u8> {
+    querier.frames().map(|f| f.data[0]).collect()
+}
+
+#[test]
+fn group_lines_up_frames() {
+    let (meta, mut querier) = timed_group(&[&[0, 100, 200, 300], &[95, 205, 298]]);
+    querier.load_frames(&meta).unwrap();
+    assert_eq!(group_sequences(&querier), [1, 0]);
+    assert_eq!(querier.skew(), Duration::from_millis(5));
+    querier.load_frames(&meta).unwrap();
+    assert_eq!(group_sequences(&querier), [2, 1]);
+    querier.load_frames(&meta).unwrap();
+    assert_eq!(group_sequences(&querier), [3, 2]);
+    let context = GroupContext::new(&meta, &querier);
+    assert_eq!(
+        context.timestamp().unwrap().captured,
+        SystemTime::UNIX_EPOCH + Duration::from_millis(298)
+    );
+    let err = querier.load_frames(&meta).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn group_gives_up() {
+    let times: &[u64] = &[0, 30, 60, 90, 120, 150, 180, 210, 240, 270, 300];
+    let offset: &[u64] = &[15, 45, 75, 105, 135, 165, 195, 225, 255, 285, 315];
+    let (meta, mut querier) = timed_group(&[times, offset]);
+    querier.load_frames(&meta).unwrap();
+    assert_eq!(querier.skew(), Duration::from_millis(15));
+    let [a, b] = group_sequences(&querier)[..] else {
+        panic!("expected two frames");
+    };
+    assert!(a.abs_diff(b) <= 1);
+}
+
+#[test]
+fn device_watcher() {
+    let dir = std::env::temp_dir().join(format!("vv-hotplug-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("video0"), b"").unwrap();
+    std::fs::write(dir.join("other"), b"").unwrap();
+    let mut watcher = DeviceWatcher::new(&dir, "video");
+    assert_eq!(watcher.devices().collect::<Vec<_>>(), [dir.join("video0")]);
+    assert!(watcher.poll().is_empty());
+    std::fs::write(dir.join("video2"), b"").unwrap();
+    std::fs::remove_file(dir.join("video0")).unwrap();
+    std::fs::write(dir.join("another"), b"").unwrap();
+    assert_eq!(
+        watcher.poll(),
+        DeviceChanges {
+            added: vec![dir.join("video2")],
+            removed: vec![dir.join("video0")],
+        }
+    );
+    assert!(watcher.poll().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(watcher.poll().removed, [dir.join("video2")]);
+}
+
+#[test]
+fn hotplug_starts_and_stops() {
+    use HotplugAction::*;
+    let mut state = HotplugState::default();
+    // not plugged in yet
+    assert_eq!(state.update(false, false, CameraRun::Stopped), Wait);
+    // plugged in, but the first start fails, and only the first failure is reported
+    assert_eq!(state.update(true, true, CameraRun::Stopped), Start);
+    assert!(state.start_failed());
+    assert_eq!(state.update(true, false, CameraRun::Stopped), Start);
+    assert!(!state.start_failed());
+    // another device being plugged in resets that
+    assert_eq!(state.update(true, true, CameraRun::Stopped), Start);
+    assert!(state.start_failed());
+    assert_eq!(state.update(true, false, CameraRun::Stopped), Start);
+    state.started();
+    assert_eq!(state.update(true, false, CameraRun::Running), Wait);
+    // unplugged, so it's told to stop exactly once
+    assert_eq!(state.update(false, false, CameraRun::Running), Stop);
+    assert_eq!(state.update(false, false, CameraRun::Running), Wait);
+    assert_eq!(state.update(false, false, CameraRun::Stopped), Wait);
+    // plugged back in, and it can be stopped again after restarting
+    assert_eq!(state.update(true, true, CameraRun::Stopped), Start);
+    state.started();
+    assert_eq!(state.update(false, false, CameraRun::Running), Stop);
+    // a camera that finishes on its own isn't restarted
+    assert_eq!(state.update(true, false, CameraRun::Finished), Forget);
+}
+
+const TWO_GRAY: &[u8] = include_bytes!("data/two-gray.jpg");
+
+/// Accept a connection and read the request, returning the stream to write the response to.
+fn accept_request(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut request = Vec::new();
+    let mut buf = [0; 256];
+    while !request.ends_with(b"\r\n\r\n") {
+        let n = stream.read(&mut buf).unwrap();
+        assert_ne!(n, 0, "client closed the connection");
+        request.extend_from_slice(&buf[..n]);
+    }
+    assert!(request.starts_with(b"GET /stream HTTP/1.0\r\n"));
+    stream
+}
+
+fn assert_two_gray(frame: Buffer<'_>) {
+    assert_eq!((frame.width, frame.height), (16, 8));
+    assert_eq!(frame.format, PixelFormat::LUMA);
+    for y in 0..8 {
+        for x in 0..16 {
+            let expected = if x < 8 { 64 } else { 192 };
+            let px = frame.pixel(x, y).unwrap();
+            assert!(
+                px.iter().all(|&c| c.abs_diff(expected) <= 2),
+                "pixel at ({x}, {y}) is {px:?}, expected {expected}"
+            );
+        }
+    }
+}
+
+#[test]
+fn mjpeg_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/stream", listener.local_addr().unwrap());
+    let server = std::thread::spawn(move || {
+        for _ in 0..2 {
+            let mut stream = accept_request(&listener);
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\n\r\n"
+            )
+            .unwrap();
+            // one part with a length, and one without
+            write!(
+                stream,
+                "--frame\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+                TWO_GRAY.len()
+            )
+            .unwrap();
+            stream.write_all(TWO_GRAY).unwrap();
+            stream
+                .write_all(b"\r\n--frame\r\nContent-Type: image/jpeg\r\n\r\n")
+                .unwrap();
+            stream.write_all(TWO_GRAY).unwrap();
+            stream.write_all(b"\r\n--frame--\r\n").unwrap();
+        }
+    });
+    let config = MjpegCameraConfig {
+        url,
+        timeout: Duration::from_secs(5),
+    };
+    let mut camera = config.build_camera().unwrap();
+    assert_eq!(
+        camera.frame_size(),
+        FrameSize {
+            width: 16,
+            height: 8
+        }
+    );
+    assert_two_gray(camera.get_frame());
+    camera.load_frame().unwrap();
+    assert_two_gray(camera.get_frame());
+    let err = camera.load_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionAborted);
+    assert!(camera.reload());
+    camera.load_frame().unwrap();
+    assert_two_gray(camera.get_frame());
+    server.join().unwrap();
+}
+
+#[test]
+fn mjpeg_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/stream", listener.local_addr().unwrap());
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let server = std::thread::spawn(move || {
+        let mut stream = accept_request(&listener);
+        write!(
+            stream,
+            "HTTP/1.0 200 OK\r\nContent-Type: multipart/x-mixed-replace;boundary=\"--frame\"\r\n\r\n"
+        )
+        .unwrap();
+        // never send a frame, but keep the connection open until the client gives up
+        let _ = done_rx.recv();
+    });
+    let config = MjpegCameraConfig {
+        url,
+        timeout: Duration::from_millis(100),
+    };
+    let err = config.build_camera().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    drop(done_tx);
+    server.join().unwrap();
+}
+
+#[test]
+fn mjpeg_trickle_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/stream", listener.local_addr().unwrap());
+    let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+    let server = std::thread::spawn(move || {
+        let mut stream = accept_request(&listener);
+        write!(
+            stream,
+            "HTTP/1.0 200 OK\r\nContent-Type: multipart/x-mixed-replace; boundary=frame\r\n\r\n"
+        )
+        .unwrap();
+        write!(stream, "--frame\r\nContent-Length: 1000\r\n\r\n").unwrap();
+        // send a byte more often than the timeout, but never enough for a whole frame
+        while let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+            done_rx.recv_timeout(Duration::from_millis(20))
+        {
+            if stream.write_all(&[0]).is_err() {
+                break;
+            }
+        }
+    });
+    let config = MjpegCameraConfig {
+        url,
+        timeout: Duration::from_millis(200),
+    };
+    let start = Instant::now();
+    let err = config.build_camera().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(5));
+    drop(done_tx);
+    server.join().unwrap();
+}
+
+fn synthetic_scene() -> SyntheticCameraConfig {
+    SyntheticCameraConfig {
+        width: 64,
+        height: 48,
+        background_color: [0, 0, 0],
+        shapes: vec![
+            Shape::Rect {
+                center: Trajectory::Fixed([16.0, 12.0]),
+                width: 8.0,
+                height: 6.0,
+                angle: 0.0,
+                color: [255, 0, 0],
+            },
+            Shape::Circle {
+                center: Trajectory::Waypoints {
+                    waypoints: vec![[40.0, 30.0], [50.0, 30.0]],
+                    frames: NonZeroU32::new(2).unwrap(),
+                    mode: TrajectoryMode::Once,
+                },
+                radius: 5.0,
+                color: [0, 255, 0],
+            },
+        ],
+        render_intrinsics: None,
+        blur: 0.0,
+        noise: 0.0,
+        seed: 0,
+    }
+}
+
+#[cfg(feature = "supply")]
+#[test]
+fn synthetic_shapes() {
+    use supply::prelude::*;
+
+    let meta = CameraMetadata::new("synthetic".to_string());
+    let mut querier = CameraQuerier::new(synthetic_scene().build_camera().unwrap());
+    querier.load_frame(&meta).unwrap();
+    let frame = querier.get_frame();
+    assert_eq!(frame.pixel(16, 12), Some(&[255, 0, 0][..]));
+    assert_eq!(frame.pixel(40, 30), Some(&[0, 255, 0][..]));
+    assert_eq!(frame.pixel(0, 0), Some(&[0, 0, 0][..]));
+    assert_eq!(querier.frame_timestamp().unwrap().sequence, Some(0));
+
+    let mut blobs = BlobsIterator::from_buffer(&frame).collect::<Vec<_>>();
+    blobs.sort_by_key(|b| b.min_x);
+    let truth = querier
+        .frame_context()
+        .unwrap()
+        .request::<GroundTruthTag>()
+        .unwrap();
+    assert_eq!(truth.frame, 0);
+    assert_eq!(blobs.len(), truth.objects.len());
+    for (blob, object) in blobs.iter().zip(&truth.objects) {
+        assert!(object.visible);
+        let bounds = [blob.min_x, blob.min_y, blob.max_x, blob.max_y].map(f64::from);
+        assert_eq!(bounds, object.bounds);
+    }
+
+    let centers = (1..4)
+        .map(|i| {
+            querier.load_frame(&meta).unwrap();
+            assert_eq!(querier.frame_timestamp().unwrap().sequence, Some(i));
+            let truth = querier
+                .frame_context()
+                .unwrap()
+                .request::<GroundTruthTag>()
+                .unwrap();
+            truth.objects[1].center
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(centers, [[45.0, 30.0], [50.0, 30.0], [50.0, 30.0]]);
+    assert_eq!(querier.get_frame().pixel(54, 30), Some(&[0, 255, 0][..]));
+}
+
+#[test]
+fn synthetic_noise() {
+    let config = SyntheticCameraConfig {
+        noise: 8.0,
+        seed: 42,
+        ..synthetic_scene()
+    };
+    let render = |config: &SyntheticCameraConfig| {
+        let mut camera = config.build_camera().unwrap();
+        camera.load_frame().unwrap();
+        camera.get_frame().into_static()
+    };
+    let first = render(&config);
+    assert_eq!(first, render(&config));
+    assert_ne!(first, render(&synthetic_scene()));
+    assert_ne!(
+        first,
+        render(&SyntheticCameraConfig {
+            seed: 43,
+            ..config.clone()
+        })
+    );
+}
+
+#[test]
+fn synthetic_trajectory() {
+    let trajectory = Trajectory::Waypoints {
+        waypoints: vec![0.0, 4.0, 8.0],
+        frames: NonZeroU32::new(4).unwrap(),
+        mode: TrajectoryMode::Bounce,
+    };
+    let values = (0..18)
+        .step_by(2)
+        .map(|i| trajectory.at(i))
+        .collect::<Vec<_>>();
+    assert_eq!(values, [0.0, 2.0, 4.0, 6.0, 8.0, 6.0, 4.0, 2.0, 0.0]);
+    let trajectory = Trajectory::Waypoints {
+        waypoints: vec![0.0, 4.0],
+        frames: NonZeroU32::new(2).unwrap(),
+        mode: TrajectoryMode::Loop,
+    };
+    let values = (0..5).map(|i| trajectory.at(i)).collect::<Vec<_>>();
+    assert_eq!(values, [0.0, 2.0, 4.0, 2.0, 0.0]);
+}
+
+#[cfg(feature = "v4l")]
+mod capture {
+    use super::super::capture::*;
+    use std::io;
+    use std::os::unix::fs::symlink;
+    use std::path::{Path, PathBuf};
+    use v4l::control::{Description, Flags, MenuItem, Type, Value};
+
+    fn description(id: u32, name: &str, typ: Type) -> Description {
+        Description {
+            id,
+            typ,
+            name: name.to_string(),
+            minimum: 0,
+            maximum: 100,
+            step: 1,
+            default: 50,
+            flags: Flags::empty(),
+            items: None,
+        }
+    }
+
+    fn descriptions() -> Vec<Description> {
+        let mut power_line = description(
+            control_ids::POWER_LINE_FREQUENCY,
+            "Power Line Frequency",
+            Type::Menu,
+        );
+        power_line.maximum = 2;
+        power_line.items = Some(vec![
+            (0, MenuItem::Name("Disabled".to_string())),
+            (1, MenuItem::Name("50 Hz".to_string())),
+            (2, MenuItem::Name("60 Hz".to_string())),
+        ]);
+        vec![
+            description(control_ids::BRIGHTNESS, "Brightness", Type::Integer),
+            description(
+                control_ids::WHITE_BALANCE_AUTOMATIC,
+                "White Balance, Automatic",
+                Type::Boolean,
+            ),
+            power_line,
+            description(0x009a0910, "Focus (absolute)", Type::Integer),
+        ]
+    }
+
+    #[test]
+    fn control_names() {
+        assert_eq!(
+            normalize_control_name("White Balance Temperature"),
+            "white_balance_temperature"
+        );
+        assert_eq!(
+            normalize_control_name("  White Balance, Automatic "),
+            "white_balance_automatic"
+        );
+        assert_eq!(normalize_control_name("Focus (absolute)"), "focus_absolute");
+        assert_eq!(normalize_control_name("exposure_time"), "exposure_time");
+        assert_eq!(normalize_control_name("--"), "");
+    }
+
+    #[test]
+    fn find_controls() {
+        let descs = descriptions();
+        let find = |key| find_control(&descs, key).map(|d| d.id);
+        // well-known names, numeric IDs in decimal and hex, and names as the driver reports them
+        assert_eq!(find("brightness"), Some(control_ids::BRIGHTNESS));
+        assert_eq!(find("9963776"), Some(control_ids::BRIGHTNESS));
+        assert_eq!(find("0x00980900"), Some(control_ids::BRIGHTNESS));
+        assert_eq!(
+            find("0X0098090C"),
+            Some(control_ids::WHITE_BALANCE_AUTOMATIC)
+        );
+        assert_eq!(
+            find("Power Line Frequency"),
+            Some(control_ids::POWER_LINE_FREQUENCY)
+        );
+        assert_eq!(find("focus_absolute"), Some(0x009a0910));
+        // IDs and well-known names that the device doesn't have aren't matched by name instead
+        assert_eq!(find("gain"), None);
+        assert_eq!(find("0x1234"), None);
+        assert_eq!(find("zoom"), None);
+    }
+
+    #[test]
+    fn validate_controls() {
+        let descs = descriptions();
+        let (brightness, auto_wb, power_line) = (&descs[0], &descs[1], &descs[2]);
+        let int = ControlValue::Integer;
+        assert_eq!(
+            validate_control(brightness, &int(75)),
+            Ok(Value::Integer(75))
+        );
+        assert!(validate_control(brightness, &int(101)).is_err());
+        assert!(validate_control(brightness, &int(-1)).is_err());
+        assert!(validate_control(brightness, &ControlValue::Boolean(true)).is_err());
+        let stepped = Description {
+            minimum: 10,
+            step: 5,
+            ..description(control_ids::GAIN, "Gain", Type::Integer)
+        };
+        assert_eq!(validate_control(&stepped, &int(25)), Ok(Value::Integer(25)));
+        assert!(validate_control(&stepped, &int(27)).is_err());
+
+        assert_eq!(
+            validate_control(auto_wb, &ControlValue::Boolean(false)),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(validate_control(auto_wb, &int(1)), Ok(Value::Boolean(true)));
+        assert!(validate_control(auto_wb, &int(2)).is_err());
+
+        assert_eq!(validate_control(power_line, &int(2)), Ok(Value::Integer(2)));
+        assert!(validate_control(power_line, &int(3)).is_err());
+        assert_eq!(
+            validate_control(power_line, &ControlValue::Name("50 hz".to_string())),
+            Ok(Value::Integer(1))
+        );
+        assert!(validate_control(power_line, &ControlValue::Name("100 Hz".to_string())).is_err());
+
+        let read_only = Description {
+            flags: Flags::READ_ONLY,
+            ..description(control_ids::BRIGHTNESS, "Brightness", Type::Integer)
+        };
+        assert!(validate_control(&read_only, &int(50)).is_err());
+    }
+
+    /// Build a fake sysfs and `/dev/v4l/by-path` in a new directory.
+    ///
+    /// `video0` and `video1` are the capture and metadata nodes of a camera with serial `AAA`, and `video2` and `video3` are two
+    /// cameras with the same name, with serials `BBB` and `CCC`. There's also a `serial` outside of `devices`, which shouldn't be found.
+    fn v4l_tree(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("vv-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir(&root).unwrap();
+        // devices are found through their canonical paths, so the root can't go through any links
+        let root = root.canonicalize().unwrap();
+        let class = root.join("sys/class/video4linux");
+        std::fs::create_dir_all(&class).unwrap();
+        std::fs::write(root.join("sys/serial"), "OUT\n").unwrap();
+        let nodes = [
+            ("video0", "Front Cam", 0, "usb1/1-1", "AAA"),
+            ("video1", "Front Cam", 1, "usb1/1-1", "AAA"),
+            ("video2", "USB Camera", 0, "usb1/1-2", "BBB"),
+            ("video3", "USB Camera", 0, "usb2/2-1", "CCC"),
+        ];
+        for (node, name, index, usb, serial) in nodes {
+            let usb = root.join("sys/devices").join(usb);
+            let interface = usb.join("interface");
+            std::fs::create_dir_all(&interface).unwrap();
+            std::fs::write(usb.join("serial"), format!("{serial}\n")).unwrap();
+            let dir = class.join(node);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("name"), format!("{name}\n")).unwrap();
+            std::fs::write(dir.join("index"), format!("{index}\n")).unwrap();
+            symlink(&interface, dir.join("device")).unwrap();
+        }
+        let dev = root.join("dev");
+        let by_path = dev.join("v4l/by-path");
+        std::fs::create_dir_all(&by_path).unwrap();
+        let links = [
+            ("pci-0000:00:14.0-usb-0:1.2:1.0-video-index0", "video0"),
+            ("pci-0000:00:14.0-usb-0:1.2:1.0-video-index1", "video1"),
+            ("pci-0000:00:14.0-usb-0:3:1.0-video-index0", "video2"),
+            ("platform-xhci-hcd.1-usb-0:3:1.0-video-index0", "video3"),
+        ];
+        for (link, node) in links {
+            std::fs::write(dev.join(node), "").unwrap();
+            symlink(dev.join(node), by_path.join(link)).unwrap();
+        }
+        root
+    }
+
+    fn not_found<T: std::fmt::Debug>(res: io::Result<T>) -> bool {
+        res.unwrap_err().kind() == io::ErrorKind::NotFound
+    }
+
+    #[test]
+    fn locate_by_name() {
+        let root = v4l_tree("v4l-name");
+        let sysfs = root.join("sys");
+        let locate = |name: &str| V4lName(name.to_string()).locate_in(&sysfs);
+        // the metadata node has the same name, but it's skipped
+        assert_eq!(locate("Front Cam").unwrap(), Path::new("/dev/video0"));
+        let err = locate("USB Camera").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other, "{err}");
+        assert!(not_found(locate("Back Cam")));
+        assert!(not_found(V4lName("Front Cam".to_string()).locate_in(&root)));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn locate_by_serial() {
+        let root = v4l_tree("v4l-serial");
+        let sysfs = root.join("sys");
+        let locate = |serial: &str| V4lSerial(serial.to_string()).locate_in(&sysfs);
+        assert_eq!(locate("AAA").unwrap(), Path::new("/dev/video0"));
+        assert_eq!(locate("BBB").unwrap(), Path::new("/dev/video2"));
+        assert_eq!(locate("CCC").unwrap(), Path::new("/dev/video3"));
+        assert!(not_found(locate("DDD")));
+        assert!(not_found(locate("OUT")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn locate_by_port() {
+        let root = v4l_tree("v4l-port");
+        let by_path = root.join("dev/v4l/by-path");
+        let locate = |port: &str| V4lPort(port.to_string()).locate_in(&by_path);
+        let video0 = root.join("dev/video0");
+        assert_eq!(locate("usb-0:1.2:1.0").unwrap(), video0);
+        assert_eq!(locate("0:1.2:1.0").unwrap(), video0);
+        assert_eq!(locate("pci-0000:00:14.0-usb-0:1.2:1.0").unwrap(), video0);
+        // the same port on two different controllers
+        let err = locate("usb-0:3:1.0").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Other, "{err}");
+        assert_eq!(
+            locate("platform-xhci-hcd.1-usb-0:3:1.0").unwrap(),
+            root.join("dev/video3")
+        );
+        // only whole parts of the port match
+        assert!(not_found(locate("1.2:1.0")));
+        assert!(not_found(locate("usb-0:4:1.0")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "supply")]
 use supply::ProviderExt;
 #[cfg(feature = "supply")]
-use vv_utils::common_types::{Fov, FrameSize};
+use vv_utils::common_types::{Fov, FrameSize, Intrinsics};
 use vv_utils::mutex::Mutex;
 use vv_vision::buffer::{Buffer, PixelFormat};
 
@@ -110,20 +110,32 @@ impl Component for DetectPoseComponent {
             Self::Fixed(p) => p,
             #[cfg(feature = "supply")]
             Self::Infer { tag_size } => {
-                let Some(Fov(fov)) = context.context.request::<Fov>() else {
-                    tracing::error!("attempted to infer parameters for a camera without an FOV");
-                    return;
-                };
-                let Some(FrameSize { width, height }) = context.context.request::<FrameSize>()
+                let Some(size @ FrameSize { width, height }) =
+                    context.context.request::<FrameSize>()
                 else {
                     tracing::error!(
                         "attempted to infer parameters for a camera without a frame size"
                     );
                     return;
                 };
-                vv_apriltag::PoseParams {
-                    tag_size,
-                    ..vv_apriltag::PoseParams::from_dimensions(width, height, fov)
+                if let Some(intrinsics) = context.context.request::<Intrinsics>() {
+                    let Intrinsics { fx, fy, cx, cy, .. } = intrinsics.scaled_to(size);
+                    vv_apriltag::PoseParams {
+                        tag_size,
+                        center: [cx, cy],
+                        fov: [fx, fy],
+                    }
+                } else {
+                    let Some(Fov(fov)) = context.context.request::<Fov>() else {
+                        tracing::error!(
+                            "attempted to infer parameters for a camera without an FOV or intrinsics"
+                        );
+                        return;
+                    };
+                    vv_apriltag::PoseParams {
+                        tag_size,
+                        ..vv_apriltag::PoseParams::from_dimensions(width, height, fov)
+                    }
                 }
             }
             #[cfg(not(feature = "supply"))]
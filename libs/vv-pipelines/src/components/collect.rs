@@ -7,7 +7,9 @@ use std::marker::PhantomData;
 #[cfg(all(feature = "vision", feature = "serde"))]
 use vv_vision::buffer::Buffer;
 #[cfg(all(feature = "vision", feature = "serde"))]
-use vv_vision::vision::Blob;
+use vv_vision::draw::Line;
+#[cfg(all(feature = "vision", feature = "serde"))]
+use vv_vision::vision::{Blob, Circle, Contour, RotatedRect};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct CollectVecComponent<T> {
@@ -110,6 +112,14 @@ impl TryFrom<CVFShim> for CollectVecFactory {
             "f64" => CollectVecComponent::<f64>::new_boxed,
             #[cfg(feature = "vision")]
             "blob" => CollectVecComponent::<Blob>::new_boxed,
+            #[cfg(feature = "vision")]
+            "contour" => CollectVecComponent::<Contour>::new_boxed,
+            #[cfg(feature = "vision")]
+            "rotated-rect" => CollectVecComponent::<RotatedRect>::new_boxed,
+            #[cfg(feature = "vision")]
+            "circle" => CollectVecComponent::<Circle>::new_boxed,
+            #[cfg(feature = "vision")]
+            "line" => CollectVecComponent::<Line>::new_boxed,
             #[cfg(feature = "apriltag")]
             "apriltag" => CollectVecComponent::<vv_apriltag::Detection>::new_boxed,
             #[cfg(feature = "vision")]
@@ -129,6 +139,14 @@ impl TryFrom<CVFShim> for CollectVecFactory {
             "[f64]" => CollectVecComponent::<Vec<f64>>::new_boxed,
             #[cfg(feature = "vision")]
             "[blob]" => CollectVecComponent::<Vec<Blob>>::new_boxed,
+            #[cfg(feature = "vision")]
+            "[contour]" => CollectVecComponent::<Vec<Contour>>::new_boxed,
+            #[cfg(feature = "vision")]
+            "[rotated-rect]" => CollectVecComponent::<Vec<RotatedRect>>::new_boxed,
+            #[cfg(feature = "vision")]
+            "[circle]" => CollectVecComponent::<Vec<Circle>>::new_boxed,
+            #[cfg(feature = "vision")]
+            "[line]" => CollectVecComponent::<Vec<Line>>::new_boxed,
             #[cfg(feature = "apriltag")]
             "[apriltag]" => CollectVecComponent::<Vec<vv_apriltag::Detection>>::new_boxed,
             #[cfg(feature = "vision")]
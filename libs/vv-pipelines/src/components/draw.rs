@@ -7,9 +7,9 @@ use std::marker::PhantomData;
 use vv_utils::mutex::Mutex;
 use vv_vision::buffer::Buffer;
 use vv_vision::draw::*;
-#[cfg(feature = "serde")]
-use vv_vision::vision::Blob;
 use vv_vision::vision::Color;
+#[cfg(feature = "serde")]
+use vv_vision::vision::{Blob, Circle, Contour, RotatedRect};
 
 pub struct DrawComponent<T> {
     pub color: Color,
@@ -66,6 +66,9 @@ pub struct DrawFactory {
     /// Currently supported types are:
     /// - [`Blob`] as `blob`
     /// - [`Line`] as `line`
+    /// - [`Contour`] as `contour`
+    /// - [`RotatedRect`] as `rotated-rect`
+    /// - [`Circle`] as `circle`
     /// - [`apriltag::Detection`](crate::apriltag::Detection) as `apriltag`
     /// - a [`Vec`] of any of the previous types, as the previous wrapped in brackets e.g. `[blob]` for `Vec<Blob>`
     pub draw: String,
@@ -102,10 +105,16 @@ impl TryFrom<DrawShim> for DrawFactory {
         let factory = match &*value.draw {
             "blob" => DrawComponent::<Blob>::new_boxed,
             "line" => DrawComponent::<Line>::new_boxed,
+            "contour" => DrawComponent::<Contour>::new_boxed,
+            "rotated-rect" => DrawComponent::<RotatedRect>::new_boxed,
+            "circle" => DrawComponent::<Circle>::new_boxed,
             #[cfg(feature = "apriltag")]
             "apriltag" => DrawComponent::<vv_apriltag::Detection>::new_boxed,
             "[blob]" => DrawComponent::<Vec<Blob>>::new_boxed,
             "[line]" => DrawComponent::<Vec<Line>>::new_boxed,
+            "[contour]" => DrawComponent::<Vec<Contour>>::new_boxed,
+            "[rotated-rect]" => DrawComponent::<Vec<RotatedRect>>::new_boxed,
+            "[circle]" => DrawComponent::<Vec<Circle>>::new_boxed,
             #[cfg(feature = "apriltag")]
             "[apriltag]" => DrawComponent::<Vec<vv_apriltag::Detection>>::new_boxed,
             name => return Err(format!("Unrecognized type {name:?}")),
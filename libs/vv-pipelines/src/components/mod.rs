@@ -50,7 +50,7 @@ pub mod prelude {
         ChannelComponent, CloneComponent, DebugComponent, FpsComponent, WrapMutexComponent,
     };
     pub use super::vision::{
-        BlobsComponent, ColorFilterComponent, ColorSpaceComponent, GaussianBlurComponent,
-        PercentileFilterComponent,
+        BlobsComponent, ColorFilterComponent, ColorSpaceComponent, ContoursComponent,
+        GaussianBlurComponent, PercentileFilterComponent, UndistortComponent,
     };
 }
@@ -3,8 +3,13 @@
 use crate::pipeline::prelude::*;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, PoisonError};
+#[cfg(feature = "supply")]
+use supply::ProviderExt;
 #[cfg(feature = "serde")]
 use thiserror::Error;
+use vv_utils::common_types::{FrameSize, Intrinsics};
+use vv_utils::mutex::Mutex;
 use vv_vision::buffer::{Buffer, PixelFormat};
 use vv_vision::vision::*;
 use vv_vision::vision_debug;
@@ -89,6 +94,111 @@ impl ComponentFactory for ColorFilterComponent {
     }
 }
 
+/// A component that thresholds an image with a level chosen by Otsu's method.
+///
+/// It outputs a [`Buffer`] with the [`LUMA`](PixelFormat::LUMA) format, with a value of 255 for pixels brighter than the level and 0
+/// for others, and the level itself on the `level` channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OtsuComponent {
+    /// Select the darker pixels instead of the brighter ones.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub invert: bool,
+}
+impl Component for OtsuComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        match name {
+            "" | "level" => OutputKind::Single,
+            _ => OutputKind::None,
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let mut mask = Buffer::empty(PixelFormat::LUMA);
+        let level = otsu_threshold(img.borrow(), &mut mask, self.invert);
+        context.submit("", mask);
+        context.submit("level", level);
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "otsu"))]
+impl ComponentFactory for OtsuComponent {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+/// A component that thresholds each pixel of an image against the pixels around it.
+///
+/// It outputs a [`Buffer`] with the [`LUMA`](PixelFormat::LUMA) format, with a value of 255 for pixels that are brighter than their
+/// surroundings by more than the offset and 0 for others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "AdaptiveShim"))]
+pub struct AdaptiveThresholdComponent {
+    /// How the local brightness around each pixel is computed.
+    pub method: AdaptiveMethod,
+    /// Side length of the window around each pixel. Must be odd.
+    pub size: usize,
+    /// How much brighter than its surroundings a pixel has to be to be in the mask.
+    pub offset: i16,
+    /// Whether to mask pixels darker than their surroundings instead.
+    pub invert: bool,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<AdaptiveShim> for AdaptiveThresholdComponent {
+    type Error = FromShimError;
+
+    fn try_from(value: AdaptiveShim) -> Result<Self, Self::Error> {
+        if value.size & 1 == 0 {
+            return Err(FromShimError::EvenSize);
+        }
+        Ok(Self {
+            method: value.method,
+            size: value.size,
+            offset: value.offset,
+            invert: value.invert,
+        })
+    }
+}
+impl Component for AdaptiveThresholdComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        if name.is_empty() {
+            OutputKind::Single
+        } else {
+            OutputKind::None
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let mut mask = Buffer::empty(PixelFormat::LUMA);
+        adaptive_threshold(
+            img.borrow(),
+            &mut mask,
+            self.method,
+            self.size,
+            self.offset,
+            self.invert,
+        );
+        context.submit("", mask);
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "adaptive-threshold"))]
+impl ComponentFactory for AdaptiveThresholdComponent {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
 #[inline(always)]
 #[cfg(feature = "serde")]
 const fn max_u32() -> u32 {
@@ -109,11 +219,29 @@ const fn max_f32() -> f32 {
 const fn one_f32() -> f32 {
     1.0
 }
+#[inline(always)]
+#[cfg(feature = "serde")]
+const fn one_f64() -> f64 {
+    1.0
+}
+#[inline(always)]
+#[cfg(feature = "serde")]
+const fn half_f64() -> f64 {
+    0.5
+}
+#[inline(always)]
+#[cfg(feature = "serde")]
+const fn nearest() -> Interpolation {
+    Interpolation::Nearest
+}
 
 /// A component that detects and filters blobs in binary images.
 ///
-/// It can output blobs either as a collected vector on the primary channel or stream individual
-/// blobs on the "elem" channel, filtered by size, pixel count, and aspect ratio constraints.
+/// It can output blobs either as individual blobs on the primary channel or as a collected vector
+/// on the "vec" channel, filtered by size, pixel count, fill ratio, and aspect ratio constraints.
+///
+/// Blobs can also be filtered by the shape of their outlines. This traces their [`Contour`]s, which is slower, so it's only done if
+/// one of those filters is set.
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
@@ -148,6 +276,36 @@ pub struct BlobsComponent {
     /// Maximum aspect ratio (height / width) of blobs to emit.
     #[cfg_attr(feature = "serde", serde(default = "max_f32"))]
     pub max_aspect: f32,
+    /// Minimum solidity (area / convex hull area) of blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_solidity: f32,
+    /// Maximum solidity (area / convex hull area) of blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default = "one_f32"))]
+    pub max_solidity: f32,
+    /// Minimum convexity (convex hull perimeter / perimeter) of blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_convexity: f32,
+    /// Maximum convexity (convex hull perimeter / perimeter) of blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default = "one_f32"))]
+    pub max_convexity: f32,
+    /// Minimum fill ratio of the smallest rotated rectangle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_rect_fill: f32,
+    /// Maximum fill ratio of the smallest rotated rectangle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default = "one_f32"))]
+    pub max_rect_fill: f32,
+    /// Minimum aspect ratio (long side / short side) of the smallest rotated rectangle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_rect_aspect: f32,
+    /// Maximum aspect ratio (long side / short side) of the smallest rotated rectangle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default = "max_f32"))]
+    pub max_rect_aspect: f32,
+    /// Minimum fill ratio of the smallest circle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_circle_fill: f32,
+    /// Maximum fill ratio of the smallest circle around blobs to emit.
+    #[cfg_attr(feature = "serde", serde(default = "one_f32"))]
+    pub max_circle_fill: f32,
 }
 impl Default for BlobsComponent {
     fn default() -> Self {
@@ -162,9 +320,115 @@ impl Default for BlobsComponent {
             max_fill: 1.0,
             min_aspect: 0.0,
             max_aspect: f32::INFINITY,
+            min_solidity: 0.0,
+            max_solidity: 1.0,
+            min_convexity: 0.0,
+            max_convexity: 1.0,
+            min_rect_fill: 0.0,
+            max_rect_fill: 1.0,
+            min_rect_aspect: 0.0,
+            max_rect_aspect: f32::INFINITY,
+            min_circle_fill: 0.0,
+            max_circle_fill: 1.0,
         }
     }
 }
+impl BlobsComponent {
+    /// Check if a blob passes the filters on its bounding box.
+    fn accepts(&self, blob: &Blob) -> bool {
+        let w = blob.width();
+        let h = blob.height();
+        if w < self.min_w || w > self.max_w {
+            return false;
+        }
+        if h < self.min_h || h > self.max_h {
+            return false;
+        }
+        if blob.pixels < self.min_px || blob.pixels > self.max_px {
+            return false;
+        }
+        if self.min_aspect > 0.0 || self.max_aspect < f32::INFINITY {
+            let mut frac = h as f32 / w as f32;
+            if frac.is_nan() {
+                frac = f32::INFINITY;
+            }
+            if frac < self.min_aspect || frac > self.max_aspect {
+                return false;
+            }
+        }
+        if self.min_fill > 0.0 || self.max_fill < 1.0 {
+            let frac = blob.pixels as f32 / (w as f32 * h as f32);
+            if frac < self.min_fill || frac > self.max_fill {
+                return false;
+            }
+        }
+        true
+    }
+    fn filters_solidity(&self) -> bool {
+        self.min_solidity > 0.0 || self.max_solidity < 1.0
+    }
+    fn filters_convexity(&self) -> bool {
+        self.min_convexity > 0.0 || self.max_convexity < 1.0
+    }
+    fn filters_rect(&self) -> bool {
+        self.min_rect_fill > 0.0
+            || self.max_rect_fill < 1.0
+            || self.min_rect_aspect > 0.0
+            || self.max_rect_aspect < f32::INFINITY
+    }
+    fn filters_circle(&self) -> bool {
+        self.min_circle_fill > 0.0 || self.max_circle_fill < 1.0
+    }
+    /// Check if any of the filters need the blobs' contours.
+    fn needs_contours(&self) -> bool {
+        self.filters_solidity()
+            || self.filters_convexity()
+            || self.filters_rect()
+            || self.filters_circle()
+    }
+    /// Check if a blob passes the filters on the shape of its outline.
+    fn accepts_shape(&self, contour: &Contour) -> bool {
+        let in_range = |v: f64, min: f32, max: f32| v >= min as f64 && v <= max as f64;
+        let hull = contour.hull();
+        let area = contour.area();
+        if self.filters_solidity() {
+            let solidity = fill_ratio(area, polygon_area(&hull));
+            if !in_range(solidity, self.min_solidity, self.max_solidity) {
+                return false;
+            }
+        }
+        if self.filters_convexity() {
+            let convexity = fill_ratio(polygon_perimeter(&hull), contour.perimeter());
+            if !in_range(convexity, self.min_convexity, self.max_convexity) {
+                return false;
+            }
+        }
+        if self.filters_rect() {
+            let rect = min_area_rect(&hull);
+            if !in_range(
+                fill_ratio(area, rect.area()),
+                self.min_rect_fill,
+                self.max_rect_fill,
+            ) {
+                return false;
+            }
+            if !in_range(rect.aspect(), self.min_rect_aspect, self.max_rect_aspect) {
+                return false;
+            }
+        }
+        if self.filters_circle() {
+            let circle = min_enclosing_circle(&hull);
+            if !in_range(
+                fill_ratio(area, circle.area()),
+                self.min_circle_fill,
+                self.max_circle_fill,
+            ) {
+                return false;
+            }
+        }
+        true
+    }
+}
 impl Component for BlobsComponent {
     fn inputs(&self) -> Inputs {
         Inputs::Primary
@@ -180,51 +444,249 @@ impl Component for BlobsComponent {
         let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
             return;
         };
-        let blobs = BlobsIterator::from_buffer(&img);
+        let blobs: Box<dyn Iterator<Item = Blob>> = if self.needs_contours() {
+            Box::new(
+                find_contours(&img)
+                    .into_iter()
+                    .filter(|c| self.accepts(&c.blob) && self.accepts_shape(c))
+                    .map(|c| c.blob),
+            )
+        } else {
+            Box::new(BlobsIterator::from_buffer(&img).filter(|b| self.accepts(b)))
+        };
         let collect = context.listening("vec");
         let stream = context.listening("");
         let mut vec = Vec::new();
         for blob in blobs {
-            let w = blob.width();
-            let h = blob.height();
-            if w < self.min_w || w > self.max_w {
-                continue;
+            if collect {
+                vec.push(blob);
             }
-            if h < self.min_h || h > self.max_h {
-                continue;
+            if stream {
+                context.submit("", blob);
             }
-            if blob.pixels < self.min_px || blob.pixels > self.max_px {
+        }
+        if collect {
+            context.submit("vec", vec);
+        }
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "blobs"))]
+impl ComponentFactory for BlobsComponent {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+/// A component that traces the outlines of the regions in binary images.
+///
+/// Like [`BlobsComponent`], it can output contours either as a collected vector or individually. Contours can optionally be
+/// simplified into polygons.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct ContoursComponent {
+    /// The maximum distance between the original and simplified contours, or 0 to not simplify them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub epsilon: f64,
+    /// Minimum pixel count of regions to emit.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min_px: usize,
+    /// Maximum pixel count of regions to emit.
+    #[cfg_attr(feature = "serde", serde(default = "max_usize"))]
+    pub max_px: usize,
+}
+impl Default for ContoursComponent {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.0,
+            min_px: 0,
+            max_px: usize::MAX,
+        }
+    }
+}
+impl Component for ContoursComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        match name {
+            "" => OutputKind::Multiple,
+            "vec" => OutputKind::Single,
+            _ => OutputKind::None,
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let collect = context.listening("vec");
+        let stream = context.listening("");
+        let mut vec = Vec::new();
+        for mut contour in find_contours(&img) {
+            if contour.blob.pixels < self.min_px || contour.blob.pixels > self.max_px {
                 continue;
             }
-            if self.min_aspect > 0.0 || self.max_aspect < f32::INFINITY {
-                let mut frac = h as f32 / w as f32;
-                if frac.is_nan() {
-                    frac = f32::INFINITY;
-                }
-                if frac < self.min_aspect || frac > self.max_aspect {
-                    continue;
-                }
+            if self.epsilon > 0.0 {
+                contour = contour.simplify(self.epsilon);
             }
-            if self.min_fill > 0.0 || self.max_fill < 1.0 {
-                let frac = blob.pixels as f32 / (w as f32 * h as f32);
-                if frac < self.min_aspect || frac > self.max_aspect {
-                    continue;
-                }
+            if stream {
+                context.submit("", contour.clone());
             }
             if collect {
-                vec.push(blob);
-            }
-            if stream {
-                context.submit("elem", blob);
+                vec.push(contour);
             }
         }
         if collect {
-            context.submit("", vec);
+            context.submit("vec", vec);
         }
     }
 }
-#[cfg_attr(feature = "serde", typetag::serde(name = "blobs"))]
-impl ComponentFactory for BlobsComponent {
+#[cfg_attr(feature = "serde", typetag::serde(name = "contours"))]
+impl ComponentFactory for ContoursComponent {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+/// A component that detects straight lines in edge images with the Hough transform.
+///
+/// Like [`BlobsComponent`], it can output lines either as a collected vector or individually.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "HoughLinesShim"))]
+pub struct HoughLinesComponent {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub params: HoughLineParams,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<HoughLinesShim> for HoughLinesComponent {
+    type Error = FromShimError;
+
+    fn try_from(value: HoughLinesShim) -> Result<Self, Self::Error> {
+        if !value.rho.is_finite() || !value.theta.is_finite() {
+            return Err(FromShimError::NonFiniteResolution);
+        }
+        if value.rho <= 0.0 || value.theta <= 0.0 {
+            return Err(FromShimError::NonPositiveResolution);
+        }
+        Ok(Self {
+            params: HoughLineParams {
+                threshold: value.threshold,
+                rho: value.rho,
+                theta: value.theta,
+                max_gap: value.max_gap,
+                min_length: value.min_length,
+                max_lines: value.max_lines,
+            },
+        })
+    }
+}
+impl Component for HoughLinesComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        match name {
+            "" => OutputKind::Multiple,
+            "vec" => OutputKind::Single,
+            _ => OutputKind::None,
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let lines = hough_lines(img.borrow(), &self.params);
+        if context.listening("") {
+            for &line in &lines {
+                context.submit("", line);
+            }
+        }
+        if context.listening("vec") {
+            context.submit("vec", lines);
+        }
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "hough-lines"))]
+impl ComponentFactory for HoughLinesComponent {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+}
+
+/// A component that detects circles in images with the Hough gradient method.
+///
+/// Like [`BlobsComponent`], it can output circles either as a collected vector or individually.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "HoughCirclesShim"))]
+pub struct HoughCirclesComponent {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub params: HoughCircleParams,
+}
+#[cfg(feature = "serde")]
+impl TryFrom<HoughCirclesShim> for HoughCirclesComponent {
+    type Error = FromShimError;
+
+    fn try_from(value: HoughCirclesShim) -> Result<Self, Self::Error> {
+        if !value.min_radius.is_finite() || !value.max_radius.is_finite() {
+            return Err(FromShimError::NonFiniteRadius);
+        }
+        if value.min_radius > value.max_radius {
+            return Err(FromShimError::RadiusOrder);
+        }
+        if !value.low.is_finite() || !value.high.is_finite() {
+            return Err(FromShimError::NonFiniteThreshold);
+        }
+        if value.low < 0.0 || value.high < 0.0 {
+            return Err(FromShimError::NegativeThreshold);
+        }
+        if value.low > value.high {
+            return Err(FromShimError::ThresholdOrder);
+        }
+        Ok(Self {
+            params: HoughCircleParams {
+                min_radius: value.min_radius,
+                max_radius: value.max_radius,
+                threshold: value.threshold,
+                min_coverage: value.min_coverage,
+                min_distance: value.min_distance,
+                low: value.low,
+                high: value.high,
+                kernel: value.kernel,
+                max_circles: value.max_circles,
+            },
+        })
+    }
+}
+impl Component for HoughCirclesComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        match name {
+            "" => OutputKind::Multiple,
+            "vec" => OutputKind::Single,
+            _ => OutputKind::None,
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let circles = hough_circles(img.borrow(), &self.params);
+        if context.listening("") {
+            for &circle in &circles {
+                context.submit("", circle);
+            }
+        }
+        if context.listening("vec") {
+            context.submit("vec", circles);
+        }
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "hough-circles"))]
+impl ComponentFactory for HoughCirclesComponent {
     fn build(&self) -> Box<dyn Component> {
         Box::new(*self)
     }
@@ -243,6 +705,14 @@ struct BlurShim {
     width: usize,
     height: usize,
 }
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct MorphShim {
+    width: usize,
+    height: usize,
+    #[serde(default)]
+    shape: ElementShape,
+}
 
 #[cfg(feature = "serde")]
 #[derive(Deserialize)]
@@ -252,6 +722,66 @@ struct GaussianShim {
     height: usize,
 }
 
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct CannyShim {
+    low: f32,
+    high: f32,
+    #[serde(default)]
+    kernel: GradientKernel,
+    #[serde(default)]
+    sigma: Option<f32>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct AdaptiveShim {
+    #[serde(default)]
+    method: AdaptiveMethod,
+    size: usize,
+    #[serde(default)]
+    offset: i16,
+    #[serde(default)]
+    invert: bool,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct HoughLinesShim {
+    threshold: u32,
+    #[serde(default = "one_f64")]
+    rho: f64,
+    #[serde(default = "one_f64")]
+    theta: f64,
+    #[serde(default)]
+    max_gap: Option<f64>,
+    #[serde(default)]
+    min_length: f64,
+    #[serde(default = "max_usize")]
+    max_lines: usize,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct HoughCirclesShim {
+    min_radius: f64,
+    max_radius: f64,
+    threshold: u32,
+    #[serde(default = "half_f64")]
+    min_coverage: f64,
+    #[serde(default)]
+    min_distance: Option<f64>,
+    low: f32,
+    high: f32,
+    #[serde(default)]
+    kernel: GradientKernel,
+    #[serde(default = "max_usize")]
+    max_circles: usize,
+}
+
 #[cfg(feature = "serde")]
 #[derive(Debug, Error)]
 enum FromShimError {
@@ -259,10 +789,26 @@ enum FromShimError {
     EvenWidth,
     #[error("window height must be odd")]
     EvenHeight,
+    #[error("window size must be odd")]
+    EvenSize,
     #[error("pixel index must be less than the window size")]
     IndexOob,
     #[error("sigma must be positive")]
     NonPositiveSigma,
+    #[error("thresholds must be finite")]
+    NonFiniteThreshold,
+    #[error("thresholds must not be negative")]
+    NegativeThreshold,
+    #[error("low threshold must not be greater than the high threshold")]
+    ThresholdOrder,
+    #[error("resolution must be finite")]
+    NonFiniteResolution,
+    #[error("resolution must be positive")]
+    NonPositiveResolution,
+    #[error("radii must be finite")]
+    NonFiniteRadius,
+    #[error("minimum radius must not be greater than the maximum radius")]
+    RadiusOrder,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -437,76 +983,208 @@ impl ComponentFactory for GaussianBlurComponent {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// A component that computes the gradient magnitude of an image.
+///
+/// It outputs a [`Buffer`] with the [`LUMA`](PixelFormat::LUMA) format, which is brighter where the image changes more sharply.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(feature = "serde", serde(try_from = "BlurShim"))]
-pub struct DilateFactory {
-    pub width: usize,
-    pub height: usize,
+pub struct SobelComponent {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub kernel: GradientKernel,
 }
-#[cfg(feature = "serde")]
-impl TryFrom<BlurShim> for DilateFactory {
-    type Error = FromShimError;
-
-    fn try_from(value: BlurShim) -> Result<Self, Self::Error> {
-        if value.width & 1 == 0 {
-            return Err(FromShimError::EvenWidth);
-        }
-        if value.height & 1 == 0 {
-            return Err(FromShimError::EvenHeight);
+impl Component for SobelComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        if name.is_empty() {
+            OutputKind::Single
+        } else {
+            OutputKind::None
         }
-        Ok(Self {
-            width: value.width,
-            height: value.height,
-        })
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let mut dst = Buffer::empty_rgb();
+        sobel(img.borrow(), &mut dst, self.kernel);
+        context.submit("", dst);
     }
 }
-#[cfg_attr(feature = "serde", typetag::serde(name = "dilate"))]
-impl ComponentFactory for DilateFactory {
+#[cfg_attr(feature = "serde", typetag::serde(name = "sobel"))]
+impl ComponentFactory for SobelComponent {
     fn build(&self) -> Box<dyn Component> {
-        Box::new(PercentileFilterComponent {
-            width: self.width,
-            height: self.height,
-            index: 0,
-        })
+        Box::new(*self)
     }
 }
 
+/// A component that detects edges with the Canny edge detector.
+///
+/// It outputs a [`Buffer`] with the [`LUMA`](PixelFormat::LUMA) format, with a value of 255 for edge pixels and 0 everywhere else.
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[cfg_attr(feature = "serde", serde(try_from = "BlurShim"))]
-pub struct ErodeFactory {
-    pub width: usize,
-    pub height: usize,
+#[cfg_attr(feature = "serde", serde(try_from = "CannyShim"))]
+pub struct CannyComponent {
+    pub low: f32,
+    pub high: f32,
+    pub kernel: GradientKernel,
+    /// The standard deviation of a Gaussian blur to apply first, if any.
+    pub sigma: Option<f32>,
 }
 #[cfg(feature = "serde")]
-impl TryFrom<BlurShim> for ErodeFactory {
+impl TryFrom<CannyShim> for CannyComponent {
     type Error = FromShimError;
 
-    fn try_from(value: BlurShim) -> Result<Self, Self::Error> {
-        if value.width & 1 == 0 {
-            return Err(FromShimError::EvenWidth);
+    fn try_from(value: CannyShim) -> Result<Self, Self::Error> {
+        if !value.low.is_finite() || !value.high.is_finite() {
+            return Err(FromShimError::NonFiniteThreshold);
         }
-        if value.height & 1 == 0 {
-            return Err(FromShimError::EvenHeight);
+        if value.low < 0.0 || value.high < 0.0 {
+            return Err(FromShimError::NegativeThreshold);
+        }
+        if value.low > value.high {
+            return Err(FromShimError::ThresholdOrder);
+        }
+        if value.sigma.is_some_and(|s| !s.is_finite() || s <= 0.0) {
+            return Err(FromShimError::NonPositiveSigma);
         }
         Ok(Self {
-            width: value.width,
-            height: value.height,
+            low: value.low,
+            high: value.high,
+            kernel: value.kernel,
+            sigma: value.sigma,
         })
     }
 }
-#[cfg_attr(feature = "serde", typetag::serde(name = "erode"))]
-impl ComponentFactory for ErodeFactory {
+impl Component for CannyComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        if name.is_empty() {
+            OutputKind::Single
+        } else {
+            OutputKind::None
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let mut dst = Buffer::empty_rgb();
+        if let Some(sigma) = self.sigma {
+            // only the brightness matters, so there's less to blur after converting
+            let mut img = img.convert(PixelFormat::LUMA);
+            let size = 2 * (sigma * 3.0).ceil() as usize + 1;
+            gaussian_blur(&mut img, &mut Buffer::empty_rgb(), sigma, size, size);
+            canny(img, &mut dst, self.low, self.high, self.kernel);
+        } else {
+            canny(img.borrow(), &mut dst, self.low, self.high, self.kernel);
+        }
+        context.submit("", dst);
+    }
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "canny"))]
+impl ComponentFactory for CannyComponent {
     fn build(&self) -> Box<dyn Component> {
-        Box::new(PercentileFilterComponent {
-            width: self.width,
-            height: self.height,
-            index: self.width * self.height - 1,
-        })
+        Box::new(*self)
     }
 }
 
+/// A component that applies a morphological operation, like erosion or dilation, to an image.
+///
+/// It outputs a [`Buffer`] with the same format as the input, with each channel processed separately. It's built by the
+/// `dilate`, `erode`, `open`, `close`, `gradient`, and `tophat` components, which each fix the operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MorphologyComponent {
+    /// The operation to apply.
+    pub op: MorphOp,
+    /// Shape of the structuring element.
+    pub shape: ElementShape,
+    /// Width of the structuring element. Must be odd.
+    pub width: usize,
+    /// Height of the structuring element. Must be odd.
+    pub height: usize,
+}
+impl Component for MorphologyComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        if name.is_empty() {
+            OutputKind::Single
+        } else {
+            OutputKind::None
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        let mut dst = Buffer::empty_rgb();
+        morphology(
+            img.borrow(),
+            &mut dst,
+            self.op,
+            self.shape,
+            self.width,
+            self.height,
+        );
+        context.submit("", dst);
+    }
+}
+
+/// Define a factory for a [`MorphologyComponent`] with a fixed operation.
+macro_rules! morph_factory {
+    ($name:ident, $tag:literal, $op:expr) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+        #[cfg_attr(feature = "serde", serde(try_from = "MorphShim"))]
+        pub struct $name {
+            pub width: usize,
+            pub height: usize,
+            pub shape: ElementShape,
+        }
+        #[cfg(feature = "serde")]
+        impl TryFrom<MorphShim> for $name {
+            type Error = FromShimError;
+
+            fn try_from(value: MorphShim) -> Result<Self, Self::Error> {
+                if value.width & 1 == 0 {
+                    return Err(FromShimError::EvenWidth);
+                }
+                if value.height & 1 == 0 {
+                    return Err(FromShimError::EvenHeight);
+                }
+                Ok(Self {
+                    width: value.width,
+                    height: value.height,
+                    shape: value.shape,
+                })
+            }
+        }
+        #[cfg_attr(feature = "serde", typetag::serde(name = $tag))]
+        impl ComponentFactory for $name {
+            fn build(&self) -> Box<dyn Component> {
+                Box::new(MorphologyComponent {
+                    op: $op,
+                    shape: self.shape,
+                    width: self.width,
+                    height: self.height,
+                })
+            }
+        }
+    };
+}
+
+morph_factory!(DilateFactory, "dilate", MorphOp::Dilate);
+morph_factory!(ErodeFactory, "erode", MorphOp::Erode);
+morph_factory!(OpenFactory, "open", MorphOp::Open);
+morph_factory!(CloseFactory, "close", MorphOp::Close);
+morph_factory!(MorphGradientFactory, "gradient", MorphOp::Gradient);
+morph_factory!(TopHatFactory, "tophat", MorphOp::TopHat);
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(try_from = "BlurShim"))]
@@ -547,6 +1225,8 @@ impl ComponentFactory for MedianFilterFactory {
 pub struct ResizeComponent {
     pub width: u32,
     pub height: u32,
+    #[cfg_attr(feature = "serde", serde(default = "nearest"))]
+    pub mode: Interpolation,
 }
 impl Component for ResizeComponent {
     fn inputs(&self) -> Inputs {
@@ -564,7 +1244,7 @@ impl Component for ResizeComponent {
             return;
         };
         let mut new = Buffer::empty_rgb();
-        resize(img.borrow(), &mut new, self.width, self.height);
+        resize(img.borrow(), &mut new, self.width, self.height, self.mode);
         context.submit("", new);
     }
 }
@@ -575,6 +1255,119 @@ impl ComponentFactory for ResizeComponent {
     }
 }
 
+/// The maximum number of maps an [`UndistortComponent`] keeps around.
+const MAX_CACHED_MAPS: usize = 8;
+
+/// A component that removes lens distortion from an image.
+///
+/// The undistorted image keeps the same focal length and principal point, so the intrinsics (without distortion) still apply to it.
+#[derive(Debug)]
+pub struct UndistortComponent {
+    pub intrinsics: Option<Intrinsics>,
+    pub interpolation: Interpolation,
+    maps: Mutex<Vec<(Intrinsics, Arc<RemapMap>)>>,
+}
+impl UndistortComponent {
+    /// Create a new component.
+    ///
+    /// If `intrinsics` is `None`, they'll be requested from the pipeline's context.
+    pub fn new(intrinsics: Option<Intrinsics>, interpolation: Interpolation) -> Self {
+        Self {
+            intrinsics,
+            interpolation,
+            maps: Mutex::new(Vec::new()),
+        }
+    }
+    /// Build a map to undistort an image, for intrinsics that have already been scaled to the image size.
+    pub fn build_map(intrinsics: &Intrinsics) -> RemapMap {
+        let Intrinsics {
+            width,
+            height,
+            fx,
+            fy,
+            cx,
+            cy,
+            ..
+        } = *intrinsics;
+        RemapMap::from_fn(width, height, width, height, |x, y| {
+            let [dx, dy] = intrinsics.distort([(x as f64 - cx) / fx, (y as f64 - cy) / fy]);
+            [dx * fx + cx, dy * fy + cy]
+        })
+    }
+    /// Get a map for the given intrinsics, building it if necessary.
+    fn get_map(&self, intrinsics: Intrinsics) -> Arc<RemapMap> {
+        let mut maps = self
+            .maps
+            .lock()
+            .inspect_err(|_| tracing::warn!("poisoned undistortion map lock"))
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, map)) = maps.iter().find(|(i, _)| *i == intrinsics) {
+            return map.clone();
+        }
+        tracing::debug!(?intrinsics, "building undistortion map");
+        let map = Arc::new(Self::build_map(&intrinsics));
+        if maps.len() >= MAX_CACHED_MAPS {
+            maps.remove(0);
+        }
+        maps.push((intrinsics, map.clone()));
+        map
+    }
+}
+impl Component for UndistortComponent {
+    fn inputs(&self) -> Inputs {
+        Inputs::Primary
+    }
+    fn output_kind(&self, name: &str) -> OutputKind {
+        if name.is_empty() {
+            OutputKind::Single
+        } else {
+            OutputKind::None
+        }
+    }
+    fn run<'s, 'r: 's>(&self, context: ComponentContext<'_, 's, 'r>) {
+        let Ok(img) = context.get_as::<Buffer>(None).and_log_err() else {
+            return;
+        };
+        #[cfg(feature = "supply")]
+        let intrinsics = self
+            .intrinsics
+            .or_else(|| context.context.request::<Intrinsics>());
+        #[cfg(not(feature = "supply"))]
+        let intrinsics = self.intrinsics;
+        let Some(intrinsics) = intrinsics else {
+            tracing::error!("attempted to undistort an image without any intrinsics");
+            return;
+        };
+        let intrinsics = intrinsics.scaled_to(FrameSize {
+            width: img.width,
+            height: img.height,
+        });
+        if intrinsics.distortion.is_zero() {
+            context.submit("", img);
+            return;
+        }
+        let map = self.get_map(intrinsics);
+        let mut out = Buffer::empty_rgb();
+        remap(img.borrow(), &mut out, &map, self.interpolation);
+        context.submit("", out);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct UndistortFactory {
+    /// The intrinsics to use, if they shouldn't come from the camera.
+    pub intrinsics: Option<Intrinsics>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interpolation: Interpolation,
+}
+#[cfg_attr(feature = "serde", typetag::serde(name = "undistort"))]
+impl ComponentFactory for UndistortFactory {
+    fn build(&self) -> Box<dyn Component> {
+        Box::new(UndistortComponent::new(self.intrinsics, self.interpolation))
+    }
+}
+
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct VisionDebugComponent {
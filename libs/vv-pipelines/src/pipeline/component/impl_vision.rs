@@ -1,7 +1,7 @@
 use super::*;
 use vv_vision::buffer::Buffer;
 use vv_vision::draw::Line;
-use vv_vision::vision::Blob;
+use vv_vision::vision::{Blob, Circle, Contour, RotatedRect};
 
 impl Data for Line {
     fn debug(&self, f: &mut Formatter) -> fmt::Result {
@@ -38,15 +38,126 @@ impl Data for Blob {
             "height" => Some(Cow::Owned(Arc::new(self.height()) as _)),
             "area" => Some(Cow::Owned(Arc::new(self.area()) as _)),
             "filled" => Some(Cow::Owned(Arc::new(self.filled()) as _)),
+            "centroid_x" => Some(Cow::Owned(Arc::new(self.centroid()[0]) as _)),
+            "centroid_y" => Some(Cow::Owned(Arc::new(self.centroid()[1]) as _)),
+            "mu20" => Some(Cow::Owned(Arc::new(self.second_moments()[0]) as _)),
+            "mu11" => Some(Cow::Owned(Arc::new(self.second_moments()[1]) as _)),
+            "mu02" => Some(Cow::Owned(Arc::new(self.second_moments()[2]) as _)),
+            "orientation" => Some(Cow::Owned(Arc::new(self.orientation()) as _)),
+            "eccentricity" => Some(Cow::Owned(Arc::new(self.eccentricity()) as _)),
             _ => None,
         }
     }
     fn known_fields(&self) -> &'static [&'static str] {
         &[
-            "min_x", "max_x", "min_y", "max_y", "pixels", "width", "height", "area", "filled",
+            "min_x",
+            "max_x",
+            "min_y",
+            "max_y",
+            "pixels",
+            "width",
+            "height",
+            "area",
+            "filled",
+            "centroid_x",
+            "centroid_y",
+            "mu20",
+            "mu11",
+            "mu02",
+            "orientation",
+            "eccentricity",
         ]
     }
 }
+impl Data for Contour {
+    fn debug(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+    fn clone_to_arc(&self) -> Arc<dyn Data> {
+        Arc::new(self.clone())
+    }
+    fn field(&self, field: &str) -> Option<Cow<'_, dyn Data>> {
+        match field {
+            "points" => Some(Cow::Owned(
+                Arc::new(self.points.as_flattened().to_vec()) as _
+            )),
+            "len" => Some(Cow::Owned(Arc::new(self.points.len()) as _)),
+            "perimeter" => Some(Cow::Owned(Arc::new(self.perimeter()) as _)),
+            "area" => Some(Cow::Owned(Arc::new(self.area()) as _)),
+            "blob" => Some(Cow::Borrowed(&self.blob)),
+            "hull" => Some(Cow::Owned(
+                Arc::new(self.hull().as_flattened().to_vec()) as _
+            )),
+            "min_rect" => Some(Cow::Owned(Arc::new(self.min_area_rect()) as _)),
+            "enclosing_circle" => Some(Cow::Owned(Arc::new(self.enclosing_circle()) as _)),
+            "solidity" => Some(Cow::Owned(Arc::new(self.solidity()) as _)),
+            "convexity" => Some(Cow::Owned(Arc::new(self.convexity()) as _)),
+            _ => None,
+        }
+    }
+    fn known_fields(&self) -> &'static [&'static str] {
+        &[
+            "points",
+            "len",
+            "perimeter",
+            "area",
+            "blob",
+            "hull",
+            "min_rect",
+            "enclosing_circle",
+            "solidity",
+            "convexity",
+        ]
+    }
+}
+impl Data for RotatedRect {
+    fn debug(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+    fn clone_to_arc(&self) -> Arc<dyn Data> {
+        Arc::new(*self)
+    }
+    fn field(&self, field: &str) -> Option<Cow<'_, dyn Data>> {
+        match field {
+            "cx" => Some(Cow::Borrowed(&self.center[0])),
+            "cy" => Some(Cow::Borrowed(&self.center[1])),
+            "width" => Some(Cow::Borrowed(&self.width)),
+            "height" => Some(Cow::Borrowed(&self.height)),
+            "angle" => Some(Cow::Borrowed(&self.angle)),
+            "area" => Some(Cow::Owned(Arc::new(self.area()) as _)),
+            "aspect" => Some(Cow::Owned(Arc::new(self.aspect()) as _)),
+            "corners" => Some(Cow::Owned(
+                Arc::new(self.corners().as_flattened().to_vec()) as _
+            )),
+            _ => None,
+        }
+    }
+    fn known_fields(&self) -> &'static [&'static str] {
+        &[
+            "cx", "cy", "width", "height", "angle", "area", "aspect", "corners",
+        ]
+    }
+}
+impl Data for Circle {
+    fn debug(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self, f)
+    }
+    fn clone_to_arc(&self) -> Arc<dyn Data> {
+        Arc::new(*self)
+    }
+    fn field(&self, field: &str) -> Option<Cow<'_, dyn Data>> {
+        match field {
+            "cx" => Some(Cow::Borrowed(&self.center[0])),
+            "cy" => Some(Cow::Borrowed(&self.center[1])),
+            "radius" => Some(Cow::Borrowed(&self.radius)),
+            "area" => Some(Cow::Owned(Arc::new(self.area()) as _)),
+            _ => None,
+        }
+    }
+    fn known_fields(&self) -> &'static [&'static str] {
+        &["cx", "cy", "radius", "area"]
+    }
+}
 impl Data for Buffer<'static> {
     fn debug(&self, f: &mut Formatter) -> fmt::Result {
         Debug::fmt(&self, f)
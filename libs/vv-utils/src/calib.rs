@@ -0,0 +1,736 @@
+//! Intrinsic camera calibration from views of a planar target.
+//!
+//! This follows Zhang's method: a homography is fit for each view, a closed-form solution for the
+//! intrinsics is derived from the homographies, and then everything (including distortion) is refined
+//! with Levenberg-Marquardt to minimize the reprojection error.
+
+use crate::common_types::{Distortion, FrameSize, Intrinsics};
+use crate::geom::{Mat3, Vec3};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Point correspondences for a single view of a calibration target.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CalibrationView {
+    /// Points on the target, in any consistent unit. The target is assumed to lie on the Z=0 plane.
+    pub object: Vec<[f64; 2]>,
+    /// The corresponding points in the image, in pixels.
+    pub image: Vec<[f64; 2]>,
+}
+
+/// Which parameters should be estimated during calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalibrationOptions {
+    /// Estimate the radial distortion coefficients `k1` and `k2`.
+    pub radial: bool,
+    /// Estimate the tangential distortion coefficients `p1` and `p2`.
+    pub tangential: bool,
+    /// Estimate the third radial coefficient, `k3`. This is only needed for wide-angle lenses, and tends to overfit otherwise.
+    pub k3: bool,
+    /// The maximum number of refinement iterations.
+    pub max_iters: usize,
+}
+impl Default for CalibrationOptions {
+    fn default() -> Self {
+        Self {
+            radial: true,
+            tangential: true,
+            k3: false,
+            max_iters: 100,
+        }
+    }
+}
+
+/// The result of a calibration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    pub intrinsics: Intrinsics,
+    /// The root-mean-square reprojection error over all points, in pixels.
+    pub rms_error: f64,
+    /// The root-mean-square reprojection error for each view, in pixels.
+    pub view_errors: Vec<f64>,
+}
+
+/// An error that can occur during calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationError {
+    /// Fewer than three views were given.
+    NotEnoughViews(usize),
+    /// A view had fewer than four points.
+    NotEnoughPoints { view: usize, points: usize },
+    /// A view had a different number of object and image points.
+    LengthMismatch { view: usize },
+    /// The views didn't constrain the solution, usually because they were all taken from the same angle.
+    Degenerate,
+}
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotEnoughViews(n) => write!(f, "at least 3 views are needed, but {n} were given"),
+            Self::NotEnoughPoints { view, points } => write!(
+                f,
+                "view {view} has {points} points, but at least 4 are needed"
+            ),
+            Self::LengthMismatch { view } => write!(
+                f,
+                "view {view} has a different number of object and image points"
+            ),
+            Self::Degenerate => f.write_str("calibration views are degenerate"),
+        }
+    }
+}
+impl Error for CalibrationError {}
+
+impl Intrinsics {
+    /// Apply lens distortion to a point in normalized image coordinates.
+    pub fn distort(&self, [x, y]: [f64; 2]) -> [f64; 2] {
+        let Distortion { k1, k2, p1, p2, k3 } = self.distortion;
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (k1 + r2 * (k2 + r2 * k3));
+        [
+            x * radial + 2.0 * p1 * x * y + p2 * (r2 + 2.0 * x * x),
+            y * radial + p1 * (r2 + 2.0 * y * y) + 2.0 * p2 * x * y,
+        ]
+    }
+    /// Project a point in camera space (with Z pointing forward) to pixel coordinates, including distortion.
+    pub fn project(&self, point: Vec3) -> [f64; 2] {
+        let [x, y] = self.distort([point.x() / point.z(), point.y() / point.z()]);
+        [self.fx * x + self.cx, self.fy * y + self.cy]
+    }
+    /// Remove lens distortion from a pixel, giving the pixel it would be at with an ideal lens.
+    ///
+    /// This is solved iteratively, so it's much slower than [`Self::distort`].
+    pub fn undistort(&self, [u, v]: [f64; 2]) -> [f64; 2] {
+        let xd = (u - self.cx) / self.fx;
+        let yd = (v - self.cy) / self.fy;
+        let [mut x, mut y] = [xd, yd];
+        for _ in 0..20 {
+            let [dx, dy] = self.distort([x, y]);
+            x -= dx - xd;
+            y -= dy - yd;
+        }
+        [self.fx * x + self.cx, self.fy * y + self.cy]
+    }
+}
+
+/// Calibrate a camera from several views of a planar target.
+///
+/// At least three views are needed, and they should be taken from a variety of angles.
+pub fn calibrate(
+    views: &[CalibrationView],
+    size: FrameSize,
+    options: CalibrationOptions,
+) -> Result<Calibration, CalibrationError> {
+    if views.len() < 3 {
+        return Err(CalibrationError::NotEnoughViews(views.len()));
+    }
+    for (view, v) in views.iter().enumerate() {
+        if v.object.len() != v.image.len() {
+            return Err(CalibrationError::LengthMismatch { view });
+        }
+        if v.object.len() < 4 {
+            return Err(CalibrationError::NotEnoughPoints {
+                view,
+                points: v.object.len(),
+            });
+        }
+    }
+
+    // work in pixel coordinates scaled down to around [-0.5, 0.5] for better conditioning
+    let scale = size.width.max(size.height) as f64;
+    let half_w = size.width as f64 * 0.5;
+    let half_h = size.height as f64 * 0.5;
+    let views = views
+        .iter()
+        .map(|v| CalibrationView {
+            object: v.object.clone(),
+            image: v
+                .image
+                .iter()
+                .map(|&[u, v]| [(u - half_w) / scale, (v - half_h) / scale])
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let homographies = views
+        .iter()
+        .map(homography)
+        .collect::<Option<Vec<_>>>()
+        .ok_or(CalibrationError::Degenerate)?;
+    let [fx, fy, cx, cy] = initial_intrinsics(&homographies).unwrap_or([1.0, 1.0, 0.0, 0.0]);
+
+    let mut params = vec![fx, fy, cx, cy, 0.0, 0.0, 0.0, 0.0, 0.0];
+    for h in &homographies {
+        params.extend(initial_extrinsics(h, [fx, fy, cx, cy]));
+    }
+    let mut fixed = [false; INTRINSIC_PARAMS];
+    fixed[4] = !options.radial;
+    fixed[5] = !options.radial;
+    fixed[6] = !options.tangential;
+    fixed[7] = !options.tangential;
+    fixed[8] = !options.k3;
+
+    refine(&views, &mut params, &fixed, options.max_iters);
+
+    let normalized = intrinsics_from_params(&params);
+    if !(normalized.fx > 0.0 && normalized.fy > 0.0) || params.iter().any(|p| !p.is_finite()) {
+        return Err(CalibrationError::Degenerate);
+    }
+    let mut total = 0.0;
+    let mut count = 0;
+    let view_errors = views
+        .iter()
+        .enumerate()
+        .map(|(i, view)| {
+            let mut err = 0.0;
+            for_each_residual(view, &normalized, view_params(&params, i), |r| {
+                err += r * r;
+            });
+            total += err;
+            count += view.object.len();
+            (err / view.object.len() as f64).sqrt() * scale
+        })
+        .collect();
+    Ok(Calibration {
+        intrinsics: Intrinsics {
+            width: size.width,
+            height: size.height,
+            fx: normalized.fx * scale,
+            fy: normalized.fy * scale,
+            cx: normalized.cx * scale + half_w,
+            cy: normalized.cy * scale + half_h,
+            distortion: normalized.distortion,
+        },
+        rms_error: (total / count as f64).sqrt() * scale,
+        view_errors,
+    })
+}
+
+const INTRINSIC_PARAMS: usize = 9;
+const VIEW_PARAMS: usize = 6;
+
+fn intrinsics_from_params(params: &[f64]) -> Intrinsics {
+    Intrinsics {
+        width: 0,
+        height: 0,
+        fx: params[0],
+        fy: params[1],
+        cx: params[2],
+        cy: params[3],
+        distortion: Distortion {
+            k1: params[4],
+            k2: params[5],
+            p1: params[6],
+            p2: params[7],
+            k3: params[8],
+        },
+    }
+}
+
+fn view_params(params: &[f64], view: usize) -> &[f64] {
+    let start = INTRINSIC_PARAMS + view * VIEW_PARAMS;
+    &params[start..(start + VIEW_PARAMS)]
+}
+
+/// Rotate a point by a rotation vector.
+fn rotate(rvec: [f64; 3], p: Vec3) -> Vec3 {
+    let r = Vec3(rvec);
+    let theta = r.abs();
+    if theta < 1e-12 {
+        return p + cross(r, p);
+    }
+    let k = Vec3(rvec.map(|x| x / theta));
+    let (sin, cos) = theta.sin_cos();
+    let kxp = cross(k, p);
+    let kdp = k.dot(p);
+    Vec3(std::array::from_fn(|i| {
+        p.0[i] * cos + kxp.0[i] * sin + k.0[i] * kdp * (1.0 - cos)
+    }))
+}
+
+fn cross(a: Vec3, b: Vec3) -> Vec3 {
+    let [ax, ay, az] = a.0;
+    let [bx, by, bz] = b.0;
+    Vec3([ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx])
+}
+
+/// Call a function with each residual (alternating X and Y) for a view.
+fn for_each_residual(
+    view: &CalibrationView,
+    intrinsics: &Intrinsics,
+    params: &[f64],
+    mut f: impl FnMut(f64),
+) {
+    let rvec = [params[0], params[1], params[2]];
+    let t = Vec3([params[3], params[4], params[5]]);
+    for (&[x, y], &[u, v]) in view.object.iter().zip(&view.image) {
+        let p = rotate(rvec, Vec3([x, y, 0.0])) + t;
+        let [pu, pv] = intrinsics.project(p);
+        f(pu - u);
+        f(pv - v);
+    }
+}
+
+fn residuals(view: &CalibrationView, intrinsics: &[f64], params: &[f64], out: &mut Vec<f64>) {
+    out.clear();
+    for_each_residual(view, &intrinsics_from_params(intrinsics), params, |r| {
+        out.push(r)
+    });
+}
+
+fn total_cost(views: &[CalibrationView], params: &[f64]) -> f64 {
+    let intrinsics = intrinsics_from_params(params);
+    let mut cost = 0.0;
+    for (i, view) in views.iter().enumerate() {
+        for_each_residual(view, &intrinsics, view_params(params, i), |r| cost += r * r);
+    }
+    cost
+}
+
+/// Refine all of the parameters with Levenberg-Marquardt.
+fn refine(
+    views: &[CalibrationView],
+    params: &mut Vec<f64>,
+    fixed: &[bool; INTRINSIC_PARAMS],
+    max_iters: usize,
+) {
+    let n = params.len();
+    let mut lambda = 1e-3;
+    let mut cost = total_cost(views, params);
+    let mut base = Vec::new();
+    let mut shifted = Vec::new();
+    let mut jac = Vec::new();
+    for _ in 0..max_iters {
+        let mut jtj = vec![0.0; n * n];
+        let mut jtr = vec![0.0; n];
+        for (i, view) in views.iter().enumerate() {
+            let start = INTRINSIC_PARAMS + i * VIEW_PARAMS;
+            // the residuals for this view only depend on the intrinsics and its own extrinsics
+            let indices = (0..INTRINSIC_PARAMS)
+                .chain(start..(start + VIEW_PARAMS))
+                .collect::<Vec<_>>();
+            let mut local = params[..INTRINSIC_PARAMS].to_vec();
+            local.extend_from_slice(view_params(params, i));
+            residuals(
+                view,
+                &local[..INTRINSIC_PARAMS],
+                &local[INTRINSIC_PARAMS..],
+                &mut base,
+            );
+            let m = base.len();
+            jac.clear();
+            jac.resize(m * indices.len(), 0.0);
+            for j in 0..indices.len() {
+                if j < INTRINSIC_PARAMS && fixed[j] {
+                    continue;
+                }
+                let old = local[j];
+                let step = 1e-7 * old.abs().max(1e-2);
+                local[j] = old + step;
+                residuals(
+                    view,
+                    &local[..INTRINSIC_PARAMS],
+                    &local[INTRINSIC_PARAMS..],
+                    &mut shifted,
+                );
+                local[j] = old;
+                for k in 0..m {
+                    jac[k * indices.len() + j] = (shifted[k] - base[k]) / step;
+                }
+            }
+            let w = indices.len();
+            for k in 0..m {
+                let row = &jac[(k * w)..((k + 1) * w)];
+                for (a, &ia) in indices.iter().enumerate() {
+                    if row[a] == 0.0 {
+                        continue;
+                    }
+                    jtr[ia] += row[a] * base[k];
+                    for (b, &ib) in indices.iter().enumerate() {
+                        jtj[ia * n + ib] += row[a] * row[b];
+                    }
+                }
+            }
+        }
+        for (j, &f) in fixed.iter().enumerate() {
+            if f {
+                jtj[j * n + j] = 1.0;
+            }
+        }
+
+        let mut improved = false;
+        while lambda < 1e10 {
+            let mut a = jtj.clone();
+            for j in 0..n {
+                a[j * n + j] += lambda * jtj[j * n + j].max(1e-12);
+            }
+            let mut delta = jtr.iter().map(|x| -x).collect::<Vec<_>>();
+            if !solve(&mut a, &mut delta, n) {
+                lambda *= 10.0;
+                continue;
+            }
+            let candidate = params
+                .iter()
+                .zip(&delta)
+                .map(|(p, d)| p + d)
+                .collect::<Vec<_>>();
+            let new_cost = total_cost(views, &candidate);
+            if new_cost < cost {
+                let rel = (cost - new_cost) / cost.max(f64::MIN_POSITIVE);
+                *params = candidate;
+                cost = new_cost;
+                lambda = (lambda * 0.1).max(1e-12);
+                improved = rel > 1e-12;
+                break;
+            }
+            lambda *= 10.0;
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// Solve `a * x = b` in place with Gaussian elimination, returning false if the matrix is singular.
+fn solve(a: &mut [f64], b: &mut [f64], n: usize) -> bool {
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))
+            .unwrap();
+        if a[pivot * n + col].abs() < 1e-300 {
+            return false;
+        }
+        if pivot != col {
+            for k in 0..n {
+                a.swap(col * n + k, pivot * n + k);
+            }
+            b.swap(col, pivot);
+        }
+        let diag = a[col * n + col];
+        for row in (col + 1)..n {
+            let factor = a[row * n + col] / diag;
+            if factor == 0.0 {
+                continue;
+            }
+            for k in col..n {
+                a[row * n + k] -= factor * a[col * n + k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    for row in (0..n).rev() {
+        let mut sum = b[row];
+        for k in (row + 1)..n {
+            sum -= a[row * n + k] * b[k];
+        }
+        b[row] = sum / a[row * n + row];
+    }
+    b.iter().all(|x| x.is_finite())
+}
+
+/// Find the eigenvector of a symmetric matrix with the smallest eigenvalue, using the Jacobi method.
+fn smallest_eigenvector(mut a: Vec<f64>, n: usize) -> Vec<f64> {
+    let mut v = vec![0.0; n * n];
+    for i in 0..n {
+        v[i * n + i] = 1.0;
+    }
+    for _ in 0..100 {
+        let off = (0..n)
+            .flat_map(|i| ((i + 1)..n).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * n + j] * a[i * n + j])
+            .sum::<f64>();
+        if off < 1e-30 {
+            break;
+        }
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[p * n + q];
+                if apq.abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = (t * t + 1.0).sqrt().recip();
+                let s = t * c;
+                for k in 0..n {
+                    let akp = a[k * n + p];
+                    let akq = a[k * n + q];
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let apk = a[p * n + k];
+                    let aqk = a[q * n + k];
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let vkp = v[k * n + p];
+                    let vkq = v[k * n + q];
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let min = (0..n)
+        .min_by(|&i, &j| a[i * n + i].total_cmp(&a[j * n + j]))
+        .unwrap();
+    (0..n).map(|k| v[k * n + min]).collect()
+}
+
+/// Find a similarity transform that centers points at the origin with an average distance of √2.
+fn normalizing_transform(points: &[[f64; 2]]) -> Mat3 {
+    let n = points.len() as f64;
+    let [sx, sy] = points
+        .iter()
+        .fold([0.0, 0.0], |[ax, ay], [x, y]| [ax + x, ay + y]);
+    let (mx, my) = (sx / n, sy / n);
+    let dist = points
+        .iter()
+        .map(|[x, y]| ((x - mx).powi(2) + (y - my).powi(2)).sqrt())
+        .sum::<f64>()
+        / n;
+    let s = if dist > 0.0 {
+        std::f64::consts::SQRT_2 / dist
+    } else {
+        1.0
+    };
+    Mat3([s, 0.0, -s * mx, 0.0, s, -s * my, 0.0, 0.0, 1.0])
+}
+
+fn transform_point(m: &Mat3, [x, y]: [f64; 2]) -> [f64; 2] {
+    let Vec3([u, v, w]) = m.mul_vec(Vec3([x, y, 1.0]));
+    [u / w, v / w]
+}
+
+/// Fit a homography from the object points to the image points with the normalized DLT.
+fn homography(view: &CalibrationView) -> Option<Mat3> {
+    let to = normalizing_transform(&view.object);
+    let ti = normalizing_transform(&view.image);
+    let mut ata = vec![0.0; 81];
+    for (&o, &i) in view.object.iter().zip(&view.image) {
+        let [x, y] = transform_point(&to, o);
+        let [u, v] = transform_point(&ti, i);
+        let rows = [
+            [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, -u],
+            [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, -v],
+        ];
+        for row in rows {
+            for a in 0..9 {
+                for b in 0..9 {
+                    ata[a * 9 + b] += row[a] * row[b];
+                }
+            }
+        }
+    }
+    let h = smallest_eigenvector(ata, 9);
+    let hn = Mat3(h.try_into().ok()?);
+    if ti.det().abs() < 1e-300 {
+        return None;
+    }
+    let h = ti.inverse().mul_mat(&hn).mul_mat(&to);
+    let scale = h.0[8];
+    (scale.abs() > 1e-300 && h.0.iter().all(|x| x.is_finite()))
+        .then(|| Mat3(h.0.map(|x| x / scale)))
+}
+
+/// Find the closed-form solution for the intrinsics, assuming zero skew.
+fn initial_intrinsics(homographies: &[Mat3]) -> Option<[f64; 4]> {
+    // column `i` of the homography is (h[i], h[3 + i], h[6 + i])
+    let v = |h: &Mat3, i: usize, j: usize| {
+        let hi = [h.0[i], h.0[3 + i], h.0[6 + i]];
+        let hj = [h.0[j], h.0[3 + j], h.0[6 + j]];
+        [
+            hi[0] * hj[0],
+            hi[0] * hj[1] + hi[1] * hj[0],
+            hi[1] * hj[1],
+            hi[2] * hj[0] + hi[0] * hj[2],
+            hi[2] * hj[1] + hi[1] * hj[2],
+            hi[2] * hj[2],
+        ]
+    };
+    let mut vtv = vec![0.0; 36];
+    for h in homographies {
+        let v12 = v(h, 0, 1);
+        let v11 = v(h, 0, 0);
+        let v22 = v(h, 1, 1);
+        let diff: [f64; 6] = std::array::from_fn(|k| v11[k] - v22[k]);
+        for row in [v12, diff] {
+            for a in 0..6 {
+                for b in 0..6 {
+                    vtv[a * 6 + b] += row[a] * row[b];
+                }
+            }
+        }
+    }
+    let mut b = smallest_eigenvector(vtv, 6);
+    if b[0] < 0.0 {
+        b.iter_mut().for_each(|x| *x = -*x);
+    }
+    let [b11, b12, b22, b13, b23, b33] = b[..] else {
+        return None;
+    };
+    let denom = b11 * b22 - b12 * b12;
+    let v0 = (b12 * b13 - b11 * b23) / denom;
+    let lambda = b33 - (b13 * b13 + v0 * (b12 * b13 - b11 * b23)) / b11;
+    let alpha = (lambda / b11).sqrt();
+    let beta = (lambda * b11 / denom).sqrt();
+    let u0 = -b13 * alpha * alpha / lambda;
+    [alpha, beta, u0, v0]
+        .iter()
+        .all(|x| x.is_finite())
+        .then_some([alpha, beta, u0, v0])
+        .filter(|_| alpha > 0.0 && beta > 0.0)
+}
+
+/// Find the rotation vector and translation for a view from its homography.
+fn initial_extrinsics(h: &Mat3, [fx, fy, cx, cy]: [f64; 4]) -> [f64; 6] {
+    let k_inv = Mat3([fx, 0.0, cx, 0.0, fy, cy, 0.0, 0.0, 1.0]).inverse();
+    let col = |i: usize| Vec3([h.0[i], h.0[3 + i], h.0[6 + i]]);
+    let r1 = k_inv.mul_vec(col(0));
+    let r2 = k_inv.mul_vec(col(1));
+    let t = k_inv.mul_vec(col(2));
+    let mut lambda = 2.0 / (r1.abs() + r2.abs());
+    if t.z() < 0.0 {
+        lambda = -lambda;
+    }
+    let scale = |v: Vec3| Vec3(v.0.map(|x| x * lambda));
+    let (r1, r2, t) = (scale(r1), scale(r2), scale(t));
+    let r3 = cross(r1, r2);
+    // columns are r1, r2, and r3
+    let mut r = Mat3([
+        r1.x(),
+        r2.x(),
+        r3.x(),
+        r1.y(),
+        r2.y(),
+        r3.y(),
+        r1.z(),
+        r2.z(),
+        r3.z(),
+    ]);
+    // find the closest rotation matrix with Newton's method for the polar decomposition
+    for _ in 0..10 {
+        if r.det().abs() < 1e-12 {
+            break;
+        }
+        let inv = r.inverse();
+        let inv_t = Mat3(std::array::from_fn(|i| inv.0[(i % 3) * 3 + i / 3]));
+        r = Mat3(std::array::from_fn(|i| 0.5 * (r.0[i] + inv_t.0[i])));
+    }
+    let [rx, ry, rz] = rotation_vector(&r);
+    [rx, ry, rz, t.x(), t.y(), t.z()]
+}
+
+/// Convert a rotation matrix to a rotation vector.
+fn rotation_vector(r: &Mat3) -> [f64; 3] {
+    let m = r.0;
+    let cos = ((m[0] + m[4] + m[8] - 1.0) * 0.5).clamp(-1.0, 1.0);
+    let theta = cos.acos();
+    let axis = [m[7] - m[5], m[2] - m[6], m[3] - m[1]];
+    let sin = theta.sin();
+    if sin.abs() < 1e-9 {
+        if cos > 0.0 {
+            return [axis[0] * 0.5, axis[1] * 0.5, axis[2] * 0.5];
+        }
+        // rotation by pi, so the axis comes from the diagonal
+        let x = ((m[0] + 1.0) * 0.5).max(0.0).sqrt();
+        let y = ((m[4] + 1.0) * 0.5).max(0.0).sqrt().copysign(m[1] + m[3]);
+        let z = ((m[8] + 1.0) * 0.5).max(0.0).sqrt().copysign(m[2] + m[6]);
+        return [x * theta, y * theta, z * theta];
+    }
+    let s = theta / (2.0 * sin);
+    axis.map(|x| x * s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synthetic_views(truth: &Intrinsics) -> Vec<CalibrationView> {
+        let object = (0..6)
+            .flat_map(|y| (0..9).map(move |x| [x as f64 * 25.0, y as f64 * 25.0]))
+            .collect::<Vec<_>>();
+        let poses = [
+            ([0.1, -0.2, 0.05], [-100.0, -60.0, 500.0]),
+            ([-0.3, 0.1, 0.0], [-80.0, -70.0, 450.0]),
+            ([0.2, 0.35, -0.1], [-120.0, -50.0, 600.0]),
+            ([0.4, -0.1, 0.2], [-90.0, -80.0, 550.0]),
+            ([-0.2, -0.3, -0.15], [-110.0, -40.0, 520.0]),
+        ];
+        poses
+            .iter()
+            .map(|&(rvec, t)| CalibrationView {
+                image: object
+                    .iter()
+                    .map(|&[x, y]| truth.project(rotate(rvec, Vec3([x, y, 0.0])) + Vec3(t)))
+                    .collect(),
+                object: object.clone(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn synthetic_calibration() {
+        let truth = Intrinsics {
+            width: 640,
+            height: 480,
+            fx: 610.0,
+            fy: 605.0,
+            cx: 330.0,
+            cy: 235.0,
+            distortion: Distortion {
+                k1: -0.12,
+                k2: 0.05,
+                p1: 0.001,
+                p2: -0.0015,
+                k3: 0.0,
+            },
+        };
+        let views = synthetic_views(&truth);
+        let calib = calibrate(
+            &views,
+            FrameSize {
+                width: 640,
+                height: 480,
+            },
+            CalibrationOptions::default(),
+        )
+        .unwrap();
+        let found = calib.intrinsics;
+        assert!(calib.rms_error < 1e-3, "RMS error of {}", calib.rms_error);
+        assert!((found.fx - truth.fx).abs() < 0.1);
+        assert!((found.fy - truth.fy).abs() < 0.1);
+        assert!((found.cx - truth.cx).abs() < 0.1);
+        assert!((found.cy - truth.cy).abs() < 0.1);
+        assert!((found.distortion.k1 - truth.distortion.k1).abs() < 1e-3);
+        assert!((found.distortion.p1 - truth.distortion.p1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn undistort_inverts_distort() {
+        let intr = Intrinsics {
+            width: 640,
+            height: 480,
+            fx: 500.0,
+            fy: 500.0,
+            cx: 320.0,
+            cy: 240.0,
+            distortion: Distortion {
+                k1: -0.2,
+                k2: 0.03,
+                p1: 0.001,
+                p2: 0.002,
+                k3: 0.0,
+            },
+        };
+        for [u, v] in [[320.0, 240.0], [10.0, 20.0], [600.0, 400.0]] {
+            let [ux, uy] = intr.undistort([u, v]);
+            let [dx, dy] = intr.distort([(ux - intr.cx) / intr.fx, (uy - intr.cy) / intr.fy]);
+            let back = [dx * intr.fx + intr.cx, dy * intr.fy + intr.cy];
+            assert!((back[0] - u).abs() < 1e-6 && (back[1] - v).abs() < 1e-6);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher, Hash};
+use std::time::SystemTime;
 #[cfg(feature = "supply")]
 use supply::prelude::*;
 
@@ -109,6 +110,80 @@ pub struct FrameSize {
     pub height: u32,
 }
 
+/// Lens distortion coefficients, using the Brown-Conrady model.
+///
+/// `k1`, `k2`, and `k3` are the radial coefficients, and `p1` and `p2` are tangential.
+/// These are in normalized image coordinates, so they don't depend on the frame size.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Distortion {
+    pub k1: f64,
+    pub k2: f64,
+    pub p1: f64,
+    pub p2: f64,
+    pub k3: f64,
+}
+impl Distortion {
+    /// Check if all of the coefficients are zero.
+    pub fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Calibrated intrinsic parameters for a camera.
+///
+/// The focal lengths and principal point are in pixels, for a frame of the given size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Intrinsics {
+    /// The width of the frames that this was calibrated with.
+    pub width: u32,
+    /// The height of the frames that this was calibrated with.
+    pub height: u32,
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Distortion::is_zero")
+    )]
+    pub distortion: Distortion,
+}
+impl Intrinsics {
+    /// Rescale these intrinsics for a frame of a different size.
+    pub fn scaled_to(&self, size: FrameSize) -> Self {
+        if size.width == self.width && size.height == self.height {
+            return *self;
+        }
+        let sx = size.width as f64 / self.width as f64;
+        let sy = size.height as f64 / self.height as f64;
+        Self {
+            width: size.width,
+            height: size.height,
+            fx: self.fx * sx,
+            fy: self.fy * sy,
+            cx: self.cx * sx,
+            cy: self.cy * sy,
+            distortion: self.distortion,
+        }
+    }
+}
+
+/// When a frame was captured, along with its sequence number from the camera if it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FrameTimestamp {
+    /// The time the frame was captured.
+    ///
+    /// This comes from the driver when possible, and is otherwise the time that the frame finished being read.
+    pub captured: SystemTime,
+    /// The sequence number of the frame, if the camera reports one.
+    ///
+    /// These should be consecutive, so a gap means that frames were dropped.
+    pub sequence: Option<u64>,
+}
+
 #[cfg(feature = "supply")]
 #[ty_tag::tag]
 pub type FovTag = Fov;
@@ -116,3 +191,11 @@ pub type FovTag = Fov;
 #[cfg(feature = "supply")]
 #[ty_tag::tag]
 pub type FrameSizeTag = FrameSize;
+
+#[cfg(feature = "supply")]
+#[ty_tag::tag]
+pub type IntrinsicsTag = Intrinsics;
+
+#[cfg(feature = "supply")]
+#[ty_tag::tag]
+pub type FrameTimestampTag = FrameTimestamp;
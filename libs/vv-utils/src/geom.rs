@@ -114,6 +114,26 @@ impl Mat3 {
         *m8 /= sz;
         Vec3([sx, sy, sz])
     }
+    /// Build a rotation matrix from a set of XYZ Euler angles.
+    ///
+    /// This is the inverse of [`Self::to_euler`].
+    pub fn from_euler(euler: EulerXYZ) -> Self {
+        let EulerXYZ([roll, pitch, yaw]) = euler;
+        let (sr, cr) = roll.sin_cos();
+        let (sp, cp) = pitch.sin_cos();
+        let (sy, cy) = yaw.sin_cos();
+        Self([
+            cy * cp,
+            -cp * sy,
+            sp,
+            cr * sy + cy * sr * sp,
+            cr * cy - sr * sp * sy,
+            -cp * sr,
+            sr * sy - cr * cy * sp,
+            cy * sr + cr * sp * sy,
+            cr * cp,
+        ])
+    }
     /// Convert this matrix to a quaternion.
     ///
     /// This assumes that the matrix is orthonormal.
@@ -500,6 +520,19 @@ mod tests {
         assert!(euler_approx_eq(euler, EulerXYZ([roll, pitch, yaw])));
     }
 
+    #[test]
+    fn test_mat3_from_euler() {
+        let euler = EulerXYZ([PI / 18.0, -PI / 9.0, PI / 6.0]);
+        let mat = Mat3::from_euler(euler);
+        assert!(approx_eq(mat.det(), 1.0));
+        assert!(euler_approx_eq(mat.to_euler(), euler));
+
+        // 90 degree rotation around Z-axis
+        let mat = Mat3::from_euler(EulerXYZ([0.0, 0.0, FRAC_PI_2]));
+        let expected = Mat3([0.0, -1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+        assert!(mat3_approx_eq(&mat, &expected));
+    }
+
     #[test]
     fn test_rotation_consistency() {
         // Create a rotation matrix, convert to quaternion,
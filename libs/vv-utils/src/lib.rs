@@ -1,3 +1,4 @@
+pub mod calib;
 pub mod common_types;
 pub mod geom;
 pub mod mutex;
@@ -0,0 +1,255 @@
+//! Chunk-wise conversion kernels.
+//!
+//! These convert [`LANES`] pixels at a time, with each channel split out into its own array. Every step is a loop over those arrays
+//! without branches, which LLVM turns into SIMD instructions on both x86 and ARM without any platform-specific code. The arithmetic is
+//! the same as in the scalar functions in the parent module, so the results are bit-identical to them.
+
+use crate::buffer::PixelFormat;
+use std::sync::LazyLock;
+
+/// The number of pixels converted at a time.
+pub const LANES: usize = 16;
+
+type Lanes = [i32; LANES];
+
+#[inline(always)]
+fn lanes(mut f: impl FnMut(usize) -> i32) -> Lanes {
+    let mut out = [0; LANES];
+    for (i, x) in out.iter_mut().enumerate() {
+        *x = f(i);
+    }
+    out
+}
+
+/// Precomputed tables for RGB to HSV conversion.
+///
+/// A full table indexed by RGB would take 48 MiB, which is much bigger than any cache, so it'd be slower than just doing the math.
+/// The saturation only depends on the maximum channel and the difference between the maximum and minimum, and the hue only depends on
+/// that difference and the difference between the other two channels, so two 2D tables give the same results in 192 KiB. These
+/// replace both of the divisions, which can't be vectorized.
+struct HsvTables {
+    /// `255 * delta / max`, with the multiplication wrapping like in [`rgb2hsv`](super::rgb2hsv), indexed by `max << 8 | delta`.
+    saturation: Box<[u8]>,
+    /// `43 * diff / delta`, indexed by `delta << 9 | (diff + 256)`.
+    hue: Box<[i8]>,
+}
+static HSV_TABLES: LazyLock<HsvTables> = LazyLock::new(|| {
+    let mut saturation = vec![0; 256 * 256].into_boxed_slice();
+    let mut hue = vec![0; 256 * 512].into_boxed_slice();
+    for max in 1..256i16 {
+        for delta in 0..=max {
+            saturation[((max as usize) << 8) | delta as usize] =
+                (255i16.wrapping_mul(delta) / max) as u8;
+        }
+    }
+    for delta in 1..256i16 {
+        for diff in -delta..=delta {
+            hue[((delta as usize) << 9) | (diff + 256) as usize] = (43 * diff / delta) as i8;
+        }
+    }
+    HsvTables { saturation, hue }
+});
+
+fn rgb2hsv([r, g, b]: [Lanes; 3]) -> [Lanes; 3] {
+    let tables = &*HSV_TABLES;
+    let max = lanes(|i| r[i].max(g[i]).max(b[i]));
+    let min = lanes(|i| r[i].min(g[i]).min(b[i]));
+    let delta = lanes(|i| max[i] - min[i]);
+    // same priority as the scalar version: red, then green, then blue
+    let base = lanes(|i| {
+        if max[i] == r[i] {
+            0
+        } else if max[i] == g[i] {
+            85
+        } else {
+            171
+        }
+    });
+    let diff = lanes(|i| {
+        if max[i] == r[i] {
+            g[i] - b[i]
+        } else if max[i] == g[i] {
+            b[i] - r[i]
+        } else {
+            r[i] - g[i]
+        }
+    });
+    let s = lanes(|i| tables.saturation[((max[i] as usize) << 8) | delta[i] as usize] as i32);
+    let q = lanes(|i| tables.hue[((delta[i] as usize) << 9) | (diff[i] + 256) as usize] as i32);
+    let h = lanes(|i| {
+        if delta[i] == 0 {
+            0
+        } else {
+            (base[i] + q[i]) & 255
+        }
+    });
+    [h, s, max]
+}
+/// Turn a condition into a mask of all ones or all zeros.
+#[inline(always)]
+fn mask(cond: bool) -> i32 {
+    -(cond as i32)
+}
+
+/// Divide by 43, for numbers from 0 to `255 * 43`.
+///
+/// Vector instructions can't divide, but this multiplication gives exactly the same results over that range.
+#[inline(always)]
+fn div43(x: i32) -> i32 {
+    (x * 12193) >> 19
+}
+fn hsv2rgb([h, s, v]: [Lanes; 3]) -> [Lanes; 3] {
+    let region = lanes(|i| div43(h[i]));
+    let c = lanes(|i| (v[i] * s[i]) >> 8);
+    // the hue is at most 255, so this is the same as `h % 85`
+    let rem =
+        lanes(|i| h[i] - 85 * ((h[i] >= 85) as i32 + (h[i] >= 170) as i32 + (h[i] == 255) as i32));
+    let x = lanes(|i| div43(c[i] * (43 - (rem[i] - 43).abs())));
+    let m = lanes(|i| v[i] - c[i]);
+    let c = lanes(|i| (c[i] + m[i]).clamp(0, 255));
+    let x = lanes(|i| (x[i] + m[i]).clamp(0, 255));
+    // pick each channel with masks, since LLVM won't vectorize the chains of ifs this would take otherwise
+    let pick = |i: usize, c_in: [i32; 2], x_in: [i32; 2]| {
+        let is_c = mask(region[i] == c_in[0] || region[i] == c_in[1]);
+        let is_x = mask(region[i] == x_in[0] || region[i] == x_in[1]);
+        let ch = (c[i] & is_c) | (x[i] & is_x) | (m[i] & !(is_c | is_x));
+        let gray = mask(s[i] == 0);
+        (v[i] & gray) | (ch & !gray)
+    };
+    [
+        lanes(|i| pick(i, [0, 5], [1, 4])),
+        lanes(|i| pick(i, [1, 2], [0, 3])),
+        lanes(|i| pick(i, [3, 4], [2, 5])),
+    ]
+}
+fn rgb2ycc([r, g, b]: [Lanes; 3]) -> [Lanes; 3] {
+    [
+        lanes(|i| ((r[i] * 77 + g[i] * 150 + b[i] * 29) / 256).clamp(0, 255)),
+        lanes(|i| ((-43 * r[i] - 85 * g[i] + 128 * b[i]) / 256 + 128).clamp(0, 255)),
+        lanes(|i| ((128 * r[i] - 107 * g[i] - 21 * b[i]) / 256 + 128).clamp(0, 255)),
+    ]
+}
+fn ycc2rgb([y, cb, cr]: [Lanes; 3]) -> [Lanes; 3] {
+    [
+        lanes(|i| ((256 * y[i] + 359 * (cr[i] - 128)) / 256).clamp(0, 255)),
+        lanes(|i| ((256 * y[i] - (88 * (cb[i] - 128) + 183 * (cr[i] - 128))) / 256).clamp(0, 255)),
+        lanes(|i| ((256 * y[i] + 454 * (cb[i] - 128)) / 256).clamp(0, 255)),
+    ]
+}
+fn rgb2luma([r, g, b]: [Lanes; 3]) -> Lanes {
+    lanes(|i| ((r[i] * 77 + g[i] * 150 + b[i] * 29) >> 8).min(255))
+}
+
+/// Pixels in the color space they were decoded into.
+///
+/// YCbCr sources are kept in YCbCr rather than going through RGB, since the scalar conversions from YCbCr to YUYV don't either.
+enum Decoded {
+    Rgb([Lanes; 3]),
+    Ycc([Lanes; 3]),
+}
+impl Decoded {
+    fn rgb(self) -> [Lanes; 3] {
+        match self {
+            Self::Rgb(rgb) => rgb,
+            Self::Ycc(ycc) => ycc2rgb(ycc),
+        }
+    }
+    fn ycc(self) -> [Lanes; 3] {
+        match self {
+            Self::Rgb(rgb) => rgb2ycc(rgb),
+            Self::Ycc(ycc) => ycc,
+        }
+    }
+}
+
+/// Load the first three channels of each pixel.
+fn load(src: &[u8], stride: usize) -> [Lanes; 3] {
+    std::array::from_fn(|c| lanes(|i| src[i * stride + c] as i32))
+}
+/// Store three channels into each pixel, and fill in the alpha channel if there's room for one.
+fn store(dst: &mut [u8], stride: usize, channels: [Lanes; 3]) {
+    for (i, px) in dst.chunks_exact_mut(stride).enumerate() {
+        for c in 0..3 {
+            px[c] = channels[c][i] as u8;
+        }
+        if stride == 4 {
+            px[3] = 255;
+        }
+    }
+}
+
+/// Check if a format has a kernel.
+pub fn supported(format: PixelFormat) -> bool {
+    matches!(
+        format,
+        PixelFormat::LUMA
+            | PixelFormat::RGB
+            | PixelFormat::HSV
+            | PixelFormat::YCC
+            | PixelFormat::RGBA
+            | PixelFormat::YUYV
+    )
+}
+
+/// Get the number of bytes that [`LANES`] pixels take up in a format.
+pub fn lane_bytes(format: PixelFormat) -> usize {
+    if format == PixelFormat::YUYV {
+        LANES * 2
+    } else {
+        LANES * format.pixel_size()
+    }
+}
+
+/// Convert [`LANES`] pixels between two different [supported](supported) formats.
+///
+/// `src` and `dst` must be exactly [`lane_bytes`] long for their formats.
+pub fn convert(src_format: PixelFormat, dst_format: PixelFormat, src: &[u8], dst: &mut [u8]) {
+    let decoded = match src_format {
+        PixelFormat::LUMA => {
+            let y = lanes(|i| src[i] as i32);
+            Decoded::Rgb([y; 3])
+        }
+        PixelFormat::RGB => Decoded::Rgb(load(src, 3)),
+        PixelFormat::RGBA => Decoded::Rgb(load(src, 4)),
+        PixelFormat::HSV => Decoded::Rgb(hsv2rgb(load(src, 3))),
+        PixelFormat::YCC => Decoded::Ycc(load(src, 3)),
+        PixelFormat::YUYV => {
+            if dst_format == PixelFormat::LUMA {
+                // this takes the luma straight from the YUYV data, unlike going from YCbCr
+                for (i, y) in dst.iter_mut().enumerate() {
+                    *y = src[i * 2];
+                }
+                return;
+            }
+            Decoded::Ycc([
+                lanes(|i| src[i * 2] as i32),
+                lanes(|i| src[(i & !1) * 2 + 1] as i32),
+                lanes(|i| src[(i & !1) * 2 + 3] as i32),
+            ])
+        }
+        _ => unreachable!("unsupported source format {src_format}"),
+    };
+    match dst_format {
+        PixelFormat::LUMA => {
+            let y = rgb2luma(decoded.rgb());
+            for (i, out) in dst.iter_mut().enumerate() {
+                *out = y[i] as u8;
+            }
+        }
+        PixelFormat::RGB => store(dst, 3, decoded.rgb()),
+        PixelFormat::RGBA => store(dst, 4, decoded.rgb()),
+        PixelFormat::HSV => store(dst, 3, rgb2hsv(decoded.rgb())),
+        PixelFormat::YCC => store(dst, 3, decoded.ycc()),
+        PixelFormat::YUYV => {
+            let [y, cb, cr] = decoded.ycc();
+            for (i, px) in dst.chunks_exact_mut(4).enumerate() {
+                let (a, b) = (i * 2, i * 2 + 1);
+                px[0] = y[a] as u8;
+                px[1] = (cb[a] as u8).midpoint(cb[b] as u8);
+                px[2] = y[b] as u8;
+                px[3] = (cr[a] as u8).midpoint(cr[b] as u8);
+            }
+        }
+        _ => unreachable!("unsupported destination format {dst_format}"),
+    }
+}
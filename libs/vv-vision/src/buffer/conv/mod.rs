@@ -1,14 +1,24 @@
 //! All of these conversion functions take an input and output array and can be used directly with [`broadcast2`](crate::broadcast::broadcast2) and [`par_broadcast2`](crate::broadcast::par_broadcast2).
 //! All functions have the convention of `<input format>2<output format>`, with `i` being used as a function prefix for in-place operations.
 //! Note that any YUYV conversions need two pixels to operate on rather than just one.
+//!
+//! Converting whole buffers should go through [`convert`] or [`convert_inplace`] instead, which run on chunk-wise kernels that convert
+//! many pixels at once. The per-pixel functions here are the reference for them, and both give exactly the same results.
 
-use crate::broadcast::{Broadcast2, ParBroadcast2};
+use crate::broadcast::{Broadcast2, ParBroadcast2, broadcast_pool, broadcast2, par_broadcast2};
 use crate::buffer::PixelFormat;
+use rayon::prelude::*;
 use std::cmp::Ordering;
 
+mod kernels;
 #[cfg(test)]
 mod tests;
 
+pub use kernels::LANES;
+
+/// The number of pixels that each thread converts at a time.
+const BLOCK: usize = LANES * 64;
+
 /// Make a conversion operate in place
 #[inline(always)]
 pub fn to_inplace<const N: usize>(f: impl Fn([u8; N]) -> [u8; N]) -> impl Fn(&mut [u8; N]) {
@@ -49,7 +59,9 @@ pub fn rgb2hsv(from: [u8; 3]) -> [u8; 3] {
     if delta == 0 {
         [0, 0, v]
     } else {
-        let s = ((255 * delta) / max) as u8;
+        // 255 * delta overflows an i16 for deltas over 128. Release builds have always wrapped here, so this does too rather than
+        // panicking in debug builds; fixing it would change the saturation of strongly saturated colors.
+        let s = (255i16.wrapping_mul(delta) / max) as u8;
         #[allow(clippy::identity_op)]
         let h16 = if max == r {
             0 + 43 * (g - b) / delta
@@ -299,3 +311,78 @@ impl ParBroadcast2<&[u8], &mut [u8], ()> for ConvertBroadcast {
         }
     }
 }
+
+/// Convert pixel data from one format to another.
+///
+/// Conversions between the known formats run on chunk-wise kernels, which are vectorized by the compiler, and use lookup tables for
+/// RGB to HSV. Anything else, like anonymous formats, falls back to [`ConvertBroadcast`]. Either way, the result is the same as
+/// running [`ConvertBroadcast`] over every pixel.
+pub fn convert(src_format: PixelFormat, dst_format: PixelFormat, src: &[u8], dst: &mut [u8]) {
+    if src_format == dst_format {
+        dst.copy_from_slice(src);
+        return;
+    }
+    let scalar = ConvertBroadcast::new(src_format, dst_format);
+    if !(kernels::supported(src_format) && kernels::supported(dst_format)) {
+        par_broadcast2(scalar, src, dst);
+        return;
+    }
+    let (src_lane, dst_lane) = (
+        kernels::lane_bytes(src_format),
+        kernels::lane_bytes(dst_format),
+    );
+    broadcast_pool().install(|| {
+        src.par_chunks(src_lane * (BLOCK / LANES))
+            .zip(dst.par_chunks_mut(dst_lane * (BLOCK / LANES)))
+            .for_each(|(src, dst)| {
+                let mut src_lanes = src.chunks_exact(src_lane);
+                let mut dst_lanes = dst.chunks_exact_mut(dst_lane);
+                for (src, dst) in (&mut src_lanes).zip(&mut dst_lanes) {
+                    kernels::convert(src_format, dst_format, src, dst);
+                }
+                broadcast2(scalar, src_lanes.remainder(), dst_lanes.into_remainder());
+            });
+    });
+}
+
+/// Convert pixel data from one format to another in place.
+///
+/// Both formats must have the same pixel size, and neither can be YUYV. Like with [`convert`], this uses the chunk-wise kernels when
+/// it can.
+pub fn convert_inplace(src_format: PixelFormat, dst_format: PixelFormat, data: &mut [u8]) {
+    let size = src_format.pixel_size();
+    assert_eq!(size, dst_format.pixel_size(), "pixel sizes must match");
+    assert!(
+        src_format != PixelFormat::YUYV && dst_format != PixelFormat::YUYV,
+        "YUYV can't be converted in place"
+    );
+    if src_format == dst_format || src_format.is_anon() || dst_format.is_anon() {
+        return;
+    }
+    let scalar = ConvertBroadcast::new(src_format, dst_format);
+    let kernel = kernels::supported(src_format) && kernels::supported(dst_format);
+    broadcast_pool().install(|| {
+        data.par_chunks_mut(size * BLOCK).for_each(|block| {
+            // the kernels can't read and write the same slice, so each chunk is copied out first
+            let mut buf = [0; LANES * 4];
+            let mut lanes = block.chunks_exact_mut(size * LANES);
+            if kernel {
+                for lane in &mut lanes {
+                    let src = &mut buf[..lane.len()];
+                    src.copy_from_slice(lane);
+                    kernels::convert(src_format, dst_format, src, lane);
+                }
+            }
+            let rest = if kernel {
+                lanes.into_remainder()
+            } else {
+                block
+            };
+            for px in rest.chunks_exact_mut(size) {
+                let src = &mut buf[..size];
+                src.copy_from_slice(px);
+                scalar.par_run(src, px);
+            }
+        });
+    });
+}
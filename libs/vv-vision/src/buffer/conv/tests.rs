@@ -1,4 +1,5 @@
 use super::*;
+use crate::broadcast::broadcast2;
 
 fn assert_close<const N: usize>(left: [u8; N], right: [u8; N], msg: &'static str) {
     for (l, r) in left.into_iter().zip(right) {
@@ -28,3 +29,86 @@ fn orange_ycc2rgb() {
     let rgb = ycc2rgb(ycc);
     assert_close(rgb, [175, 153, 63], "RGB mismatch");
 }
+
+const FORMATS: [PixelFormat; 6] = [
+    PixelFormat::LUMA,
+    PixelFormat::RGB,
+    PixelFormat::HSV,
+    PixelFormat::YCC,
+    PixelFormat::RGBA,
+    PixelFormat::YUYV,
+];
+
+/// Get the size of the data for a number of pixels in a format.
+fn data_len(format: PixelFormat, pixels: usize) -> usize {
+    if format == PixelFormat::YUYV {
+        pixels * 2
+    } else {
+        pixels * format.pixel_size()
+    }
+}
+
+/// Get some arbitrary data, deterministically.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+/// Convert pixels with both the kernels and the per-pixel reference implementation.
+fn both(src_format: PixelFormat, dst_format: PixelFormat, src: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let pixels = src.len() / data_len(src_format, 1);
+    let mut fast = vec![0; data_len(dst_format, pixels)];
+    let mut slow = fast.clone();
+    convert(src_format, dst_format, src, &mut fast);
+    broadcast2(
+        ConvertBroadcast::new(src_format, dst_format),
+        src,
+        &mut slow,
+    );
+    (fast, slow)
+}
+
+#[test]
+fn kernels_match_scalar() {
+    // a few blocks, plus some pixels that don't fill a whole lane
+    let pixels = BLOCK * 3 + LANES + 6;
+    for src_format in FORMATS {
+        let src = noise(data_len(src_format, pixels));
+        for dst_format in FORMATS {
+            let (fast, slow) = both(src_format, dst_format, &src);
+            assert!(fast == slow, "{src_format} to {dst_format} doesn't match");
+        }
+    }
+}
+
+#[test]
+fn rgb2hsv_exhaustive() {
+    // the lookup tables are the riskiest part, so check every possible 8-bit color
+    let all = (0..1u32 << 24)
+        .flat_map(|c| [(c >> 16) as u8, (c >> 8) as u8, c as u8])
+        .collect::<Vec<_>>();
+    let (fast, slow) = both(PixelFormat::RGB, PixelFormat::HSV, &all);
+    assert!(fast == slow, "RGB to HSV doesn't match");
+}
+
+#[test]
+fn inplace_matches() {
+    let pixels = BLOCK + LANES * 2 + 5;
+    let src = noise(pixels * 3);
+    let three = [PixelFormat::RGB, PixelFormat::HSV, PixelFormat::YCC];
+    for src_format in three {
+        for dst_format in three {
+            let mut data = src.clone();
+            convert_inplace(src_format, dst_format, &mut data);
+            let (fast, _) = both(src_format, dst_format, &src);
+            assert!(data == fast, "{src_format} to {dst_format} doesn't match");
+        }
+    }
+}
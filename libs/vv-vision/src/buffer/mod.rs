@@ -1,4 +1,3 @@
-use crate::broadcast::*;
 use polonius_the_crab::{ForLt, Placeholder, PoloniusResult, polonius};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -14,6 +13,7 @@ use zune_png::zune_core::{colorspace::ColorSpace, options::DecoderOptions};
 use zune_png::{PngDecoder, error::PngDecodeErrors};
 
 pub mod conv;
+pub mod raw;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct UnrecognizedFourCC(pub [u8; 4]);
@@ -164,7 +164,7 @@ impl PixelFormat {
     }
     #[cfg(feature = "v4l")]
     pub const fn known_fourcc(fourcc: v4l::FourCC) -> bool {
-        matches!(&fourcc.repr, b"YUYV" | b"RGB8" | b"RGBA" | b"MJPG")
+        matches!(&fourcc.repr, b"MJPG") || raw::RawFormat::from_fourcc(fourcc).is_some()
     }
     /// Some bright color
     ///
@@ -293,13 +293,12 @@ impl Display for PixelFormat {
 impl TryFrom<v4l::FourCC> for PixelFormat {
     type Error = UnrecognizedFourCC;
     fn try_from(value: v4l::FourCC) -> Result<Self, Self::Error> {
-        match &value.repr {
-            b"YUYV" => Ok(Self::YUYV),
-            b"RGB8" => Ok(Self::RGB),
-            b"RGBA" => Ok(Self::RGBA),
-            b"MJPG" => Ok(Self::RGB), // we decode JPEG to RGB
-            &repr => Err(UnrecognizedFourCC(repr)),
+        if &value.repr == b"MJPG" {
+            return Ok(Self::RGB); // we decode JPEG to RGB
         }
+        raw::RawFormat::from_fourcc(value)
+            .map(raw::RawFormat::pixel_format)
+            .ok_or(UnrecognizedFourCC(value.repr))
     }
 }
 impl TryFrom<ColorSpace> for PixelFormat {
@@ -432,7 +431,7 @@ impl<'a> Buffer<'a> {
             data: Cow::Owned(data),
         })
     }
-    /// Decode data in the PNG format.
+    /// Decode data in the JPEG format.
     pub fn decode_jpeg_data(data: &[u8]) -> Result<Self, JpegDecodeErrors> {
         let _guard = info_span!("decoding JPEG image", data.len = data.len()).entered();
         let mut decoder = JpegDecoder::new_with_options(
@@ -443,13 +442,19 @@ impl<'a> Buffer<'a> {
             .decode_headers()
             .inspect_err(|err| error!(%err, "failed to decode JPEG headers"))?;
         let (width, height) = decoder.dimensions().unwrap();
+        // grayscale images are decoded as luma, even though we ask for RGB
+        let space = decoder.get_output_colorspace().unwrap_or(ColorSpace::RGB);
+        let Ok(format) = space.try_into() else {
+            error!(?space, "unsupported JPEG color space");
+            return Err(JpegDecodeErrors::FormatStatic("unsupported color space"));
+        };
         let data = decoder
             .decode()
             .inspect_err(|err| error!(%err, "failed to decode JPEG image"))?;
         Ok(Self {
             width: width as _,
             height: height as _,
-            format: PixelFormat::RGB,
+            format,
             data: Cow::Owned(data),
         })
     }
@@ -518,10 +523,9 @@ impl<'a> Buffer<'a> {
             data.copy_from_slice(&self.data);
             return;
         }
-        par_broadcast2(conv::ConvertBroadcast::new(self.format, of), self, data);
+        conv::convert(self.format, of, &self.data, data);
     }
     pub fn convert_inplace(&mut self, to: PixelFormat) {
-        use conv::*;
         if self.format == to {
             return;
         }
@@ -531,16 +535,10 @@ impl<'a> Buffer<'a> {
                 return;
             }
             match (old, to) {
-                (PixelFormat::RGB, PixelFormat::HSV) => par_broadcast1(to_inplace(rgb2hsv), self),
-                (PixelFormat::RGB, PixelFormat::YCC) => par_broadcast1(to_inplace(rgb2ycc), self),
-                (PixelFormat::HSV, PixelFormat::RGB) => par_broadcast1(to_inplace(hsv2rgb), self),
-                (PixelFormat::HSV, PixelFormat::YCC) => {
-                    par_broadcast1(to_inplace(compose(hsv2rgb, rgb2ycc)), self)
-                }
-                (PixelFormat::YCC, PixelFormat::RGB) => par_broadcast1(to_inplace(ycc2rgb), self),
-                (PixelFormat::YCC, PixelFormat::HSV) => {
-                    par_broadcast1(to_inplace(compose(ycc2rgb, rgb2hsv)), self)
-                }
+                (
+                    PixelFormat::RGB | PixelFormat::HSV | PixelFormat::YCC,
+                    PixelFormat::RGB | PixelFormat::HSV | PixelFormat::YCC,
+                ) => conv::convert_inplace(old, to, self.data.to_mut()),
                 _ => unreachable!("attempted to convert {} to {}", self.format, to),
             }
         } else {
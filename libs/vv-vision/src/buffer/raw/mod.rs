@@ -0,0 +1,183 @@
+//! Unpacking raw frame data from capture devices, including planar and 16-bit layouts that a [`Buffer`] can't hold directly.
+//!
+//! Each layout is converted to the closest interleaved [`PixelFormat`]: YUV layouts become [`YUYV`](PixelFormat::YUYV), grayscale
+//! becomes [`LUMA`](PixelFormat::LUMA), and BGR becomes [`RGB`](PixelFormat::RGB).
+
+use super::{Buffer, PixelFormat};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+/// The layout of a raw frame, as it comes from a capture device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RawFormat {
+    /// Packed 4:2:2 YUV, in `Y0 U Y1 V` order.
+    Yuyv,
+    /// Packed 4:2:2 YUV, in `U Y0 V Y1` order.
+    Uyvy,
+    /// Planar 4:2:0 YUV, with a Y plane followed by a plane of interleaved U and V samples.
+    Nv12,
+    /// Planar 4:2:0 YUV, with separate Y, U, and V planes. This is also known as I420.
+    Yu12,
+    /// 8-bit grayscale.
+    Grey,
+    /// 16-bit little-endian grayscale.
+    Y16,
+    /// Packed 8-bit RGB.
+    Rgb,
+    /// Packed 8-bit BGR.
+    Bgr,
+    /// Packed 8-bit RGBA.
+    Rgba,
+}
+impl RawFormat {
+    /// Get the format for a V4L FourCC, if it's supported.
+    ///
+    /// This doesn't include compressed formats like MJPG, which need to be decoded instead.
+    #[cfg(feature = "v4l")]
+    pub const fn from_fourcc(fourcc: v4l::FourCC) -> Option<Self> {
+        match &fourcc.repr {
+            b"YUYV" => Some(Self::Yuyv),
+            b"UYVY" => Some(Self::Uyvy),
+            b"NV12" => Some(Self::Nv12),
+            b"YU12" => Some(Self::Yu12),
+            b"GREY" => Some(Self::Grey),
+            b"Y16 " => Some(Self::Y16),
+            b"RGB3" | b"RGB8" => Some(Self::Rgb),
+            b"BGR3" => Some(Self::Bgr),
+            b"RGBA" => Some(Self::Rgba),
+            _ => None,
+        }
+    }
+    /// Get the pixel format that frames in this layout are unpacked to.
+    pub const fn pixel_format(self) -> PixelFormat {
+        match self {
+            Self::Yuyv | Self::Uyvy | Self::Nv12 | Self::Yu12 => PixelFormat::YUYV,
+            Self::Grey | Self::Y16 => PixelFormat::LUMA,
+            Self::Rgb | Self::Bgr => PixelFormat::RGB,
+            Self::Rgba => PixelFormat::RGBA,
+        }
+    }
+    /// Get the number of bytes in a row of the first plane, without any padding.
+    pub const fn min_stride(self, width: u32) -> usize {
+        let width = width as usize;
+        match self {
+            Self::Yu12 | Self::Grey => width,
+            Self::Nv12 => width.next_multiple_of(2),
+            Self::Yuyv | Self::Uyvy => width.next_multiple_of(2) * 2,
+            Self::Y16 => width * 2,
+            Self::Rgb | Self::Bgr => width * 3,
+            Self::Rgba => width * 4,
+        }
+    }
+    /// Get the number of bytes in a frame, given the stride of the first plane.
+    ///
+    /// For planar formats, the chroma planes' strides are derived from the first one, like V4L does.
+    pub const fn frame_size(self, height: u32, stride: usize) -> usize {
+        let height = height as usize;
+        let chroma_rows = height.div_ceil(2);
+        match self {
+            Self::Nv12 => stride * height + stride * chroma_rows,
+            Self::Yu12 => stride * height + stride.div_ceil(2) * chroma_rows * 2,
+            _ => stride * height,
+        }
+    }
+}
+
+/// An error from unpacking a frame that doesn't have enough data for its size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("expected at least {expected} bytes of frame data, found {found}")]
+pub struct FrameTooShort {
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl Buffer<'_> {
+    /// Unpack a raw frame into this buffer, converting it to [`RawFormat::pixel_format`].
+    ///
+    /// The `stride` is the number of bytes per row in the first plane, including padding. If it's zero, rows are assumed to be unpadded.
+    pub fn copy_from_raw(
+        &mut self,
+        raw: RawFormat,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        stride: usize,
+    ) -> Result<(), FrameTooShort> {
+        let stride = stride.max(raw.min_stride(width));
+        let expected = raw.frame_size(height, stride);
+        if data.len() < expected {
+            return Err(FrameTooShort {
+                expected,
+                found: data.len(),
+            });
+        }
+        self.width = width;
+        self.height = height;
+        self.format = raw.pixel_format();
+        let row_len = self.width as usize * self.format.pixel_size();
+        let out = self.resize_data();
+        if row_len == 0 {
+            return Ok(());
+        }
+        let rows = out.chunks_exact_mut(row_len).zip(data.chunks(stride));
+        match raw {
+            RawFormat::Yuyv | RawFormat::Grey | RawFormat::Rgb | RawFormat::Rgba => {
+                for (out, row) in rows {
+                    out.copy_from_slice(&row[..row_len]);
+                }
+            }
+            RawFormat::Uyvy => {
+                for (out, row) in rows {
+                    for (o, i) in out.chunks_exact_mut(2).zip(row.chunks_exact(2)) {
+                        o[0] = i[1];
+                        o[1] = i[0];
+                    }
+                }
+            }
+            RawFormat::Y16 => {
+                for (out, row) in rows {
+                    for (o, i) in out.iter_mut().zip(row.chunks_exact(2)) {
+                        *o = i[1];
+                    }
+                }
+            }
+            RawFormat::Bgr => {
+                for (out, row) in rows {
+                    for (o, i) in out.chunks_exact_mut(3).zip(row.chunks_exact(3)) {
+                        o.copy_from_slice(&[i[2], i[1], i[0]]);
+                    }
+                }
+            }
+            RawFormat::Nv12 => {
+                let (luma, chroma) = data.split_at(stride * height as usize);
+                for (y, out) in out.chunks_exact_mut(row_len).enumerate() {
+                    let luma = &luma[y * stride..];
+                    let chroma = &chroma[y / 2 * stride..];
+                    for (x, o) in out.chunks_exact_mut(2).enumerate() {
+                        // even pixels take U and odd pixels take V, which is already the order of the interleaved plane
+                        o[0] = luma[x];
+                        o[1] = chroma[x];
+                    }
+                }
+            }
+            RawFormat::Yu12 => {
+                let chroma_stride = stride.div_ceil(2);
+                let chroma_size = chroma_stride * (height as usize).div_ceil(2);
+                let (luma, chroma) = data.split_at(stride * height as usize);
+                let (u, v) = chroma.split_at(chroma_size);
+                for (y, out) in out.chunks_exact_mut(row_len).enumerate() {
+                    let luma = &luma[y * stride..];
+                    let u = &u[y / 2 * chroma_stride..];
+                    let v = &v[y / 2 * chroma_stride..];
+                    for (x, o) in out.chunks_exact_mut(2).enumerate() {
+                        o[0] = luma[x];
+                        o[1] = if x % 2 == 0 { u[x / 2] } else { v[x / 2] };
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
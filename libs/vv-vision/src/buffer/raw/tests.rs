@@ -0,0 +1,114 @@
+use super::*;
+
+/// A 4x2 frame with distinct values everywhere, as (Y, U, V) for each pixel, where each pair of pixels shares U and V.
+const LUMA: [[u8; 4]; 2] = [[10, 20, 30, 40], [50, 60, 70, 80]];
+const U: [[u8; 2]; 2] = [[100, 110], [120, 130]];
+const V: [[u8; 2]; 2] = [[200, 210], [220, 230]];
+
+/// The expected YUYV data for a frame, given which rows of chroma samples each row uses.
+fn expected_yuyv(chroma_rows: [usize; 2]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (y, c) in chroma_rows.into_iter().enumerate() {
+        for p in 0..2 {
+            out.extend([LUMA[y][p * 2], U[c][p], LUMA[y][p * 2 + 1], V[c][p]]);
+        }
+    }
+    out
+}
+
+fn unpack(raw: RawFormat, data: &[u8], width: u32, height: u32, stride: usize) -> Buffer<'static> {
+    let mut buffer = Buffer::empty_rgb();
+    buffer
+        .copy_from_raw(raw, data, width, height, stride)
+        .unwrap();
+    buffer.assert_sized_correctly();
+    buffer
+}
+
+#[test]
+fn packed_yuv() {
+    let yuyv = expected_yuyv([0, 1]);
+    let buffer = unpack(RawFormat::Yuyv, &yuyv, 4, 2, 0);
+    assert_eq!(buffer.format, PixelFormat::YUYV);
+    assert_eq!(*buffer.data, *yuyv);
+
+    let uyvy = yuyv
+        .chunks(2)
+        .flat_map(|p| [p[1], p[0]])
+        .collect::<Vec<_>>();
+    let buffer = unpack(RawFormat::Uyvy, &uyvy, 4, 2, 0);
+    assert_eq!(*buffer.data, *yuyv);
+}
+
+#[test]
+fn planar_yuv() {
+    // 4:2:0 only has one row of chroma for every two rows of luma
+    let luma = LUMA.concat();
+    let nv12 = [luma.clone(), vec![U[0][0], V[0][0], U[0][1], V[0][1]]].concat();
+    let buffer = unpack(RawFormat::Nv12, &nv12, 4, 2, 0);
+    assert_eq!(buffer.format, PixelFormat::YUYV);
+    assert_eq!(*buffer.data, *expected_yuyv([0, 0]));
+
+    let yu12 = [luma, U[0].to_vec(), V[0].to_vec()].concat();
+    let buffer = unpack(RawFormat::Yu12, &yu12, 4, 2, 0);
+    assert_eq!(*buffer.data, *expected_yuyv([0, 0]));
+}
+
+#[test]
+fn planar_stride() {
+    // each row is padded to 6 bytes, and the chroma planes are padded to half of that
+    let pad = |row: &[u8], len: usize| {
+        let mut row = row.to_vec();
+        row.resize(len, 0xee);
+        row
+    };
+    let luma = [pad(&LUMA[0], 6), pad(&LUMA[1], 6)].concat();
+    let nv12 = [luma.clone(), pad(&[U[0][0], V[0][0], U[0][1], V[0][1]], 6)].concat();
+    let buffer = unpack(RawFormat::Nv12, &nv12, 4, 2, 6);
+    assert_eq!(*buffer.data, *expected_yuyv([0, 0]));
+
+    let yu12 = [luma, pad(&U[0], 3), pad(&V[0], 3)].concat();
+    let buffer = unpack(RawFormat::Yu12, &yu12, 4, 2, 6);
+    assert_eq!(*buffer.data, *expected_yuyv([0, 0]));
+
+    // four rows, so there are two rows of chroma
+    let luma = [LUMA, LUMA].concat().concat();
+    let yu12 = [luma, U.concat(), V.concat()].concat();
+    let buffer = unpack(RawFormat::Yu12, &yu12, 4, 4, 0);
+    assert_eq!(
+        *buffer.data,
+        *[expected_yuyv([0, 0]), expected_yuyv([1, 1])].concat()
+    );
+}
+
+#[test]
+fn grayscale_and_rgb() {
+    let buffer = unpack(RawFormat::Grey, &[1, 2, 3, 0, 4, 5, 6, 0], 3, 2, 4);
+    assert_eq!(buffer.format, PixelFormat::LUMA);
+    assert_eq!(*buffer.data, [1, 2, 3, 4, 5, 6]);
+
+    let y16 = [0x34, 0x12, 0xff, 0xab, 0x00, 0x80];
+    let buffer = unpack(RawFormat::Y16, &y16, 3, 1, 0);
+    assert_eq!(buffer.format, PixelFormat::LUMA);
+    assert_eq!(*buffer.data, [0x12, 0xab, 0x80]);
+
+    let buffer = unpack(RawFormat::Bgr, &[1, 2, 3, 4, 5, 6], 2, 1, 0);
+    assert_eq!(buffer.format, PixelFormat::RGB);
+    assert_eq!(*buffer.data, [3, 2, 1, 6, 5, 4]);
+}
+
+#[test]
+fn too_short() {
+    let mut buffer = Buffer::empty_rgb();
+    let err = buffer
+        .copy_from_raw(RawFormat::Nv12, &[0; 8], 4, 2, 0)
+        .unwrap_err();
+    assert_eq!(
+        err,
+        FrameTooShort {
+            expected: 12,
+            found: 8
+        }
+    );
+    assert_eq!(buffer.format, PixelFormat::RGB);
+}
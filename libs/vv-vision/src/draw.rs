@@ -1,6 +1,6 @@
 use crate::buffer::{Buffer, PixelFormat};
 // use crate::pipeline::component::Data;
-use crate::vision::Blob;
+use crate::vision::{Blob, Circle, Contour, RotatedRect};
 // use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
@@ -252,6 +252,60 @@ impl Drawable for Blob {
         }
     }
 }
+impl Drawable for Contour {
+    fn draw(&self, color: &[u8], buffer: &mut Buffer) {
+        if let [[x, y]] = *self.points {
+            buffer.set_pixel(x, y, color);
+            return;
+        }
+        for ([x0, y0], [x1, y1]) in self.edges() {
+            Line { x0, y0, x1, y1 }.draw(color, buffer);
+        }
+    }
+}
+impl Drawable for RotatedRect {
+    fn draw(&self, color: &[u8], buffer: &mut Buffer) {
+        let corners = self.corners();
+        for i in 0..4 {
+            let [x0, y0] = corners[i].map(|c| c.round() as i32);
+            let [x1, y1] = corners[(i + 1) % 4].map(|c| c.round() as i32);
+            let Ok(it) = DrawLineIterator::new(x0, y0, x1, y1);
+            for (x, y) in it {
+                let (Ok(x), Ok(y)) = (x.try_into(), y.try_into()) else {
+                    continue;
+                };
+                buffer.set_pixel(x, y, color);
+            }
+        }
+    }
+}
+impl Drawable for Circle {
+    fn draw(&self, color: &[u8], buffer: &mut Buffer) {
+        // midpoint circle algorithm, drawing all eight octants at once
+        let [cx, cy] = self.center.map(|c| c.round() as i64);
+        let mut x = self.radius.round() as i64;
+        let mut y = 0;
+        let mut err = 1 - x;
+        while x >= y {
+            for (dx, dy) in [(x, y), (y, x)] {
+                for (sx, sy) in [(1, 1), (-1, 1), (1, -1), (-1, -1)] {
+                    let (Ok(px), Ok(py)) = ((cx + sx * dx).try_into(), (cy + sy * dy).try_into())
+                    else {
+                        continue;
+                    };
+                    buffer.set_pixel(px, py, color);
+                }
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+}
 impl<T: Drawable> Drawable for Vec<T> {
     fn draw(&self, color: &[u8], buffer: &mut Buffer) {
         for elem in self {
@@ -57,7 +57,53 @@ impl DoubleEndedIterator for PixelsIterator<'_> {
     }
 }
 
-/// A contiguous blob of color in an image—a bounding rectangle and number of contained pixels.
+/// The raw moments of the pixels in a [`Blob`], up to the second order.
+///
+/// These are sums over the integer coordinates of every pixel, which keeps them exact no matter how many rows get merged. The zeroth
+/// moment is the blob's pixel count, so it isn't repeated here. Use [`Blob::centroid`] and [`Blob::second_moments`] to get values
+/// that don't depend on the blob's size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Moments {
+    /// The sum of the x coordinates.
+    pub m10: u64,
+    /// The sum of the y coordinates.
+    pub m01: u64,
+    /// The sum of the squared x coordinates.
+    pub m20: u64,
+    /// The sum of the products of the x and y coordinates.
+    pub m11: u64,
+    /// The sum of the squared y coordinates.
+    pub m02: u64,
+}
+impl Moments {
+    /// Get the moments of the pixels in a row from `min_x` (inclusive) to `max_x` (exclusive).
+    pub(super) fn from_row(min_x: u32, max_x: u32, y: u32) -> Self {
+        // sums of 0..k and of their squares
+        let sum = |k: u64| k * k.saturating_sub(1) / 2;
+        let sum_sq = |k: u64| k * k.saturating_sub(1) * (2 * k).saturating_sub(1) / 6;
+        let (min_x, max_x, y) = (min_x as u64, max_x as u64, y as u64);
+        let n = max_x - min_x;
+        let sx = sum(max_x) - sum(min_x);
+        Self {
+            m10: sx,
+            m01: n * y,
+            m20: sum_sq(max_x) - sum_sq(min_x),
+            m11: sx * y,
+            m02: n * y * y,
+        }
+    }
+}
+impl std::ops::AddAssign for Moments {
+    fn add_assign(&mut self, rhs: Self) {
+        self.m10 += rhs.m10;
+        self.m01 += rhs.m01;
+        self.m20 += rhs.m20;
+        self.m11 += rhs.m11;
+        self.m02 += rhs.m02;
+    }
+}
+
+/// A contiguous blob of color in an image—a bounding rectangle, number of contained pixels, and the moments of those pixels.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Blob {
     pub min_x: u32,
@@ -65,15 +111,17 @@ pub struct Blob {
     pub min_y: u32,
     pub max_y: u32,
     pub pixels: usize,
+    pub moments: Moments,
 }
 impl Blob {
-    fn from_row(min_x: u32, max_x: u32, y: u32) -> Self {
+    pub(super) fn from_row(min_x: u32, max_x: u32, y: u32) -> Self {
         Self {
             min_x,
             max_x,
             min_y: y,
             max_y: y + 1,
             pixels: (max_x - min_x) as _,
+            moments: Moments::from_row(min_x, max_x, y),
         }
     }
     /// Get the width of this blob.
@@ -90,7 +138,7 @@ impl Blob {
     }
     /// Merge another blob into this one.
     ///
-    /// This creates a bounding box that covers both and adds their pixel counts.
+    /// This creates a bounding box that covers both and adds their pixel counts and moments.
     pub fn absorb(&mut self, other: Self) {
         if other.min_x < self.min_x {
             self.min_x = other.min_x
@@ -105,10 +153,66 @@ impl Blob {
             self.max_y = other.max_y
         }
         self.pixels += other.pixels;
+        self.moments += other.moments;
     }
     pub fn filled(&self) -> f64 {
         self.pixels as f64 / self.area() as f64
     }
+    /// Get the centroid of this blob's pixels.
+    ///
+    /// This is in the same coordinates as the bounding box, so a lone pixel at `(x, y)` has its centroid at `(x + 0.5, y + 0.5)`.
+    /// Unlike the center of the bounding box, it isn't pulled around by a few stray pixels or a partly hidden target.
+    pub fn centroid(&self) -> [f64; 2] {
+        let n = self.pixels as f64;
+        [
+            self.moments.m10 as f64 / n + 0.5,
+            self.moments.m01 as f64 / n + 0.5,
+        ]
+    }
+    /// Get the second central moments of this blob, divided by the pixel count.
+    ///
+    /// These are the variance in x, the covariance of x and y, and the variance in y, in that order (μ₂₀, μ₁₁, and μ₀₂ in the
+    /// usual notation). They're calculated exactly before being converted to floats.
+    pub fn second_moments(&self) -> [f64; 3] {
+        let n = self.pixels as i128;
+        let Moments {
+            m10,
+            m01,
+            m20,
+            m11,
+            m02,
+        } = self.moments;
+        let [m10, m01, m20, m11, m02] = [m10, m01, m20, m11, m02].map(i128::from);
+        let n2 = (n * n) as f64;
+        [
+            (n * m20 - m10 * m10) as f64 / n2,
+            (n * m11 - m10 * m01) as f64 / n2,
+            (n * m02 - m01 * m01) as f64 / n2,
+        ]
+    }
+    /// Get the angle of this blob's principal axis, clockwise from horizontal as the image appears, in degrees.
+    ///
+    /// This is always more than -90 and at most 90, the same as [`RotatedRect::angle`](super::RotatedRect::angle). Blobs without a
+    /// longer axis, like circles and squares, have an orientation of 0.
+    pub fn orientation(&self) -> f64 {
+        let [mu20, mu11, mu02] = self.second_moments();
+        (0.5 * (2.0 * mu11).atan2(mu20 - mu02)).to_degrees()
+    }
+    /// Get the eccentricity of the ellipse with the same second moments as this blob.
+    ///
+    /// This is 0 for blobs without a longer axis, and approaches 1 as the blob gets longer and thinner. Blobs that are a single pixel
+    /// wide have an eccentricity of 1, and a single pixel has an eccentricity of 0.
+    pub fn eccentricity(&self) -> f64 {
+        let [mu20, mu11, mu02] = self.second_moments();
+        let mean = (mu20 + mu02) / 2.0;
+        let diff = ((mu20 - mu02) / 2.0).hypot(mu11);
+        let (major, minor) = (mean + diff, (mean - diff).max(0.0));
+        if major <= 0.0 {
+            0.0
+        } else {
+            (1.0 - minor / major).sqrt()
+        }
+    }
 }
 
 /// State returned from [`BlobWithBottom::eat`]
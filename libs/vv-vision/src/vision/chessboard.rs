@@ -0,0 +1,328 @@
+use crate::buffer::{Buffer, PixelFormat};
+use std::collections::{HashMap, VecDeque};
+
+/// Standard deviation of the blur used before looking for corners.
+const CORNER_SIGMA: f32 = 1.5;
+/// Radius of the circle sampled to check that a corner looks like a chessboard corner.
+const RING_RADIUS: f32 = 5.0;
+const RING_SAMPLES: usize = 32;
+
+/// Find the inner corners of a chessboard in an image.
+///
+/// `cols` and `rows` are the number of *inner* corners in each direction, so a standard 8x8 board has 7x7 inner corners.
+/// Corners are returned with subpixel precision in row-major order, or `None` if the full board couldn't be found.
+/// The board's orientation is ambiguous, so the first corner could be at any of the board's corners.
+pub fn find_chessboard_corners(img: Buffer<'_>, cols: usize, rows: usize) -> Option<Vec<[f64; 2]>> {
+    if cols < 2 || rows < 2 {
+        return None;
+    }
+    let luma = img.convert_cow(PixelFormat::LUMA);
+    let width = luma.width as usize;
+    let height = luma.height as usize;
+    if width < 16 || height < 16 {
+        return None;
+    }
+    let raw = luma.data.iter().map(|&b| b as f32).collect::<Vec<_>>();
+    let blurred = blur(&raw, width, height, CORNER_SIGMA);
+    let mut candidates = find_candidates(&blurred, width, height, cols * rows);
+    if candidates.len() < cols * rows {
+        return None;
+    }
+    for c in &mut candidates {
+        *c = refine_corner(&raw, width, height, *c);
+    }
+    for seed in 0..candidates.len().min(16) {
+        if let Some(grid) = grow_grid(&candidates, seed)
+            && let Some(corners) = order_grid(&grid, &candidates, cols, rows)
+        {
+            return Some(corners);
+        }
+    }
+    None
+}
+
+/// Separable gaussian blur on a floating-point image.
+fn blur(src: &[f32], width: usize, height: usize, sigma: f32) -> Vec<f32> {
+    let radius = (sigma * 3.0).ceil() as isize;
+    let kernel = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let sum = kernel.iter().sum::<f32>();
+    let kernel = kernel.iter().map(|k| k / sum).collect::<Vec<_>>();
+    let mut tmp = vec![0.0; src.len()];
+    let mut out = vec![0.0; src.len()];
+    for y in 0..height {
+        for x in 0..width {
+            tmp[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, w)| {
+                    let sx = (x as isize + k as isize - radius).clamp(0, width as isize - 1);
+                    src[y * width + sx as usize] * w
+                })
+                .sum();
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            out[y * width + x] = kernel
+                .iter()
+                .enumerate()
+                .map(|(k, w)| {
+                    let sy = (y as isize + k as isize - radius).clamp(0, height as isize - 1);
+                    tmp[sy as usize * width + x] * w
+                })
+                .sum();
+        }
+    }
+    out
+}
+
+/// Sample an image with bilinear interpolation.
+fn sample(img: &[f32], width: usize, height: usize, x: f32, y: f32) -> f32 {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let x0 = (x as usize).min(width - 2);
+    let y0 = (y as usize).min(height - 2);
+    let fx = x - x0 as f32;
+    let fy = y - y0 as f32;
+    let at = |x: usize, y: usize| img[y * width + x];
+    let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1, y0) * fx;
+    let bottom = at(x0, y0 + 1) * (1.0 - fx) + at(x0 + 1, y0 + 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+/// Find saddle points that look like chessboard corners, sorted by strength.
+fn find_candidates(img: &[f32], width: usize, height: usize, expected: usize) -> Vec<[f64; 2]> {
+    let at = |x: usize, y: usize| img[y * width + x];
+    let mut response = vec![0.0f32; img.len()];
+    let margin = RING_RADIUS.ceil() as usize + 1;
+    for y in margin..(height - margin) {
+        for x in margin..(width - margin) {
+            let ixx = at(x + 1, y) - 2.0 * at(x, y) + at(x - 1, y);
+            let iyy = at(x, y + 1) - 2.0 * at(x, y) + at(x, y - 1);
+            let ixy =
+                (at(x + 1, y + 1) - at(x + 1, y - 1) - at(x - 1, y + 1) + at(x - 1, y - 1)) * 0.25;
+            response[y * width + x] = (ixy * ixy - ixx * iyy).max(0.0);
+        }
+    }
+    let max = response.iter().copied().fold(0.0, f32::max);
+    if max <= 0.0 {
+        return Vec::new();
+    }
+    let threshold = max * 0.02;
+    let nms = 3;
+    let mut found = Vec::new();
+    for y in margin..(height - margin) {
+        for x in margin..(width - margin) {
+            let r = response[y * width + x];
+            if r < threshold {
+                continue;
+            }
+            let is_max = (y.saturating_sub(nms)..=(y + nms).min(height - 1)).all(|ny| {
+                (x.saturating_sub(nms)..=(x + nms).min(width - 1)).all(|nx| {
+                    let other = response[ny * width + nx];
+                    other < r || (other == r && (ny, nx) >= (y, x))
+                })
+            });
+            if !is_max || !is_x_corner(img, width, height, x as f32, y as f32) {
+                continue;
+            }
+            found.push((r, [x as f64, y as f64]));
+        }
+    }
+    found.sort_by(|a, b| b.0.total_cmp(&a.0));
+    // leave room for some false positives, but not so many that grid growing gets expensive
+    found.truncate(expected * 4);
+    found.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Refine a corner to subpixel precision.
+///
+/// At the true corner, every nearby gradient is perpendicular to the vector from the corner to where it was sampled,
+/// so this finds the point that minimizes the (weighted) sum of their dot products.
+fn refine_corner(img: &[f32], width: usize, height: usize, start: [f64; 2]) -> [f64; 2] {
+    let radius = RING_RADIUS as isize;
+    let at = |x: isize, y: isize| img[y as usize * width + x as usize] as f64;
+    let mut q = start;
+    for _ in 0..10 {
+        let cx = q[0].round() as isize;
+        let cy = q[1].round() as isize;
+        if cx - radius < 1
+            || cy - radius < 1
+            || cx + radius + 1 >= width as isize
+            || cy + radius + 1 >= height as isize
+        {
+            return start;
+        }
+        let (mut a00, mut a01, mut a11, mut b0, mut b1) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for y in (cy - radius)..=(cy + radius) {
+            for x in (cx - radius)..=(cx + radius) {
+                let gx = (at(x + 1, y) - at(x - 1, y)) * 0.5;
+                let gy = (at(x, y + 1) - at(x, y - 1)) * 0.5;
+                let dx = (x - cx) as f64;
+                let dy = (y - cy) as f64;
+                let w = (-(dx * dx + dy * dy) / (radius * radius) as f64).exp();
+                let (gxx, gxy, gyy) = (gx * gx * w, gx * gy * w, gy * gy * w);
+                a00 += gxx;
+                a01 += gxy;
+                a11 += gyy;
+                b0 += gxx * x as f64 + gxy * y as f64;
+                b1 += gxy * x as f64 + gyy * y as f64;
+            }
+        }
+        let det = a00 * a11 - a01 * a01;
+        if det.abs() < 1e-9 {
+            return start;
+        }
+        let next = [(a11 * b0 - a01 * b1) / det, (a00 * b1 - a01 * b0) / det];
+        let moved = norm(sub(next, q));
+        q = next;
+        if norm(sub(q, start)) > radius as f64 {
+            return start;
+        }
+        if moved < 0.005 {
+            break;
+        }
+    }
+    q
+}
+
+/// Check that the intensity around a point alternates between light and dark four times, like at the corner of two squares.
+fn is_x_corner(img: &[f32], width: usize, height: usize, x: f32, y: f32) -> bool {
+    let ring = (0..RING_SAMPLES)
+        .map(|i| {
+            let angle = i as f32 * std::f32::consts::TAU / RING_SAMPLES as f32;
+            let (sin, cos) = angle.sin_cos();
+            sample(
+                img,
+                width,
+                height,
+                x + cos * RING_RADIUS,
+                y + sin * RING_RADIUS,
+            )
+        })
+        .collect::<Vec<_>>();
+    let min = ring.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = ring.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    if max - min < 20.0 {
+        return false;
+    }
+    let mid = (min + max) * 0.5;
+    let transitions = (0..RING_SAMPLES)
+        .filter(|&i| (ring[i] > mid) != (ring[(i + 1) % RING_SAMPLES] > mid))
+        .count();
+    transitions == 4
+}
+
+fn sub(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+fn norm(a: [f64; 2]) -> f64 {
+    a[0].hypot(a[1])
+}
+
+/// Find the closest candidate to a point, other than the excluded one.
+fn nearest(candidates: &[[f64; 2]], point: [f64; 2], exclude: usize) -> Option<(usize, f64)> {
+    candidates
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != exclude)
+        .map(|(i, &c)| (i, norm(sub(c, point))))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+/// Grow a grid out from a seed corner, returning the grid coordinates of each corner found.
+fn grow_grid(candidates: &[[f64; 2]], seed: usize) -> Option<HashMap<(i32, i32), usize>> {
+    let origin = candidates[seed];
+    let (first, _) = nearest(candidates, origin, seed)?;
+    let u = sub(candidates[first], origin);
+    let u_len = norm(u);
+    // the second axis is the nearest corner in a direction that's far from the first one
+    let (_, v) = candidates
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i != seed && i != first)
+        .map(|(_, &c)| sub(c, origin))
+        .filter(|&d| {
+            let cos = (d[0] * u[0] + d[1] * u[1]) / (norm(d) * u_len);
+            cos.abs() < std::f64::consts::FRAC_1_SQRT_2
+        })
+        .map(|d| (norm(d), d))
+        .min_by(|a, b| a.0.total_cmp(&b.0))?;
+
+    let mut grid = HashMap::new();
+    let mut used = vec![false; candidates.len()];
+    grid.insert((0, 0), seed);
+    used[seed] = true;
+    let mut queue = VecDeque::from([((0, 0), u, v)]);
+    while let Some(((i, j), u, v)) = queue.pop_front() {
+        let here = candidates[grid[&(i, j)]];
+        for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let key = (i + di, j + dj);
+            if grid.contains_key(&key) {
+                continue;
+            }
+            let step = [
+                u[0] * di as f64 + v[0] * dj as f64,
+                u[1] * di as f64 + v[1] * dj as f64,
+            ];
+            let predicted = [here[0] + step[0], here[1] + step[1]];
+            let tolerance = norm(u).min(norm(v)) * 0.35;
+            let Some((idx, dist)) = nearest(candidates, predicted, usize::MAX) else {
+                continue;
+            };
+            if dist > tolerance || used[idx] {
+                continue;
+            }
+            used[idx] = true;
+            grid.insert(key, idx);
+            // update the basis with the actual step to follow perspective distortion
+            let actual = sub(candidates[idx], here);
+            let (nu, nv) = if di != 0 {
+                (actual.map(|x| x * di as f64), v)
+            } else {
+                (u, actual.map(|x| x * dj as f64))
+            };
+            queue.push_back((key, nu, nv));
+        }
+    }
+    Some(grid)
+}
+
+/// Check that a grid has the right shape, and put its corners in row-major order.
+fn order_grid(
+    grid: &HashMap<(i32, i32), usize>,
+    candidates: &[[f64; 2]],
+    cols: usize,
+    rows: usize,
+) -> Option<Vec<[f64; 2]>> {
+    if grid.len() != cols * rows {
+        return None;
+    }
+    let min_i = grid.keys().map(|k| k.0).min()?;
+    let max_i = grid.keys().map(|k| k.0).max()?;
+    let min_j = grid.keys().map(|k| k.1).min()?;
+    let max_j = grid.keys().map(|k| k.1).max()?;
+    let w = (max_i - min_i + 1) as usize;
+    let h = (max_j - min_j + 1) as usize;
+    let transpose = if (w, h) == (cols, rows) {
+        false
+    } else if (w, h) == (rows, cols) {
+        true
+    } else {
+        return None;
+    };
+    let mut out = Vec::with_capacity(cols * rows);
+    for row in 0..rows as i32 {
+        for col in 0..cols as i32 {
+            let key = if transpose {
+                (min_i + row, min_j + col)
+            } else {
+                (min_i + col, min_j + row)
+            };
+            out.push(candidates[*grid.get(&key)?]);
+        }
+    }
+    Some(out)
+}
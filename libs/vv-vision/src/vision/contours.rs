@@ -0,0 +1,275 @@
+use super::*;
+use crate::buffer::Buffer;
+
+/// Offsets to the neighbors of a pixel, counterclockwise (as the image appears, with y pointing down) starting from the right.
+const NEIGHBORS: [[i64; 2]; 8] = [
+    [1, 0],
+    [1, -1],
+    [0, -1],
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+];
+/// The index of the left neighbor in [`NEIGHBORS`].
+const LEFT: usize = 4;
+
+/// The state of each pixel while contours are being found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Px {
+    Background,
+    Foreground,
+    /// A foreground pixel whose region has already been found.
+    Visited,
+}
+
+/// A binary image to trace contours in.
+struct Mask {
+    pixels: Vec<Px>,
+    width: usize,
+    height: usize,
+}
+impl Mask {
+    fn from_buffer(buffer: &Buffer<'_>) -> Self {
+        let pixels = buffer
+            .data
+            .chunks(buffer.format.pixel_size())
+            .map(|px| {
+                if px.iter().any(|&v| v > 0) {
+                    Px::Foreground
+                } else {
+                    Px::Background
+                }
+            })
+            .collect();
+        Self {
+            pixels,
+            width: buffer.width as usize,
+            height: buffer.height as usize,
+        }
+    }
+    /// Get the neighbor of a pixel in a direction, if it's in the foreground.
+    fn neighbor(&self, [x, y]: [u32; 2], dir: usize) -> Option<[u32; 2]> {
+        let [dx, dy] = NEIGHBORS[dir];
+        let x = x as i64 + dx;
+        let y = y as i64 + dy;
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        let idx = y as usize * self.width + x as usize;
+        (self.pixels[idx] != Px::Background).then_some([x as u32, y as u32])
+    }
+    /// Follow the outer border of a region, starting at its first pixel in raster order.
+    ///
+    /// This is the border following from Suzuki and Abe's algorithm, restricted to outer borders. Since the starting pixel is the
+    /// first in its region, everything above it and to its left is in the background.
+    fn trace(&self, start: [u32; 2]) -> Vec<[u32; 2]> {
+        let mut points = vec![start];
+        let Some(second) = (0..8).find_map(|i| self.neighbor(start, (LEFT + 8 - i) % 8)) else {
+            return points;
+        };
+        let mut prev = second;
+        let mut cur = start;
+        loop {
+            let back = direction(cur, prev);
+            let next = (1..=8)
+                .find_map(|i| self.neighbor(cur, (back + i) % 8))
+                .expect("the previous pixel is always a neighbor");
+            if cur == second && next == start {
+                return points;
+            }
+            prev = cur;
+            cur = next;
+            points.push(cur);
+        }
+    }
+    /// Mark every pixel connected to a starting pixel as visited, and get the blob that covers them.
+    fn fill(&mut self, start: [u32; 2], stack: &mut Vec<[u32; 2]>) -> Blob {
+        let mut blob = Blob::from_row(start[0], start[0] + 1, start[1]);
+        self.pixels[start[1] as usize * self.width + start[0] as usize] = Px::Visited;
+        stack.push(start);
+        while let Some(px) = stack.pop() {
+            for dir in 0..8 {
+                let Some(n @ [x, y]) = self.neighbor(px, dir) else {
+                    continue;
+                };
+                let state = &mut self.pixels[y as usize * self.width + x as usize];
+                if *state == Px::Foreground {
+                    *state = Px::Visited;
+                    blob.absorb(Blob::from_row(x, x + 1, y));
+                    stack.push(n);
+                }
+            }
+        }
+        blob
+    }
+}
+
+/// Get the direction from one pixel to a neighboring one.
+fn direction(from: [u32; 2], to: [u32; 2]) -> usize {
+    let offset = [to[0] as i64 - from[0] as i64, to[1] as i64 - from[1] as i64];
+    NEIGHBORS
+        .iter()
+        .position(|&n| n == offset)
+        .expect("pixels should be neighbors")
+}
+
+/// Get the distance from a point to a line segment.
+fn segment_distance(p: [u32; 2], a: [u32; 2], b: [u32; 2]) -> f64 {
+    let [px, py] = p.map(f64::from);
+    let [ax, ay] = a.map(f64::from);
+    let [bx, by] = b.map(f64::from);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (((px - ax) * dx + (py - ay) * dy) / len2).clamp(0.0, 1.0)
+    };
+    (px - ax - t * dx).hypot(py - ay - t * dy)
+}
+
+/// Simplify an open polyline with the Douglas–Peucker algorithm.
+///
+/// Points are removed as long as every removed point is within `epsilon` pixels of the simplified line. The first and last points
+/// are always kept.
+pub fn simplify_polyline(points: &[[u32; 2]], epsilon: f64) -> Vec<[u32; 2]> {
+    let Some(last) = points.len().checked_sub(1).filter(|&l| l >= 2) else {
+        return points.to_vec();
+    };
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[last] = true;
+    let mut stack = vec![(0, last)];
+    while let Some((start, end)) = stack.pop() {
+        let (idx, dist) = (start + 1..end)
+            .map(|i| (i, segment_distance(points[i], points[start], points[end])))
+            .fold(
+                (start, -1.0),
+                |max, cur| if cur.1 > max.1 { cur } else { max },
+            );
+        if dist > epsilon {
+            keep[idx] = true;
+            if idx - start > 1 {
+                stack.push((start, idx));
+            }
+            if end - idx > 1 {
+                stack.push((idx, end));
+            }
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter_map(|(&p, keep)| keep.then_some(p))
+        .collect()
+}
+
+/// The outer boundary of a connected region in an image.
+///
+/// Like with [`BlobsIterator`](super::BlobsIterator), regions are 8-connected groups of pixels that aren't 0 on every channel. Holes
+/// in a region don't get their own contours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    /// The pixels along the boundary, counterclockwise as the image appears, starting with the top-left one.
+    ///
+    /// The boundary is closed, so the last point connects back to the first. Pixels on parts of the region that are only one pixel
+    /// wide show up twice, once for each side.
+    pub points: Vec<[u32; 2]>,
+    /// The bounding box and pixel count of the region.
+    pub blob: Blob,
+}
+impl Contour {
+    /// Get the edges of the boundary, as pairs of consecutive points.
+    pub fn edges(&self) -> impl Iterator<Item = ([u32; 2], [u32; 2])> + '_ {
+        let n = self.points.len();
+        (0..n).map(move |i| (self.points[i], self.points[(i + 1) % n]))
+    }
+    /// Get the length of the boundary.
+    pub fn perimeter(&self) -> f64 {
+        polygon_perimeter(&self.points)
+    }
+    /// Get the area enclosed by the boundary.
+    ///
+    /// This is the area of the polygon through the centers of the boundary pixels, so it's less than the pixel count.
+    pub fn area(&self) -> f64 {
+        polygon_area(&self.points)
+    }
+    /// Get the convex hull of the boundary.
+    pub fn hull(&self) -> Vec<[u32; 2]> {
+        convex_hull(&self.points)
+    }
+    /// Get the smallest rotated rectangle that contains the boundary.
+    pub fn min_area_rect(&self) -> RotatedRect {
+        min_area_rect(&self.hull())
+    }
+    /// Get the smallest circle that contains the boundary.
+    pub fn enclosing_circle(&self) -> Circle {
+        min_enclosing_circle(&self.hull())
+    }
+    /// Get the ratio of the area to the area of the convex hull.
+    ///
+    /// This is 1 for convex shapes, and lower for shapes with dents or holes in their outline. Shapes with no area, like lines, count
+    /// as convex.
+    pub fn solidity(&self) -> f64 {
+        fill_ratio(self.area(), polygon_area(&self.hull()))
+    }
+    /// Get the ratio of the perimeter of the convex hull to the perimeter.
+    ///
+    /// This is 1 for convex shapes, and lower for shapes with jagged outlines.
+    pub fn convexity(&self) -> f64 {
+        fill_ratio(polygon_perimeter(&self.hull()), self.perimeter())
+    }
+    /// Simplify the boundary into a polygon with the Douglas–Peucker algorithm.
+    ///
+    /// Every point on the original boundary is within `epsilon` pixels of the simplified one. A rectangle simplifies to its four
+    /// corners with an `epsilon` of a pixel or two, even if it's rotated.
+    pub fn simplify(&self, epsilon: f64) -> Self {
+        let points = &self.points;
+        if points.len() <= 2 {
+            return self.clone();
+        }
+        // split the boundary at the point farthest from the start, and simplify both halves as open polylines
+        let [x0, y0] = points[0];
+        let (split, _) = points.iter().enumerate().fold((0, 0), |max, (i, &[x, y])| {
+            let dist = x.abs_diff(x0) as u64 * x.abs_diff(x0) as u64
+                + y.abs_diff(y0) as u64 * y.abs_diff(y0) as u64;
+            if dist > max.1 { (i, dist) } else { max }
+        });
+        if split == 0 {
+            return Self {
+                points: vec![points[0]],
+                blob: self.blob,
+            };
+        }
+        let mut simplified = simplify_polyline(&points[..=split], epsilon);
+        let mut back = points[split..].to_vec();
+        back.push(points[0]);
+        let back = simplify_polyline(&back, epsilon);
+        simplified.extend_from_slice(&back[1..back.len() - 1]);
+        Self {
+            points: simplified,
+            blob: self.blob,
+        }
+    }
+}
+
+/// Find the contours of every region in an image.
+///
+/// Contours are returned in the order that the top-left pixels of their regions appear in the image.
+pub fn find_contours(buffer: &Buffer<'_>) -> Vec<Contour> {
+    let mut mask = Mask::from_buffer(buffer);
+    let mut contours = Vec::new();
+    let mut stack = Vec::new();
+    for idx in 0..mask.pixels.len() {
+        if mask.pixels[idx] != Px::Foreground {
+            continue;
+        }
+        let start = [(idx % mask.width) as u32, (idx / mask.width) as u32];
+        let points = mask.trace(start);
+        let blob = mask.fill(start, &mut stack);
+        contours.push(Contour { points, blob });
+    }
+    contours
+}
@@ -0,0 +1,255 @@
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which kernel to compute image gradients with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum GradientKernel {
+    /// The 3x3 Sobel operator, which smooths across the gradient with weights of 1, 2, and 1.
+    #[default]
+    Sobel,
+    /// The 3x3 Scharr operator, which smooths with weights of 3, 10, and 3.
+    ///
+    /// This is about as fast as the Sobel operator, but its gradient directions are more accurate, especially along diagonals.
+    Scharr,
+}
+impl GradientKernel {
+    /// Get the weights of the side and center rows (or columns) of the kernel.
+    const fn weights(self) -> [i16; 2] {
+        match self {
+            Self::Sobel => [1, 2],
+            Self::Scharr => [3, 10],
+        }
+    }
+    /// Get the sum of the weights, which is how much a difference of one between neighboring pixels changes the gradient.
+    pub const fn scale(self) -> i16 {
+        let [side, center] = self.weights();
+        side * 2 + center
+    }
+}
+
+/// The horizontal and vertical derivatives of a grayscale image.
+///
+/// The derivatives aren't normalized, so they're [`kernel.scale()`](GradientKernel::scale) times the difference between the pixels on
+/// either side of each pixel. Pixels past the edges of the image are treated as copies of the ones on the edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gradients {
+    pub width: u32,
+    pub height: u32,
+    pub kernel: GradientKernel,
+    /// The derivatives along the x-axis, in row-major order.
+    pub dx: Vec<i16>,
+    /// The derivatives along the y-axis, in row-major order.
+    pub dy: Vec<i16>,
+}
+impl Gradients {
+    /// Compute the gradients of an image.
+    ///
+    /// Images that aren't already [`LUMA`](PixelFormat::LUMA) are converted to it first.
+    pub fn new(src: Buffer<'_>, kernel: GradientKernel) -> Self {
+        let src = src.convert_cow(PixelFormat::LUMA);
+        let width = src.width as usize;
+        let height = src.height as usize;
+        let len = width * height;
+        let mut dx = vec![0; len];
+        let mut dy = vec![0; len];
+        if len > 0 {
+            let [side, center] = kernel.weights();
+            let row = |y: usize| &src.data[(y * width)..((y + 1) * width)];
+            broadcast_pool().install(|| {
+                dx.par_chunks_mut(width)
+                    .zip(dy.par_chunks_mut(width))
+                    .enumerate()
+                    .for_each(|(y, (dx, dy))| {
+                        let above = row(y.saturating_sub(1));
+                        let here = row(y);
+                        let below = row((y + 1).min(height - 1));
+                        for x in 0..width {
+                            let left = x.saturating_sub(1);
+                            let right = (x + 1).min(width - 1);
+                            let px = |row: &[u8], x: usize| row[x] as i16;
+                            dx[x] = side * (px(above, right) - px(above, left))
+                                + center * (px(here, right) - px(here, left))
+                                + side * (px(below, right) - px(below, left));
+                            dy[x] = side * (px(below, left) - px(above, left))
+                                + center * (px(below, x) - px(above, x))
+                                + side * (px(below, right) - px(above, right));
+                        }
+                    });
+            });
+        }
+        Self {
+            width: src.width,
+            height: src.height,
+            kernel,
+            dx,
+            dy,
+        }
+    }
+
+    /// Get the squared, unnormalized magnitude of the gradient at an index.
+    #[inline(always)]
+    fn magnitude_squared(&self, idx: usize) -> u32 {
+        let dx = self.dx[idx] as i32;
+        let dy = self.dy[idx] as i32;
+        (dx * dx + dy * dy) as u32
+    }
+
+    /// Get the magnitude of the gradient at a pixel.
+    ///
+    /// This is normalized by the kernel's scale, so an edge going from 0 to 255 has a magnitude of 255.
+    pub fn magnitude(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = y as usize * self.width as usize + x as usize;
+        Some((self.magnitude_squared(idx) as f32).sqrt() / self.kernel.scale() as f32)
+    }
+
+    /// Get the direction of the gradient at a pixel, in radians counterclockwise from the x-axis.
+    ///
+    /// Since the y-axis points down in images, this is flipped from how it looks.
+    pub fn direction(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = y as usize * self.width as usize + x as usize;
+        Some((self.dy[idx] as f32).atan2(self.dx[idx] as f32))
+    }
+}
+
+/// Compute the gradient magnitude of an image.
+///
+/// The output is a [`LUMA`](PixelFormat::LUMA) image with the same dimensions as the source, with each pixel being the
+/// [magnitude](Gradients::magnitude) of the gradient there, clamped to 255.
+pub fn sobel(src: Buffer<'_>, dst: &mut Buffer<'_>, kernel: GradientKernel) {
+    let grads = Gradients::new(src, kernel);
+    dst.width = grads.width;
+    dst.height = grads.height;
+    dst.format = PixelFormat::LUMA;
+    let scale = kernel.scale() as f32;
+    broadcast_pool().install(|| {
+        dst.resize_data()
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(idx, px)| {
+                *px = ((grads.magnitude_squared(idx) as f32).sqrt() / scale)
+                    .round()
+                    .min(255.0) as u8;
+            });
+    });
+}
+
+/// tan(22.5°) in 16.16 fixed point, for sorting gradients into directions.
+const TAN_22_5: i64 = 27146;
+/// tan(67.5°) in 16.16 fixed point.
+const TAN_67_5: i64 = 158218;
+
+/// Pixels that are certainly edges.
+const STRONG: u8 = 2;
+/// Pixels that are only edges if they're connected to a strong edge.
+const WEAK: u8 = 1;
+
+/// Detect edges in an image with the Canny edge detector.
+///
+/// The thresholds are on the same scale as [`Gradients::magnitude`]. Pixels with a gradient magnitude of at least `high` are always
+/// edges, and ones with a magnitude of at least `low` are only edges if they're connected to one of those. This doesn't blur the
+/// image first, so noisy images should be blurred with [`gaussian_blur`](super::gaussian_blur) beforehand.
+///
+/// The output is a [`LUMA`](PixelFormat::LUMA) image with the same dimensions as the source, with edges one pixel wide that have a
+/// value of 255, and 0 everywhere else.
+pub fn canny(src: Buffer<'_>, dst: &mut Buffer<'_>, low: f32, high: f32, kernel: GradientKernel) {
+    canny_gradients(&Gradients::new(src, kernel), dst, low, high);
+}
+
+/// Detect edges from gradients that were already computed, like [`canny`].
+pub(crate) fn canny_gradients(grads: &Gradients, dst: &mut Buffer<'_>, low: f32, high: f32) {
+    assert!(
+        low <= high,
+        "Low threshold {low} is greater than high threshold {high}"
+    );
+    let width = grads.width as usize;
+    let height = grads.height as usize;
+    dst.width = grads.width;
+    dst.height = grads.height;
+    dst.format = PixelFormat::LUMA;
+    let data = dst.resize_data();
+    if data.is_empty() {
+        return;
+    }
+    // compare squared magnitudes to avoid taking square roots
+    let threshold = |t: f32| {
+        let t = t.max(0.0) as f64 * grads.kernel.scale() as f64;
+        t * t
+    };
+    let (low, high) = (threshold(low), threshold(high));
+    let mags = broadcast_pool().install(|| {
+        (0..data.len())
+            .into_par_iter()
+            .map(|idx| grads.magnitude_squared(idx))
+            .collect::<Vec<_>>()
+    });
+    let mag = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            0
+        } else {
+            mags[y as usize * width + x as usize]
+        }
+    };
+    // non-maximum suppression, which thins edges by only keeping pixels with a larger magnitude than their neighbors along the gradient
+    broadcast_pool().install(|| {
+        data.par_chunks_mut(width).enumerate().for_each(|(y, row)| {
+            for (x, px) in row.iter_mut().enumerate() {
+                let idx = y * width + x;
+                let m = mags[idx];
+                *px = 0;
+                if (m as f64) < low || m == 0 {
+                    continue;
+                }
+                let dx = grads.dx[idx] as i64;
+                let dy = grads.dy[idx] as i64;
+                let ay = dy.abs() << 16;
+                let (ox, oy) = if ay < dx.abs() * TAN_22_5 {
+                    (1, 0)
+                } else if ay > dx.abs() * TAN_67_5 {
+                    (0, 1)
+                } else if (dx < 0) == (dy < 0) {
+                    (1, 1)
+                } else {
+                    (1, -1)
+                };
+                let (x, y) = (x as isize, y as isize);
+                // the strict comparison on one side keeps plateaus from being two pixels wide
+                if m > mag(x - ox, y - oy) && m >= mag(x + ox, y + oy) {
+                    *px = if m as f64 >= high { STRONG } else { WEAK };
+                }
+            }
+        });
+    });
+    // hysteresis, which keeps weak edges that are connected to strong ones
+    let mut stack = data
+        .iter()
+        .enumerate()
+        .filter(|&(_, &px)| px == STRONG)
+        .map(|(idx, _)| idx)
+        .collect::<Vec<_>>();
+    while let Some(idx) = stack.pop() {
+        let (x, y) = (idx % width, idx / width);
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                let n = ny * width + nx;
+                if data[n] == WEAK {
+                    data[n] = STRONG;
+                    stack.push(n);
+                }
+            }
+        }
+    }
+    for px in data.iter_mut() {
+        *px = if *px == STRONG { 255 } else { 0 };
+    }
+}
@@ -0,0 +1,386 @@
+use super::edges::canny_gradients;
+use super::{Circle, GradientKernel, Gradients};
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use crate::draw::Line;
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+use std::f64::consts::{PI, TAU};
+
+/// Parameters for [`hough_lines`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct HoughLineParams {
+    /// The minimum number of edge pixels that have to lie on a line for it to be detected.
+    pub threshold: u32,
+    /// The distance resolution of the accumulator, in pixels.
+    pub rho: f64,
+    /// The angle resolution of the accumulator, in degrees.
+    pub theta: f64,
+    /// The largest gap between edge pixels within one segment, in pixels.
+    ///
+    /// If this is set, each line is split into segments of edge pixels along it. Otherwise, lines stretch across the whole image.
+    pub max_gap: Option<f64>,
+    /// The minimum length of segments, in pixels, if they're being found.
+    pub min_length: f64,
+    /// The maximum number of lines to output, with lines that more pixels lie on coming first.
+    pub max_lines: usize,
+}
+impl HoughLineParams {
+    /// Create parameters with a vote threshold and the default for everything else.
+    pub const fn new(threshold: u32) -> Self {
+        Self {
+            threshold,
+            rho: 1.0,
+            theta: 1.0,
+            max_gap: None,
+            min_length: 0.0,
+            max_lines: usize::MAX,
+        }
+    }
+}
+
+/// Parameters for [`hough_circles`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct HoughCircleParams {
+    /// The smallest radius to look for, in pixels.
+    pub min_radius: f64,
+    /// The largest radius to look for, in pixels.
+    pub max_radius: f64,
+    /// The minimum number of edge pixels whose gradients have to point at a center for it to be considered.
+    pub threshold: u32,
+    /// The minimum fraction of a circle's circumference that has to be covered by edge pixels for it to be detected.
+    pub min_coverage: f64,
+    /// The minimum distance between the centers of circles, which defaults to the minimum radius.
+    pub min_distance: Option<f64>,
+    /// The low threshold for [Canny edge detection](super::canny).
+    pub low: f32,
+    /// The high threshold for [Canny edge detection](super::canny).
+    pub high: f32,
+    /// The kernel to compute gradients with.
+    pub kernel: GradientKernel,
+    /// The maximum number of circles to output, with circles with more votes for their centers coming first.
+    pub max_circles: usize,
+}
+
+/// Find the cells in an accumulator with at least `threshold` votes that are local maximums, with the most votes first.
+///
+/// `neighbors` gets the indices of the cells around a cell. Ties are broken by index, so plateaus only have one peak.
+fn peaks<T: Copy + Ord + Send + Sync, I: IntoIterator<Item = usize>>(
+    acc: &[T],
+    threshold: T,
+    neighbors: impl Fn(usize) -> I + Sync,
+) -> Vec<usize> {
+    let mut peaks = broadcast_pool().install(|| {
+        acc.par_iter()
+            .enumerate()
+            .filter(|&(idx, &votes)| {
+                votes >= threshold
+                    && neighbors(idx).into_iter().all(|n| {
+                        let other = acc[n];
+                        other < votes || (other == votes && n > idx)
+                    })
+            })
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>()
+    });
+    peaks.sort_by_key(|&idx| std::cmp::Reverse(acc[idx]));
+    peaks
+}
+
+/// Get the nonzero pixels of an image.
+fn edge_pixels(img: &Buffer<'_>) -> Vec<[u32; 2]> {
+    let width = img.width as usize;
+    img.data
+        .iter()
+        .enumerate()
+        .filter(|&(_, &px)| px != 0)
+        .map(|(idx, _)| [(idx % width) as u32, (idx / width) as u32])
+        .collect()
+}
+
+/// Detect straight lines in an edge image with the Hough transform.
+///
+/// Every nonzero pixel is considered an edge, so this is typically used on the output of [`canny`](super::canny) or a color filter.
+/// Images that aren't already [`LUMA`](PixelFormat::LUMA) are converted to it first.
+pub fn hough_lines(img: Buffer<'_>, params: &HoughLineParams) -> Vec<Line> {
+    assert!(params.rho > 0.0, "Distance resolution must be positive");
+    assert!(params.theta > 0.0, "Angle resolution must be positive");
+    let img = img.convert_cow(PixelFormat::LUMA);
+    if img.width == 0 || img.height == 0 {
+        return Vec::new();
+    }
+    let edges = edge_pixels(&img);
+    let n_theta = ((180.0 / params.theta).round() as usize).max(1);
+    let trig = (0..n_theta)
+        .map(|t| (t as f64 * PI / n_theta as f64).sin_cos())
+        .collect::<Vec<_>>();
+    let offset = ((img.width as f64).hypot(img.height as f64) / params.rho).ceil() as usize;
+    let n_rho = offset * 2 + 1;
+    // Each row of the accumulator is one angle, so the rows can be filled in parallel. The upper half of each cell is the number of
+    // votes, and the lower half adds up how close each pixel is to the center of the cell. Lines at slightly different angles often
+    // get the same number of votes, and this makes the one that fits the pixels best win.
+    let mut acc = vec![0u64; n_theta * n_rho];
+    broadcast_pool().install(|| {
+        acc.par_chunks_mut(n_rho)
+            .zip(&trig)
+            .for_each(|(row, &(sin, cos))| {
+                for &[x, y] in &edges {
+                    let rho = (x as f64 * cos + y as f64 * sin) / params.rho;
+                    let bin = rho.round();
+                    let closeness = ((0.5 - (rho - bin).abs()) * 256.0) as u64;
+                    row[(bin as isize + offset as isize) as usize] += (1 << 32) + closeness;
+                }
+            });
+    });
+    let threshold = (params.threshold.max(1) as u64) << 32;
+    let peaks = peaks(&acc, threshold, |idx| {
+        let (t, r) = (idx / n_rho, idx % n_rho);
+        let mut out = Vec::with_capacity(8);
+        for dt in [-1, 0, 1] {
+            for dr in [-1, 0, 1] {
+                if dt == 0 && dr == 0 {
+                    continue;
+                }
+                // angles wrap around to the same line with the distance negated
+                let (t, r) = match t as isize + dt {
+                    -1 => (n_theta - 1, n_rho - 1 - r),
+                    t if t == n_theta as isize => (0, n_rho - 1 - r),
+                    t => (t as usize, r),
+                };
+                let r = r as isize + dr;
+                if (0..n_rho as isize).contains(&r) {
+                    out.push(t * n_rho + r as usize);
+                }
+            }
+        }
+        out
+    });
+    let max_x = (img.width - 1) as f64;
+    let max_y = (img.height - 1) as f64;
+    let is_edge = |[x, y]: [f64; 2]| {
+        let (x, y) = (x.round(), y.round());
+        (0.0..=max_x).contains(&x)
+            && (0.0..=max_y).contains(&y)
+            && img.data[y as usize * img.width as usize + x as usize] != 0
+    };
+    let point = |[x, y]: [f64; 2]| [x.round().clamp(0.0, max_x), y.round().clamp(0.0, max_y)];
+    let line = |a: [f64; 2], b: [f64; 2]| {
+        let ([x0, y0], [x1, y1]) = (point(a), point(b));
+        Line {
+            x0: x0 as u32,
+            y0: y0 as u32,
+            x1: x1 as u32,
+            y1: y1 as u32,
+        }
+    };
+    let mut lines = Vec::new();
+    // Pixels near a line also vote for lines at slightly different angles through some of them. Only letting each pixel count toward
+    // one line, starting from the lines with the most votes, keeps those from being detected too.
+    let mut used = vec![false; edges.len()];
+    let mut on_line = Vec::new();
+    for idx in peaks {
+        if lines.len() >= params.max_lines {
+            break;
+        }
+        let (sin, cos) = trig[idx / n_rho];
+        let bin = (idx % n_rho) as isize - offset as isize;
+        on_line.clear();
+        on_line.extend(edges.iter().enumerate().filter_map(|(i, &[x, y])| {
+            let rho = (x as f64 * cos + y as f64 * sin) / params.rho;
+            (!used[i] && rho.round() as isize == bin).then_some(i)
+        }));
+        if on_line.len() < params.threshold as usize {
+            continue;
+        }
+        for &i in &on_line {
+            used[i] = true;
+        }
+        let rho = bin as f64 * params.rho;
+        // the line is the points `base + t * dir`
+        let base = [rho * cos, rho * sin];
+        let dir = [-sin, cos];
+        let at = |t: f64| [base[0] + t * dir[0], base[1] + t * dir[1]];
+        // clip the line to the image
+        let (mut t_min, mut t_max) = (f64::NEG_INFINITY, f64::INFINITY);
+        for (b, d, max) in [(base[0], dir[0], max_x), (base[1], dir[1], max_y)] {
+            if d.abs() < 1e-9 {
+                if !(0.0..=max).contains(&b) {
+                    t_min = f64::INFINITY;
+                }
+                continue;
+            }
+            let (a, b) = (-b / d, (max - b) / d);
+            t_min = t_min.max(a.min(b));
+            t_max = t_max.min(a.max(b));
+        }
+        if t_min > t_max {
+            continue;
+        }
+        let Some(max_gap) = params.max_gap else {
+            lines.push(line(at(t_min), at(t_max)));
+            continue;
+        };
+        // walk along the line, allowing for the accumulator's resolution on either side of it
+        let hit = |t: f64| {
+            let [x, y] = at(t);
+            let spread = params.rho * 0.5;
+            is_edge([x, y])
+                || is_edge([x + cos * spread, y + sin * spread])
+                || is_edge([x - cos * spread, y - sin * spread])
+        };
+        let mut segment: Option<(f64, f64)> = None;
+        let emit = |start: f64, end: f64, lines: &mut Vec<Line>| {
+            if end - start >= params.min_length && lines.len() < params.max_lines {
+                lines.push(line(at(start), at(end)));
+            }
+        };
+        let steps = (t_max - t_min).floor() as usize;
+        for i in 0..=steps {
+            let t = t_min + i as f64;
+            if !hit(t) {
+                continue;
+            }
+            segment = match segment {
+                Some((start, end)) if t - end <= max_gap + 1.0 => Some((start, t)),
+                Some((start, end)) => {
+                    emit(start, end, &mut lines);
+                    Some((t, t))
+                }
+                None => Some((t, t)),
+            };
+        }
+        if let Some((start, end)) = segment {
+            emit(start, end, &mut lines);
+        }
+    }
+    lines
+}
+
+/// Detect circles in an image with the Hough gradient method.
+///
+/// This finds edges with the [Canny edge detector](super::canny), and then each edge pixel votes for the centers that are between the
+/// minimum and maximum radius away from it, along its gradient. Centers with enough votes are then checked for a radius that enough
+/// edge pixels lie on. Unlike [`hough_lines`], this takes the original image rather than edges, since it needs the gradients.
+pub fn hough_circles(img: Buffer<'_>, params: &HoughCircleParams) -> Vec<Circle> {
+    assert!(
+        params.min_radius <= params.max_radius,
+        "Minimum radius {} is greater than maximum radius {}",
+        params.min_radius,
+        params.max_radius
+    );
+    let grads = Gradients::new(img, params.kernel);
+    let width = grads.width as usize;
+    let height = grads.height as usize;
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let mut edge_img = Buffer::empty_rgb();
+    canny_gradients(&grads, &mut edge_img, params.low, params.high);
+    let edges = edge_pixels(&edge_img);
+    let min_radius = params.min_radius.max(1.0);
+    let radii = (params.max_radius - min_radius).max(0.0).floor() as usize + 1;
+    let acc = broadcast_pool().install(|| {
+        edges
+            .par_iter()
+            .fold(
+                || vec![0u32; width * height],
+                |mut acc, &[x, y]| {
+                    let idx = y as usize * width + x as usize;
+                    let (dx, dy) = (grads.dx[idx] as f64, grads.dy[idx] as f64);
+                    let mag = dx.hypot(dy);
+                    if mag == 0.0 {
+                        return acc;
+                    }
+                    let (ux, uy) = (dx / mag, dy / mag);
+                    // the center could be on either side, depending on whether the circle is brighter or darker than its surroundings
+                    for sign in [-1.0, 1.0] {
+                        let mut last = usize::MAX;
+                        for i in 0..radii {
+                            let r = (min_radius + i as f64) * sign;
+                            let cx = (x as f64 + ux * r).round();
+                            let cy = (y as f64 + uy * r).round();
+                            if cx < 0.0 || cy < 0.0 || cx >= width as f64 || cy >= height as f64 {
+                                break;
+                            }
+                            let c = cy as usize * width + cx as usize;
+                            // don't vote twice for the same cell when stepping diagonally
+                            if c != last {
+                                acc[c] += 1;
+                                last = c;
+                            }
+                        }
+                    }
+                    acc
+                },
+            )
+            .reduce_with(|mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                a
+            })
+            .unwrap_or_else(|| vec![0; width * height])
+    });
+    let centers = peaks(&acc, params.threshold.max(1), |idx| {
+        let (x, y) = (idx % width, idx / width);
+        let mut out = Vec::with_capacity(8);
+        for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+            for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                if (nx, ny) != (x, y) {
+                    out.push(ny * width + nx);
+                }
+            }
+        }
+        out
+    });
+    let min_distance = params.min_distance.unwrap_or(params.min_radius);
+    let first_radius = min_radius.ceil() as usize;
+    // no edge pixel can be further from a center than the image's diagonal, so larger radii don't need buckets
+    let diagonal = (width as f64).hypot(height as f64).ceil() as usize;
+    let last_radius = (params.max_radius.floor() as usize).min(diagonal);
+    let mut circles = Vec::<Circle>::new();
+    for idx in centers {
+        if circles.len() >= params.max_circles {
+            break;
+        }
+        let center = [(idx % width) as f64, (idx / width) as f64];
+        if circles
+            .iter()
+            .any(|c| (c.center[0] - center[0]).hypot(c.center[1] - center[1]) < min_distance)
+        {
+            continue;
+        }
+        // bucket the distances of the edge pixels by their integer part, keeping their sums to find the exact radius
+        let mut counts = vec![0u32; last_radius + 2];
+        let mut sums = vec![0.0; last_radius + 2];
+        for &[x, y] in &edges {
+            let d = (x as f64 - center[0]).hypot(y as f64 - center[1]);
+            let bucket = d as usize;
+            if bucket < counts.len() {
+                counts[bucket] += 1;
+                sums[bucket] += d;
+            }
+        }
+        // each radius gets the pixels within one pixel of it, relative to how many a full circle would have, and radii without any
+        // pixels are skipped so that the exact radius is never 0/0, even if the minimum coverage is 0
+        let best = (first_radius..=last_radius)
+            .filter(|&r| r > 0 && counts[r - 1] + counts[r] > 0)
+            .map(|r| {
+                let support = counts[r - 1] + counts[r];
+                (r, support as f64 / (TAU * r as f64))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let Some((r, coverage)) = best else {
+            continue;
+        };
+        if coverage < params.min_coverage {
+            continue;
+        }
+        let radius = (sums[r - 1] + sums[r]) / (counts[r - 1] + counts[r]) as f64;
+        circles.push(Circle { center, radius });
+    }
+    circles
+}
@@ -6,12 +6,32 @@ use rayon::prelude::*;
 use std::cell::Cell;
 
 mod blobs;
+mod chessboard;
 mod color;
+mod contours;
+mod edges;
+mod hough;
+mod morph;
+mod orient;
+mod remap;
+mod resize;
+mod shape;
 #[cfg(test)]
 mod tests;
+mod threshold;
 
 pub use blobs::*;
+pub use chessboard::*;
 pub use color::*;
+pub use contours::*;
+pub use edges::*;
+pub use hough::*;
+pub use morph::*;
+pub use orient::*;
+pub use remap::*;
+pub use resize::*;
+pub use shape::*;
+pub use threshold::*;
 
 /// A [`Broadcast2`] implementor that outputs into a black/white image based on a minimum and maximum channel range
 #[derive(Debug, Clone, Copy)]
@@ -90,7 +110,8 @@ pub fn color_filter(mut src: Buffer<'_>, dst: &mut Buffer<'_>, filter: ColorFilt
 /// Percentile filter an image.
 ///
 /// The width and height must be odd numbers, and the index must be less than their product.
-/// An index of 0 is a dilation, an index of `(width * height)` is an erosion, and `(width * height / 2)` is a median filter.
+/// An index of 0 is an erosion, an index of `(width * height - 1)` is a dilation, and `(width * height / 2)` is a median filter.
+/// [`erode`] and [`dilate`] are much faster for those, though.
 /// The output buffer will have the same dimensions and format as the input buffer.
 pub fn percentile_filter(
     src: Buffer<'_>,
@@ -367,40 +388,6 @@ pub fn gaussian_blur(
     });
 }
 
-/// Resize an image.
-///
-/// This uses nearest-neighbor resizing for simplicity.
-pub fn resize(from: Buffer<'_>, to: &mut Buffer<'_>, mut new_width: u32, new_height: u32) {
-    let chunk_size = if from.format == PixelFormat::YUYV {
-        new_width += 1;
-        new_width &= !1;
-        4
-    } else {
-        from.format.pixel_size()
-    };
-    to.format = from.format;
-    to.width = new_width;
-    to.height = new_height;
-    let xmul = from.width as usize;
-    let xdiv = to.width as usize;
-    let ymul = from.height as usize;
-    let ydiv = to.height as usize;
-    broadcast_pool().install(|| {
-        to.resize_data()
-            .par_chunks_mut(chunk_size)
-            .enumerate()
-            .for_each(|(n, px)| {
-                let y = n / xdiv;
-                let x = n % xdiv;
-                // We love removing bounds checks to halve the time this takes
-                unsafe {
-                    let old = from.pixel_unchecked((x * xmul / xdiv) as _, (y * ymul / ydiv) as _);
-                    std::ptr::copy_nonoverlapping(old.as_ptr(), px.as_mut_ptr(), chunk_size);
-                }
-            });
-    });
-}
-
 /// A [`Broadcast2`] implementor that reorders the channels of an image
 #[derive(Debug, Clone, Copy)]
 pub struct Swizzle<'a> {
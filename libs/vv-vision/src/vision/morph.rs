@@ -0,0 +1,311 @@
+use super::AssertSync;
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+/// The shape of a structuring element, which is the window that morphological operations look at around each pixel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ElementShape {
+    /// The whole rectangle.
+    #[default]
+    Rect,
+    /// The middle row and column of the rectangle.
+    Cross,
+    /// The ellipse that fits in the rectangle.
+    ///
+    /// This is split into one rectangle for each distinct row width, so it takes about that many times as long as a rectangle.
+    Ellipse,
+}
+impl ElementShape {
+    /// Get the rectangles that this shape is made up of, as their half-widths and half-heights.
+    fn rects(self, width: usize, height: usize) -> Vec<[usize; 2]> {
+        let (rx, ry) = (width / 2, height / 2);
+        match self {
+            Self::Rect => vec![[rx, ry]],
+            Self::Cross => vec![[rx, 0], [0, ry]],
+            Self::Ellipse => {
+                if ry == 0 {
+                    return vec![[rx, 0]];
+                }
+                // the half-width of each row, going outwards from the middle, which never gets wider
+                let half_width = |dy: usize| {
+                    let t = dy as f64 / ry as f64;
+                    (rx as f64 * (1.0 - t * t).sqrt()).round() as usize
+                };
+                // each rectangle is as tall as the rows that are at least its width
+                let mut rects = Vec::<[usize; 2]>::new();
+                for dy in 0..=ry {
+                    let hw = half_width(dy);
+                    match rects.last_mut() {
+                        Some(last) if last[0] == hw => last[1] = dy,
+                        _ => rects.push([hw, dy]),
+                    }
+                }
+                rects
+            }
+        }
+    }
+
+    /// Check if the structuring element contains an offset from its center.
+    pub fn contains(self, width: usize, height: usize, dx: isize, dy: isize) -> bool {
+        self.rects(width, height)
+            .iter()
+            .any(|&[rx, ry]| dx.unsigned_abs() <= rx && dy.unsigned_abs() <= ry)
+    }
+}
+
+/// A morphological operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum MorphOp {
+    /// Take the minimum of each window, which shrinks bright regions.
+    Erode,
+    /// Take the maximum of each window, which grows bright regions.
+    Dilate,
+    /// Erode and then dilate, which removes bright regions smaller than the window.
+    Open,
+    /// Dilate and then erode, which fills in dark gaps smaller than the window.
+    Close,
+    /// Subtract the erosion from the dilation, which leaves the outlines of regions.
+    Gradient,
+    /// Subtract the opening from the image, which leaves the bright regions smaller than the window.
+    TopHat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Extremum {
+    Min,
+    Max,
+}
+impl Extremum {
+    /// The value that doesn't change the result when it's in the window, which is used past the edges of the image.
+    const fn identity(self) -> u8 {
+        match self {
+            Self::Min => 255,
+            Self::Max => 0,
+        }
+    }
+    #[inline(always)]
+    fn pick(self, a: u8, b: u8) -> u8 {
+        match self {
+            Self::Min => a.min(b),
+            Self::Max => a.max(b),
+        }
+    }
+}
+
+/// Buffers reused between lines.
+#[derive(Default)]
+struct Scratch {
+    padded: Vec<u8>,
+    forward: Vec<u8>,
+    backward: Vec<u8>,
+}
+
+/// Filter one line of `len` values with a window of `size` values, with the van Herk/Gil-Werman algorithm.
+///
+/// The line is split into blocks of the window size, and the running extremum is computed forwards and backwards through each block.
+/// Every window covers the end of one block and the start of the next, so its result is the backward value at its start combined
+/// with the forward value at its end. This takes three comparisons per value, no matter how big the window is.
+fn filter_line(
+    scratch: &mut Scratch,
+    len: usize,
+    size: usize,
+    ext: Extremum,
+    input: impl Fn(usize) -> u8,
+    mut output: impl FnMut(usize, u8),
+) {
+    let half = size / 2;
+    let padded_len = (len + half * 2).div_ceil(size) * size;
+    let Scratch {
+        padded,
+        forward,
+        backward,
+    } = scratch;
+    padded.clear();
+    padded.resize(padded_len, ext.identity());
+    for i in 0..len {
+        padded[i + half] = input(i);
+    }
+    forward.resize(padded_len, 0);
+    backward.resize(padded_len, 0);
+    for start in (0..padded_len).step_by(size) {
+        let end = start + size - 1;
+        forward[start] = padded[start];
+        for i in (start + 1)..=end {
+            forward[i] = ext.pick(forward[i - 1], padded[i]);
+        }
+        backward[end] = padded[end];
+        for i in (start..end).rev() {
+            backward[i] = ext.pick(backward[i + 1], padded[i]);
+        }
+    }
+    for i in 0..len {
+        output(i, ext.pick(backward[i], forward[i + size - 1]));
+    }
+}
+
+/// Filter an image with a rectangular window, one axis at a time.
+fn filter_rect(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    pxlen: usize,
+    [rx, ry]: [usize; 2],
+    ext: Extremum,
+) {
+    let row_len = width * pxlen;
+    let height = src.len() / row_len;
+    if rx > 0 {
+        dst.par_chunks_mut(row_len)
+            .zip(src.par_chunks(row_len))
+            .for_each_init(Scratch::default, |scratch, (out, row)| {
+                for c in 0..pxlen {
+                    filter_line(
+                        scratch,
+                        width,
+                        rx * 2 + 1,
+                        ext,
+                        |x| row[x * pxlen + c],
+                        |x, v| out[x * pxlen + c] = v,
+                    );
+                }
+            });
+    } else {
+        dst.copy_from_slice(src);
+    }
+    if ry > 0 {
+        // filter the columns in place, since each one is only read before it's written
+        let cells = AssertSync(Cell::from_mut(dst).as_slice_of_cells());
+        (0..row_len)
+            .into_par_iter()
+            .for_each_init(Scratch::default, |scratch, col| {
+                let cells = cells.inner();
+                filter_line(
+                    scratch,
+                    height,
+                    ry * 2 + 1,
+                    ext,
+                    |y| cells[y * row_len + col].get(),
+                    |y, v| cells[y * row_len + col].set(v),
+                );
+            });
+    }
+}
+
+/// Filter an image with a structuring element.
+fn filter(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    pxlen: usize,
+    shape: ElementShape,
+    size: [usize; 2],
+    ext: Extremum,
+) {
+    let rects = shape.rects(size[0], size[1]);
+    let (first, rest) = rects
+        .split_first()
+        .expect("shapes have at least one rectangle");
+    filter_rect(src, dst, width, pxlen, *first, ext);
+    let mut tmp = vec![0; src.len()];
+    for &rect in rest {
+        filter_rect(src, &mut tmp, width, pxlen, rect, ext);
+        dst.par_iter_mut()
+            .zip(&tmp)
+            .for_each(|(a, &b)| *a = ext.pick(*a, b));
+    }
+}
+
+/// Apply a morphological operation to an image.
+///
+/// The width and height of the structuring element must be odd numbers. Each channel is processed separately, and pixels past the
+/// edges of the image are ignored. Rectangles and crosses take the same time no matter how large they are.
+/// The output buffer will have the same dimensions and format as the input buffer.
+pub fn morphology(
+    src: Buffer<'_>,
+    dst: &mut Buffer<'_>,
+    op: MorphOp,
+    shape: ElementShape,
+    width: usize,
+    height: usize,
+) {
+    assert_ne!(
+        src.format,
+        PixelFormat::YUYV,
+        "Morphology isn't implemented for YUYV images"
+    );
+    assert!(width & 1 == 1, "Window width must be an odd number");
+    assert!(height & 1 == 1, "Window height must be an odd number");
+    dst.width = src.width;
+    dst.height = src.height;
+    dst.format = src.format;
+    let data = dst.resize_data();
+    if data.is_empty() {
+        return;
+    }
+    let img_width = src.width as usize;
+    let pxlen = src.format.pixel_size();
+    let size = [width, height];
+    let run =
+        |src: &[u8], dst: &mut [u8], ext| filter(src, dst, img_width, pxlen, shape, size, ext);
+    broadcast_pool().install(|| match op {
+        MorphOp::Erode => run(&src.data, data, Extremum::Min),
+        MorphOp::Dilate => run(&src.data, data, Extremum::Max),
+        MorphOp::Open | MorphOp::Close | MorphOp::TopHat => {
+            let (first, second) = if op == MorphOp::Close {
+                (Extremum::Max, Extremum::Min)
+            } else {
+                (Extremum::Min, Extremum::Max)
+            };
+            let mut tmp = vec![0; data.len()];
+            run(&src.data, &mut tmp, first);
+            run(&tmp, data, second);
+            if op == MorphOp::TopHat {
+                data.par_iter_mut()
+                    .zip(&*src.data)
+                    .for_each(|(out, &px)| *out = px - *out);
+            }
+        }
+        MorphOp::Gradient => {
+            let mut eroded = vec![0; data.len()];
+            run(&src.data, &mut eroded, Extremum::Min);
+            run(&src.data, data, Extremum::Max);
+            data.par_iter_mut()
+                .zip(eroded)
+                .for_each(|(out, low)| *out -= low);
+        }
+    });
+}
+
+/// Erode an image, taking the minimum of the structuring element around each pixel.
+///
+/// See [`morphology`] for the requirements.
+pub fn erode(
+    src: Buffer<'_>,
+    dst: &mut Buffer<'_>,
+    shape: ElementShape,
+    width: usize,
+    height: usize,
+) {
+    morphology(src, dst, MorphOp::Erode, shape, width, height);
+}
+
+/// Dilate an image, taking the maximum of the structuring element around each pixel.
+///
+/// See [`morphology`] for the requirements.
+pub fn dilate(
+    src: Buffer<'_>,
+    dst: &mut Buffer<'_>,
+    shape: ElementShape,
+    width: usize,
+    height: usize,
+) {
+    morphology(src, dst, MorphOp::Dilate, shape, width, height);
+}
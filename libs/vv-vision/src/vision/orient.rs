@@ -0,0 +1,197 @@
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A clockwise rotation by a multiple of 90 degrees.
+///
+/// This is serialized as the number of degrees.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u16", into = "u16"))]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+impl Rotation {
+    /// Get the rotation in degrees.
+    pub const fn degrees(self) -> u16 {
+        match self {
+            Self::None => 0,
+            Self::Cw90 => 90,
+            Self::Cw180 => 180,
+            Self::Cw270 => 270,
+        }
+    }
+    /// Check if this rotation swaps the width and height.
+    pub const fn is_transposed(self) -> bool {
+        matches!(self, Self::Cw90 | Self::Cw270)
+    }
+}
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::None),
+            90 => Ok(Self::Cw90),
+            180 => Ok(Self::Cw180),
+            270 => Ok(Self::Cw270),
+            _ => Err(format!(
+                "rotation must be 0, 90, 180, or 270 degrees, not {value}"
+            )),
+        }
+    }
+}
+impl From<Rotation> for u16 {
+    fn from(value: Rotation) -> Self {
+        value.degrees()
+    }
+}
+
+/// Which axes to mirror an image across.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Flip {
+    #[default]
+    None,
+    /// Mirror left-to-right.
+    Horizontal,
+    /// Mirror top-to-bottom.
+    Vertical,
+    /// Mirror both ways, which is the same as rotating by 180 degrees.
+    Both,
+}
+impl Flip {
+    const fn axes(self) -> [bool; 2] {
+        match self {
+            Self::None => [false, false],
+            Self::Horizontal => [true, false],
+            Self::Vertical => [false, true],
+            Self::Both => [true, true],
+        }
+    }
+}
+
+/// A rectangular region of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A change to the orientation of an image, followed by an optional crop.
+///
+/// The image is rotated first, then flipped, and the crop is in the coordinates of the rotated and flipped image. Crops that extend
+/// past the edges of the image are clipped to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Orientation {
+    pub rotate: Rotation,
+    pub flip: Flip,
+    pub crop: Option<CropRect>,
+}
+impl Orientation {
+    /// Check if this leaves images unchanged.
+    pub const fn is_identity(&self) -> bool {
+        matches!(self.rotate, Rotation::None)
+            && matches!(self.flip, Flip::None)
+            && self.crop.is_none()
+    }
+    /// Get the size of the rotated image, before cropping.
+    pub const fn rotated_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.rotate.is_transposed() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+    /// Get the crop, clipped to a rotated image of the given size.
+    pub fn clipped_crop(&self, width: u32, height: u32) -> CropRect {
+        let Some(crop) = self.crop else {
+            return CropRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            };
+        };
+        let x = crop.x.min(width);
+        let y = crop.y.min(height);
+        CropRect {
+            x,
+            y,
+            width: crop.width.min(width - x),
+            height: crop.height.min(height - y),
+        }
+    }
+    /// Get the size of an image after this is applied to it.
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = self.rotated_size(width, height);
+        let crop = self.clipped_crop(width, height);
+        (crop.width, crop.height)
+    }
+    /// Map a pixel in the rotated and flipped image back to the source image.
+    #[inline(always)]
+    const fn source(&self, x: u32, y: u32, width: u32, height: u32) -> (u32, u32) {
+        let (rw, rh) = self.rotated_size(width, height);
+        let [fh, fv] = self.flip.axes();
+        let x = if fh { rw - 1 - x } else { x };
+        let y = if fv { rh - 1 - y } else { y };
+        match self.rotate {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (y, height - 1 - x),
+            Rotation::Cw180 => (width - 1 - x, height - 1 - y),
+            Rotation::Cw270 => (width - 1 - y, x),
+        }
+    }
+}
+
+/// Rotate, flip, and crop an image.
+///
+/// For YUYV images, each pixel keeps its own luma, and takes the chroma from the pair that it came from.
+pub fn orient(from: Buffer<'_>, to: &mut Buffer<'_>, orientation: Orientation) {
+    let (rw, rh) = orientation.rotated_size(from.width, from.height);
+    let crop = orientation.clipped_crop(rw, rh);
+    to.format = from.format;
+    to.width = crop.width;
+    to.height = crop.height;
+    let pixel_size = from.format.pixel_size();
+    let row_len = crop.width as usize * pixel_size;
+    let data = to.resize_data();
+    if row_len == 0 {
+        return;
+    }
+    let yuyv = from.format == PixelFormat::YUYV;
+    let src_width = from.width as usize;
+    broadcast_pool().install(|| {
+        data.par_chunks_exact_mut(row_len)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, px) in row.chunks_exact_mut(pixel_size).enumerate() {
+                    let (sx, sy) = orientation.source(
+                        x as u32 + crop.x,
+                        y as u32 + crop.y,
+                        from.width,
+                        from.height,
+                    );
+                    let (sx, sy) = (sx as usize, sy as usize);
+                    let idx = (sy * src_width + sx) * pixel_size;
+                    if yuyv {
+                        // U comes after the first luma sample of a pair, and V after the second
+                        let chroma = (sy * src_width + (sx & !1) + (x & 1)) * 2 + 1;
+                        px[0] = from.data[idx];
+                        px[1] = from.data.get(chroma).copied().unwrap_or(128);
+                    } else {
+                        px.copy_from_slice(&from.data[idx..idx + pixel_size]);
+                    }
+                }
+            });
+    });
+}
@@ -0,0 +1,185 @@
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How to sample an image at a position between pixels, for [`remap`] and [`resize`](super::resize).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Interpolation {
+    /// Use the closest pixel.
+    ///
+    /// This is the fastest mode, but when shrinking an image, it skips over pixels entirely, so thin edges and fine patterns alias.
+    Nearest,
+    /// Linearly interpolate between the four surrounding pixels.
+    ///
+    /// This is smooth when enlarging an image, but still skips over pixels when shrinking one to less than half its size.
+    #[default]
+    Bilinear,
+    /// Average all of the pixels that each new pixel covers, weighted by how much of them it covers.
+    ///
+    /// This is the best mode for shrinking an image, since every pixel contributes to the result. When enlarging an image, pixels are
+    /// only blended where their edges fall inside a new pixel. [`remap`] doesn't know how much of the source each pixel covers, so it
+    /// treats this like [`Bilinear`](Self::Bilinear).
+    Area,
+}
+
+/// Marker for a destination pixel that maps outside of the source image.
+const OUT_OF_BOUNDS: u32 = u32::MAX;
+
+/// A source position, split into an integer part and a fraction in 256ths.
+///
+/// The fraction is rounded, so it can be anywhere from 0 to 256 inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RemapEntry {
+    x: u32,
+    y: u32,
+    fx: u16,
+    fy: u16,
+}
+
+/// A precomputed mapping from each pixel in a destination image to a position in a source image, for use with [`remap`].
+///
+/// Building a map is relatively expensive, so it should be reused across frames when possible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemapMap {
+    /// Width of the destination image.
+    pub width: u32,
+    /// Height of the destination image.
+    pub height: u32,
+    /// Width of the source image.
+    pub src_width: u32,
+    /// Height of the source image.
+    pub src_height: u32,
+    entries: Vec<RemapEntry>,
+}
+impl RemapMap {
+    /// Build a map by calling a function with each destination pixel to get its position in the source image.
+    ///
+    /// Pixel centers are at integer coordinates. Positions that are more than half a pixel outside of the source image will be filled with zeroes.
+    pub fn from_fn(
+        width: u32,
+        height: u32,
+        src_width: u32,
+        src_height: u32,
+        f: impl Fn(u32, u32) -> [f64; 2] + Sync,
+    ) -> Self {
+        let max_x = src_width as f64 - 0.5;
+        let max_y = src_height as f64 - 0.5;
+        let entries = broadcast_pool().install(|| {
+            (0..(width as usize * height as usize))
+                .into_par_iter()
+                .map(|n| {
+                    let [x, y] = f((n % width as usize) as u32, (n / width as usize) as u32);
+                    if !(x >= -0.5 && y >= -0.5 && x < max_x && y < max_y) {
+                        return RemapEntry {
+                            x: OUT_OF_BOUNDS,
+                            y: OUT_OF_BOUNDS,
+                            fx: 0,
+                            fy: 0,
+                        };
+                    }
+                    let x = x.clamp(0.0, (src_width - 1) as f64);
+                    let y = y.clamp(0.0, (src_height - 1) as f64);
+                    let ix = x.floor();
+                    let iy = y.floor();
+                    RemapEntry {
+                        x: ix as u32,
+                        y: iy as u32,
+                        fx: ((x - ix) * 256.0).round() as u16,
+                        fy: ((y - iy) * 256.0).round() as u16,
+                    }
+                })
+                .collect()
+        });
+        Self {
+            width,
+            height,
+            src_width,
+            src_height,
+            entries,
+        }
+    }
+    /// Get the source position for a destination pixel, or `None` if it's out of bounds.
+    pub fn get(&self, x: u32, y: u32) -> Option<[f64; 2]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let entry = self.entries[y as usize * self.width as usize + x as usize];
+        (entry.x != OUT_OF_BOUNDS).then(|| {
+            [
+                entry.x as f64 + entry.fx as f64 / 256.0,
+                entry.y as f64 + entry.fy as f64 / 256.0,
+            ]
+        })
+    }
+}
+
+/// Remap an image with a precomputed map.
+///
+/// This works for all interleaved formats. YUYV images are converted to YCbCr first, since their pixels can't be sampled individually.
+/// Panics if the source image isn't the size that the map expects.
+pub fn remap(src: Buffer<'_>, dst: &mut Buffer<'_>, map: &RemapMap, interpolation: Interpolation) {
+    assert_eq!(
+        (src.width, src.height),
+        (map.src_width, map.src_height),
+        "source image doesn't match the map's size"
+    );
+    let src = if src.format == PixelFormat::YUYV {
+        src.convert_cow(PixelFormat::YCC)
+    } else {
+        src
+    };
+    dst.width = map.width;
+    dst.height = map.height;
+    dst.format = src.format;
+    if src.width == 0 || src.height == 0 {
+        // every position is out of bounds
+        dst.resize_data().fill(0);
+        return;
+    }
+    let pxlen = src.format.pixel_size();
+    let src_width = src.width as usize;
+    let max_x = src.width - 1;
+    let max_y = src.height - 1;
+    let data = &*src.data;
+    let at = |x: u32, y: u32| {
+        let start = (y as usize * src_width + x as usize) * pxlen;
+        &data[start..(start + pxlen)]
+    };
+    broadcast_pool().install(|| {
+        dst.resize_data()
+            .par_chunks_mut(pxlen)
+            .zip(&map.entries)
+            .for_each(|(px, entry)| {
+                if entry.x == OUT_OF_BOUNDS {
+                    px.fill(0);
+                    return;
+                }
+                match interpolation {
+                    Interpolation::Nearest => {
+                        let x = (entry.x + (entry.fx >= 128) as u32).min(max_x);
+                        let y = (entry.y + (entry.fy >= 128) as u32).min(max_y);
+                        px.copy_from_slice(at(x, y));
+                    }
+                    Interpolation::Bilinear | Interpolation::Area => {
+                        let x1 = (entry.x + 1).min(max_x);
+                        let y1 = (entry.y + 1).min(max_y);
+                        let tl = at(entry.x, entry.y);
+                        let tr = at(x1, entry.y);
+                        let bl = at(entry.x, y1);
+                        let br = at(x1, y1);
+                        let fx = entry.fx as u32;
+                        let fy = entry.fy as u32;
+                        for (c, out) in px.iter_mut().enumerate() {
+                            let top = tl[c] as u32 * (256 - fx) + tr[c] as u32 * fx;
+                            let bottom = bl[c] as u32 * (256 - fx) + br[c] as u32 * fx;
+                            *out = ((top * (256 - fy) + bottom * fy + 32768) >> 16) as u8;
+                        }
+                    }
+                }
+            });
+    });
+}
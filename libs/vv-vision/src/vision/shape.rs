@@ -0,0 +1,276 @@
+/// Get the cross product of `a - o` and `b - o`.
+fn cross(o: [u32; 2], a: [u32; 2], b: [u32; 2]) -> i64 {
+    let [ox, oy] = o.map(i64::from);
+    let [ax, ay] = a.map(i64::from);
+    let [bx, by] = b.map(i64::from);
+    (ax - ox) * (by - oy) - (ay - oy) * (bx - ox)
+}
+
+/// Divide a measurement of a shape by the same measurement of a bigger shape that contains it, like an area or perimeter.
+///
+/// The result is clamped to 1 to account for rounding. If the bigger shape is empty, so is the smaller one, and it counts as filling
+/// it completely.
+pub fn fill_ratio(inner: f64, outer: f64) -> f64 {
+    if outer == 0.0 {
+        1.0
+    } else {
+        (inner / outer).min(1.0)
+    }
+}
+
+/// Get the area of a closed polygon.
+///
+/// The points can go around the polygon in either direction.
+pub fn polygon_area(points: &[[u32; 2]]) -> f64 {
+    let n = points.len();
+    let twice = (0..n)
+        .map(|i| {
+            let [x0, y0] = points[i];
+            let [x1, y1] = points[(i + 1) % n];
+            x0 as f64 * y1 as f64 - x1 as f64 * y0 as f64
+        })
+        .sum::<f64>();
+    twice.abs() / 2.0
+}
+
+/// Get the perimeter of a closed polygon.
+pub fn polygon_perimeter(points: &[[u32; 2]]) -> f64 {
+    let n = points.len();
+    if n < 2 {
+        return 0.0;
+    }
+    (0..n)
+        .map(|i| {
+            let [x0, y0] = points[i];
+            let [x1, y1] = points[(i + 1) % n];
+            (x1 as f64 - x0 as f64).hypot(y1 as f64 - y0 as f64)
+        })
+        .sum()
+}
+
+/// Get the convex hull of a set of points.
+///
+/// The hull goes counterclockwise as the image appears, the same as a [`Contour`](super::Contour), starting from the leftmost point
+/// (the topmost one if there's a tie). Points along the edges of the hull aren't included.
+pub fn convex_hull(points: &[[u32; 2]]) -> Vec<[u32; 2]> {
+    let mut sorted = points.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() <= 2 {
+        return sorted;
+    }
+    // Andrew's monotone chain, with the turns reversed since y points down
+    let mut hull: Vec<[u32; 2]> = Vec::with_capacity(sorted.len() + 1);
+    for &p in &sorted {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) >= 0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    let lower = hull.len() + 1;
+    for &p in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) >= 0 {
+            hull.pop();
+        }
+        hull.push(p);
+    }
+    hull.pop();
+    hull
+}
+
+/// A rectangle that can be rotated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RotatedRect {
+    pub center: [f64; 2],
+    /// The length of the longer side.
+    pub width: f64,
+    /// The length of the shorter side.
+    pub height: f64,
+    /// The angle of the longer side, clockwise from horizontal as the image appears, in degrees.
+    ///
+    /// This is always more than -90 and at most 90.
+    pub angle: f64,
+}
+impl RotatedRect {
+    /// Get the area of this rectangle.
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+    /// Get the ratio of the longer side to the shorter one.
+    ///
+    /// This is infinite for a rectangle with no height.
+    pub fn aspect(&self) -> f64 {
+        self.width / self.height
+    }
+    /// Get the corners of this rectangle, going around it in order.
+    pub fn corners(&self) -> [[f64; 2]; 4] {
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let [cx, cy] = self.center;
+        let (hw, hh) = (self.width / 2.0, self.height / 2.0);
+        [[-hw, -hh], [hw, -hh], [hw, hh], [-hw, hh]]
+            .map(|[x, y]| [cx + x * cos - y * sin, cy + x * sin + y * cos])
+    }
+}
+
+/// Get the smallest rectangle, rotated to any angle, that contains a convex polygon.
+///
+/// One of the rectangle's sides is always along an edge of the polygon, so this just checks each edge. To find the rectangle for
+/// any set of points, pass in their [`convex_hull`] first.
+pub fn min_area_rect(hull: &[[u32; 2]]) -> RotatedRect {
+    let pts = hull.iter().map(|p| p.map(f64::from)).collect::<Vec<_>>();
+    let Some(&first) = pts.first() else {
+        return RotatedRect {
+            center: [0.0; 2],
+            width: 0.0,
+            height: 0.0,
+            angle: 0.0,
+        };
+    };
+    let mut best = RotatedRect {
+        center: first,
+        width: 0.0,
+        height: 0.0,
+        angle: 0.0,
+    };
+    let mut best_area = f64::INFINITY;
+    let n = pts.len();
+    for i in 0..n {
+        let [ox, oy] = pts[i];
+        let [nx, ny] = pts[(i + 1) % n];
+        let len = (nx - ox).hypot(ny - oy);
+        if len == 0.0 {
+            continue;
+        }
+        let u = [(nx - ox) / len, (ny - oy) / len];
+        let v = [-u[1], u[0]];
+        let (mut min_u, mut max_u, mut min_v, mut max_v) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+        for &[x, y] in &pts {
+            let pu = (x - ox) * u[0] + (y - oy) * u[1];
+            let pv = (x - ox) * v[0] + (y - oy) * v[1];
+            min_u = min_u.min(pu);
+            max_u = max_u.max(pu);
+            min_v = min_v.min(pv);
+            max_v = max_v.max(pv);
+        }
+        let area = (max_u - min_u) * (max_v - min_v);
+        if area < best_area {
+            best_area = area;
+            let (mu, mv) = ((min_u + max_u) / 2.0, (min_v + max_v) / 2.0);
+            let center = [ox + u[0] * mu + v[0] * mv, oy + u[1] * mu + v[1] * mv];
+            let (w, h) = (max_u - min_u, max_v - min_v);
+            let mut angle = u[1].atan2(u[0]).to_degrees();
+            let (width, height) = if w >= h {
+                (w, h)
+            } else {
+                angle += 90.0;
+                (h, w)
+            };
+            while angle > 90.0 {
+                angle -= 180.0;
+            }
+            while angle <= -90.0 {
+                angle += 180.0;
+            }
+            best = RotatedRect {
+                center,
+                width,
+                height,
+                angle,
+            };
+        }
+    }
+    best
+}
+
+/// A circle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Circle {
+    pub center: [f64; 2],
+    pub radius: f64,
+}
+impl Circle {
+    /// Get the area of this circle.
+    pub fn area(&self) -> f64 {
+        std::f64::consts::PI * self.radius * self.radius
+    }
+    /// Check if a point is inside this circle, with some tolerance for rounding.
+    fn contains(&self, [x, y]: [f64; 2]) -> bool {
+        (x - self.center[0]).hypot(y - self.center[1]) <= self.radius + 1e-7
+    }
+    /// Get the smallest circle through two points.
+    fn from_two([ax, ay]: [f64; 2], [bx, by]: [f64; 2]) -> Self {
+        Self {
+            center: [(ax + bx) / 2.0, (ay + by) / 2.0],
+            radius: (bx - ax).hypot(by - ay) / 2.0,
+        }
+    }
+    /// Get the smallest circle through three points.
+    fn from_three(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> Self {
+        let [ax, ay] = a;
+        let [bx, by] = b;
+        let [cx, cy] = c;
+        let d = 2.0 * (ax * (by - cy) + bx * (cy - ay) + cx * (ay - by));
+        if d.abs() < 1e-9 {
+            // the points are on a line, so the circle goes through the two farthest ones
+            return [(a, b), (a, c), (b, c)]
+                .map(|(p, q)| Self::from_two(p, q))
+                .into_iter()
+                .max_by(|l, r| l.radius.total_cmp(&r.radius))
+                .unwrap();
+        }
+        let (a2, b2, c2) = (ax * ax + ay * ay, bx * bx + by * by, cx * cx + cy * cy);
+        let x = (a2 * (by - cy) + b2 * (cy - ay) + c2 * (ay - by)) / d;
+        let y = (a2 * (cx - bx) + b2 * (ax - cx) + c2 * (bx - ax)) / d;
+        Self {
+            center: [x, y],
+            radius: (ax - x).hypot(ay - y),
+        }
+    }
+}
+
+/// Get the smallest circle that contains every point.
+///
+/// This uses Welzl's algorithm, which takes expected linear time. Passing in the [`convex_hull`] of the points first gives the same
+/// circle with fewer points to check.
+pub fn min_enclosing_circle(points: &[[u32; 2]]) -> Circle {
+    let mut pts = points.iter().map(|p| p.map(f64::from)).collect::<Vec<_>>();
+    // the expected time only holds for points in a random order, but it doesn't need to be a good random order
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for i in (1..pts.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        pts.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    let Some(&first) = pts.first() else {
+        return Circle {
+            center: [0.0; 2],
+            radius: 0.0,
+        };
+    };
+    let mut circle = Circle {
+        center: first,
+        radius: 0.0,
+    };
+    for i in 1..pts.len() {
+        if circle.contains(pts[i]) {
+            continue;
+        }
+        circle = Circle {
+            center: pts[i],
+            radius: 0.0,
+        };
+        for j in 0..i {
+            if circle.contains(pts[j]) {
+                continue;
+            }
+            circle = Circle::from_two(pts[i], pts[j]);
+            for k in 0..j {
+                if !circle.contains(pts[k]) {
+                    circle = Circle::from_three(pts[i], pts[j], pts[k]);
+                }
+            }
+        }
+    }
+    circle
+}
@@ -21,8 +21,53 @@ fn assert_timeout<R: Send + 'static, F: FnOnce() -> R + Send + 'static>(
     }
 }
 
+#[track_caller]
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-6, "{a} != {b}");
+}
+
+/// Make a grayscale image with each pixel's value given by `f`.
+fn luma(width: u32, height: u32, f: impl Fn(u32, u32) -> u8) -> Buffer<'static> {
+    let data = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| f(x, y))
+        .collect::<Vec<u8>>();
+    Buffer {
+        width,
+        height,
+        format: PixelFormat::LUMA,
+        data: data.into(),
+    }
+}
+
 mod blob {
     use super::*;
+    use std::ops::Range;
+
+    /// Add up the moments of the pixels in part of an image, one pixel at a time.
+    pub(super) fn sum_moments(
+        xs: Range<u32>,
+        ys: Range<u32>,
+        pixel: impl Fn(u32, u32) -> bool,
+    ) -> Moments {
+        let mut moments = Moments::default();
+        for y in ys {
+            for x in xs.clone() {
+                if pixel(x, y) {
+                    let (x, y) = (x as u64, y as u64);
+                    moments.m10 += x;
+                    moments.m01 += y;
+                    moments.m20 += x * x;
+                    moments.m11 += x * y;
+                    moments.m02 += y * y;
+                }
+            }
+        }
+        moments
+    }
+    fn moments<const W: usize>(img: &[[bool; W]], xs: Range<u32>, ys: Range<u32>) -> Moments {
+        sum_moments(xs, ys, |x, y| img[y as usize][x as usize])
+    }
 
     #[test]
     fn _1x1() {
@@ -34,7 +79,8 @@ mod blob {
                 max_x: 1,
                 min_y: 0,
                 max_y: 1,
-                pixels: 1
+                pixels: 1,
+                moments: moments(&img, 0..1, 0..1),
             }]
         );
     }
@@ -48,7 +94,8 @@ mod blob {
                 max_x: 2,
                 min_y: 0,
                 max_y: 2,
-                pixels: 4
+                pixels: 4,
+                moments: moments(&img, 0..2, 0..2),
             }]
         );
     }
@@ -62,7 +109,8 @@ mod blob {
                 max_x: 2,
                 min_y: 0,
                 max_y: 2,
-                pixels: 2
+                pixels: 2,
+                moments: moments(&img, 0..2, 0..2),
             }]
         );
     }
@@ -77,14 +125,16 @@ mod blob {
                     max_x: 1,
                     min_y: 0,
                     max_y: 1,
-                    pixels: 1
+                    pixels: 1,
+                    moments: moments(&img, 0..1, 0..1),
                 },
                 Blob {
                     min_x: 2,
                     max_x: 3,
                     min_y: 1,
                     max_y: 2,
-                    pixels: 1
+                    pixels: 1,
+                    moments: moments(&img, 2..3, 1..2),
                 }
             ]
         );
@@ -100,14 +150,16 @@ mod blob {
                     max_x: 1,
                     min_y: 0,
                     max_y: 1,
-                    pixels: 1
+                    pixels: 1,
+                    moments: moments(&img, 0..1, 0..1),
                 },
                 Blob {
                     min_x: 2,
                     max_x: 3,
                     min_y: 0,
                     max_y: 1,
-                    pixels: 1
+                    pixels: 1,
+                    moments: moments(&img, 2..3, 0..1),
                 }
             ]
         );
@@ -126,7 +178,8 @@ mod blob {
                 max_x: 4,
                 min_y: 0,
                 max_y: 3,
-                pixels: 6
+                pixels: 6,
+                moments: moments(&img, 0..4, 0..3),
             }]
         );
     }
@@ -144,7 +197,8 @@ mod blob {
                 max_x: 4,
                 min_y: 0,
                 max_y: 3,
-                pixels: 6
+                pixels: 6,
+                moments: moments(&img, 0..4, 0..3),
             }]
         );
     }
@@ -162,7 +216,8 @@ mod blob {
                 max_x: 12,
                 min_y: 0,
                 max_y: 2,
-                pixels: 12
+                pixels: 12,
+                moments: moments(&img, 0..12, 0..2),
             }]
         );
     }
@@ -182,6 +237,7 @@ mod blob {
                     min_y: 0,
                     max_y: 2,
                     pixels: 5,
+                    moments: moments(&img, 0..5, 0..2),
                 },
                 Blob {
                     min_x: 2,
@@ -189,12 +245,62 @@ mod blob {
                     min_y: 2,
                     max_y: 3,
                     pixels: 1,
+                    moments: moments(&img, 2..3, 2..3),
                 },
             ]
         )
     }
 
-    static FERRIS: &[u8] = include_bytes!("data/ferris.png");
+    #[test]
+    fn moments_of_shapes() {
+        let rect = BlobsIterator::new([[true; 3]; 2]).next().unwrap();
+        assert_eq!(rect.centroid(), [1.5, 1.0]);
+        let [mu20, mu11, mu02] = rect.second_moments();
+        assert_close(mu20, 2.0 / 3.0);
+        assert_close(mu11, 0.0);
+        assert_close(mu02, 0.25);
+        assert_close(rect.orientation(), 0.0);
+        assert_close(rect.eccentricity(), 0.625f64.sqrt());
+
+        let img = [
+            [true, false, false, false],
+            [false, true, false, false],
+            [false, false, true, false],
+            [false, false, false, true],
+        ];
+        let diagonal = BlobsIterator::new(img).next().unwrap();
+        assert_eq!(diagonal.centroid(), [2.0, 2.0]);
+        assert_close(diagonal.orientation(), 45.0);
+        assert_close(diagonal.eccentricity(), 1.0);
+        let flipped = BlobsIterator::new(img.map(|r| [r[3], r[2], r[1], r[0]]))
+            .next()
+            .unwrap();
+        assert_close(flipped.orientation(), -45.0);
+        let vertical = BlobsIterator::new([[false, true]; 3]).next().unwrap();
+        assert_eq!(vertical.centroid(), [1.5, 1.5]);
+        assert_close(vertical.orientation(), 90.0);
+
+        let pixel = BlobsIterator::new([[false, false], [false, true]])
+            .next()
+            .unwrap();
+        assert_eq!(pixel.centroid(), [1.5, 1.5]);
+        assert_eq!(pixel.second_moments(), [0.0; 3]);
+        assert_eq!(pixel.eccentricity(), 0.0);
+
+        // the center of this one's bounding box isn't even on it
+        let img = [
+            [true, false, false, false],
+            [true, false, false, false],
+            [true, false, false, false],
+            [true, true, true, true],
+        ];
+        let l = BlobsIterator::new(img).next().unwrap();
+        let [cx, cy] = l.centroid();
+        assert_close(cx, 6.0 / 7.0 + 0.5);
+        assert_close(cy, 15.0 / 7.0 + 0.5);
+    }
+
+    pub(super) static FERRIS: &[u8] = include_bytes!("data/ferris.png");
 
     #[test]
     fn ferris_1() {
@@ -206,6 +312,7 @@ mod blob {
                 .filter(|b| b.width() >= 10 && b.height() >= 10)
                 .collect::<Vec<_>>()
         });
+        // the moments are checked against a flood fill in contour::matches_blobs
         assert_eq!(
             blobs,
             [
@@ -214,14 +321,16 @@ mod blob {
                     max_x: 283,
                     min_y: 150,
                     max_y: 183,
-                    pixels: 542
+                    pixels: 542,
+                    ..blobs[0]
                 },
                 Blob {
                     min_x: 177,
                     max_x: 202,
                     min_y: 150,
                     max_y: 185,
-                    pixels: 568
+                    pixels: 568,
+                    ..blobs[1]
                 }
             ]
         );
@@ -244,7 +353,8 @@ mod blob {
                 max_x: 446,
                 min_y: 13,
                 max_y: 295,
-                pixels: 63627
+                pixels: 63627,
+                ..blobs[0]
             }]
         );
     }
@@ -267,3 +377,1131 @@ mod window {
         percentile_filter(img, &mut dst, 15, 15, 0);
     }
 }
+mod chessboard {
+    use super::*;
+    use crate::buffer::*;
+
+    const H: [f64; 9] = [0.9, 0.12, 110.0, -0.06, 0.95, 70.0, 0.0002, 0.0001, 1.0];
+    const SQUARE: f64 = 36.0;
+
+    fn project([x, y]: [f64; 2]) -> [f64; 2] {
+        let w = H[6] * x + H[7] * y + H[8];
+        [
+            (H[0] * x + H[1] * y + H[2]) / w,
+            (H[3] * x + H[4] * y + H[5]) / w,
+        ]
+    }
+
+    /// Render a board with `cols + 1` by `rows + 1` squares, seen through a homography.
+    fn render(cols: usize, rows: usize) -> Buffer<'static> {
+        let [a, b, c, d, e, f, g, h, i] = H;
+        // adjugate of the homography, which works as an inverse up to scale
+        let inv = [
+            e * i - f * h,
+            c * h - b * i,
+            b * f - c * e,
+            f * g - d * i,
+            a * i - c * g,
+            c * d - a * f,
+            d * h - e * g,
+            b * g - a * h,
+            a * e - b * d,
+        ];
+        let mut img = Buffer::zeroed(640, 480, PixelFormat::LUMA);
+        for py in 0..480 {
+            for px in 0..640 {
+                let mut total = 0.0;
+                for sy in 0..4 {
+                    for sx in 0..4 {
+                        // pixel centers are at integer coordinates
+                        let u = px as f64 + (sx as f64 + 0.5) / 4.0 - 0.5;
+                        let v = py as f64 + (sy as f64 + 0.5) / 4.0 - 0.5;
+                        let w = inv[6] * u + inv[7] * v + inv[8];
+                        let x = (inv[0] * u + inv[1] * v + inv[2]) / w / SQUARE;
+                        let y = (inv[3] * u + inv[4] * v + inv[5]) / w / SQUARE;
+                        let inside =
+                            x >= 0.0 && y >= 0.0 && x < (cols + 1) as f64 && y < (rows + 1) as f64;
+                        let dark = inside && (x.floor() as i64 + y.floor() as i64) % 2 == 0;
+                        total += if dark { 20.0 } else { 230.0 };
+                    }
+                }
+                img.set_pixel(px, py, &[(total / 16.0) as u8]);
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn warped_board() {
+        let (cols, rows) = (8, 6);
+        let img = render(cols, rows);
+        let found = find_chessboard_corners(img, cols, rows).expect("failed to find the board");
+        assert_eq!(found.len(), cols * rows);
+        for row in 1..=rows {
+            for col in 1..=cols {
+                let [ex, ey] = project([col as f64 * SQUARE, row as f64 * SQUARE]);
+                let closest = found
+                    .iter()
+                    .map(|[x, y]| (x - ex).hypot(y - ey))
+                    .fold(f64::INFINITY, f64::min);
+                assert!(
+                    closest < 0.1,
+                    "corner at ({ex:.2}, {ey:.2}) was off by {closest:.3}px"
+                );
+            }
+        }
+        // neighbors in the output should be neighbors on the board
+        for pair in found.chunks(cols).flat_map(|row| row.windows(2)) {
+            let dist = (pair[0][0] - pair[1][0]).hypot(pair[0][1] - pair[1][1]);
+            assert!(dist > SQUARE * 0.6 && dist < SQUARE * 1.4);
+        }
+    }
+
+    #[test]
+    fn wrong_size() {
+        let img = render(8, 6);
+        assert!(find_chessboard_corners(img, 7, 6).is_none());
+    }
+}
+mod remap {
+    use super::*;
+    use crate::buffer::*;
+
+    fn gradient() -> Buffer<'static> {
+        let mut img = Buffer::zeroed(8, 6, PixelFormat::RGB);
+        for y in 0..6 {
+            for x in 0..8 {
+                img.set_pixel(x, y, &[x as u8 * 20, y as u8 * 30, 7]);
+            }
+        }
+        img
+    }
+
+    #[test]
+    fn identity() {
+        let img = gradient();
+        let map = RemapMap::from_fn(8, 6, 8, 6, |x, y| [x as f64, y as f64]);
+        for interp in [Interpolation::Nearest, Interpolation::Bilinear] {
+            let mut out = Buffer::empty_rgb();
+            remap(img.borrow(), &mut out, &map, interp);
+            assert_eq!(out, img);
+        }
+    }
+
+    #[test]
+    fn half_pixel_shift() {
+        let img = gradient();
+        let map = RemapMap::from_fn(7, 6, 8, 6, |x, y| [x as f64 + 0.5, y as f64]);
+        let mut out = Buffer::empty_rgb();
+        remap(img.borrow(), &mut out, &map, Interpolation::Bilinear);
+        assert_eq!(out.pixel(0, 0), Some(&[10, 0, 7][..]));
+        assert_eq!(out.pixel(3, 2), Some(&[70, 60, 7][..]));
+        remap(img.borrow(), &mut out, &map, Interpolation::Nearest);
+        assert_eq!(out.pixel(3, 2), Some(&[80, 60, 7][..]));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let img = gradient();
+        let map = RemapMap::from_fn(8, 6, 8, 6, |x, y| [x as f64 - 4.0, y as f64]);
+        let mut out = Buffer::empty_rgb();
+        remap(img.borrow(), &mut out, &map, Interpolation::Bilinear);
+        assert_eq!(out.pixel(0, 0), Some(&[0, 0, 0][..]));
+        assert_eq!(out.pixel(5, 1), Some(&[20, 30, 7][..]));
+        assert_eq!(map.get(0, 0), None);
+    }
+
+    #[test]
+    fn empty_source() {
+        let img = Buffer::zeroed(0, 0, PixelFormat::RGB);
+        let map = RemapMap::from_fn(4, 3, 0, 0, |x, y| [x as f64, y as f64]);
+        for interp in [Interpolation::Nearest, Interpolation::Bilinear] {
+            let mut out = Buffer::empty_rgb();
+            remap(img.borrow(), &mut out, &map, interp);
+            assert_eq!(out, Buffer::zeroed(4, 3, PixelFormat::RGB));
+        }
+    }
+}
+
+mod resize {
+    use super::*;
+    use crate::buffer::*;
+
+    fn luma(width: u32, height: u32, data: Vec<u8>) -> Buffer<'static> {
+        Buffer {
+            width,
+            height,
+            format: PixelFormat::LUMA,
+            data: data.into(),
+        }
+    }
+
+    fn resized(img: &Buffer, width: u32, height: u32, mode: Interpolation) -> Vec<u8> {
+        let mut out = Buffer::empty_rgb();
+        resize(img.borrow(), &mut out, width, height, mode);
+        out.assert_sized_correctly();
+        assert_eq!((out.width, out.height), (width, height));
+        out.data.into_owned()
+    }
+
+    #[test]
+    fn checkerboard() {
+        let data = (0..64).map(|i| if (i + i / 8) % 2 == 0 { 255 } else { 0 });
+        let img = luma(8, 8, data.collect());
+        // nearest-neighbor only ever sees the white squares, and area averaging sees all of them
+        assert_eq!(resized(&img, 4, 4, Interpolation::Nearest), [255; 16]);
+        assert_eq!(resized(&img, 4, 4, Interpolation::Area), [128; 16]);
+        assert_eq!(resized(&img, 2, 2, Interpolation::Area), [128; 4]);
+    }
+
+    #[test]
+    fn area_fractional() {
+        let img = luma(3, 1, vec![0, 90, 180]);
+        assert_eq!(resized(&img, 2, 1, Interpolation::Area), [30, 150]);
+        assert_eq!(resized(&img, 1, 1, Interpolation::Area), [90]);
+        let img = luma(1, 3, vec![0, 90, 180]);
+        assert_eq!(resized(&img, 1, 2, Interpolation::Area), [30, 150]);
+    }
+
+    #[test]
+    fn bilinear() {
+        let img = luma(2, 1, vec![0, 100]);
+        assert_eq!(
+            resized(&img, 4, 1, Interpolation::Bilinear),
+            [0, 25, 75, 100]
+        );
+        let img = luma(2, 2, vec![0, 100, 100, 200]);
+        assert_eq!(
+            resized(&img, 4, 4, Interpolation::Bilinear),
+            [
+                0, 25, 75, 100, //
+                25, 50, 100, 125, //
+                75, 100, 150, 175, //
+                100, 125, 175, 200,
+            ]
+        );
+    }
+
+    #[test]
+    fn same_size() {
+        let img = luma(3, 2, vec![1, 2, 3, 4, 5, 6]);
+        for mode in [
+            Interpolation::Nearest,
+            Interpolation::Bilinear,
+            Interpolation::Area,
+        ] {
+            assert_eq!(resized(&img, 3, 2, mode), [1, 2, 3, 4, 5, 6], "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn channels() {
+        let img = Buffer {
+            width: 2,
+            height: 1,
+            format: PixelFormat::RGB,
+            data: vec![0, 10, 200, 100, 30, 0].into(),
+        };
+        let mut out = Buffer::empty_rgb();
+        resize(img.borrow(), &mut out, 1, 1, Interpolation::Area);
+        assert_eq!(out.format, PixelFormat::RGB);
+        assert_eq!(&*out.data, [50, 20, 100]);
+        let img = Buffer {
+            width: 4,
+            height: 1,
+            format: PixelFormat::YUYV,
+            data: vec![0, 100, 20, 200, 40, 0, 60, 100].into(),
+        };
+        resize(img.borrow(), &mut out, 2, 1, Interpolation::Area);
+        assert_eq!(&*out.data, [20, 50, 40, 150]);
+    }
+}
+mod orient {
+    use super::*;
+
+    fn small() -> Buffer<'static> {
+        Buffer {
+            width: 3,
+            height: 2,
+            format: PixelFormat::LUMA,
+            data: vec![1, 2, 3, 4, 5, 6].into(),
+        }
+    }
+
+    fn oriented(rotate: Rotation, flip: Flip, crop: Option<CropRect>) -> (u32, u32, Vec<u8>) {
+        let orientation = Orientation { rotate, flip, crop };
+        let mut out = Buffer::empty_rgb();
+        orient(small(), &mut out, orientation);
+        out.assert_sized_correctly();
+        assert_eq!(
+            orientation.output_size(3, 2),
+            (out.width, out.height),
+            "output size mismatch"
+        );
+        (out.width, out.height, out.data.into_owned())
+    }
+
+    #[test]
+    fn rotate() {
+        assert_eq!(
+            oriented(Rotation::None, Flip::None, None),
+            (3, 2, vec![1, 2, 3, 4, 5, 6])
+        );
+        assert_eq!(
+            oriented(Rotation::Cw90, Flip::None, None),
+            (2, 3, vec![4, 1, 5, 2, 6, 3])
+        );
+        assert_eq!(
+            oriented(Rotation::Cw180, Flip::None, None),
+            (3, 2, vec![6, 5, 4, 3, 2, 1])
+        );
+        assert_eq!(
+            oriented(Rotation::Cw270, Flip::None, None),
+            (2, 3, vec![3, 6, 2, 5, 1, 4])
+        );
+    }
+
+    #[test]
+    fn flip() {
+        assert_eq!(
+            oriented(Rotation::None, Flip::Horizontal, None),
+            (3, 2, vec![3, 2, 1, 6, 5, 4])
+        );
+        assert_eq!(
+            oriented(Rotation::None, Flip::Vertical, None),
+            (3, 2, vec![4, 5, 6, 1, 2, 3])
+        );
+        assert_eq!(
+            oriented(Rotation::None, Flip::Both, None),
+            oriented(Rotation::Cw180, Flip::None, None)
+        );
+        // flipping happens after rotating
+        assert_eq!(
+            oriented(Rotation::Cw90, Flip::Vertical, None),
+            (2, 3, vec![6, 3, 5, 2, 4, 1])
+        );
+    }
+
+    #[test]
+    fn crop() {
+        let crop = |x, y, width, height| {
+            Some(CropRect {
+                x,
+                y,
+                width,
+                height,
+            })
+        };
+        assert_eq!(
+            oriented(Rotation::None, Flip::None, crop(1, 0, 2, 2)),
+            (2, 2, vec![2, 3, 5, 6])
+        );
+        // the crop is in rotated coordinates, and is clipped to the image
+        assert_eq!(
+            oriented(Rotation::Cw90, Flip::None, crop(1, 1, 5, 5)),
+            (1, 2, vec![2, 3])
+        );
+        assert_eq!(
+            oriented(Rotation::None, Flip::None, crop(10, 10, 5, 5)),
+            (0, 0, vec![])
+        );
+    }
+
+    #[test]
+    fn yuyv() {
+        let src = Buffer {
+            width: 2,
+            height: 1,
+            format: PixelFormat::YUYV,
+            data: vec![10, 20, 30, 40].into(),
+        };
+        let mut out = Buffer::empty_rgb();
+        let orientation = Orientation {
+            flip: Flip::Horizontal,
+            ..Default::default()
+        };
+        orient(src, &mut out, orientation);
+        assert_eq!(out.format, PixelFormat::YUYV);
+        assert_eq!(*out.data, [30, 20, 10, 40]);
+    }
+}
+mod contour {
+    use super::*;
+    use crate::buffer::*;
+    use crate::draw::Drawable;
+
+    /// Make a LUMA image from rows of `#` and `.`.
+    pub(super) fn mask(rows: &[&str]) -> Buffer<'static> {
+        let data = rows
+            .iter()
+            .flat_map(|r| r.bytes().map(|b| if b == b'#' { 255 } else { 0 }))
+            .collect::<Vec<u8>>();
+        Buffer {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            format: PixelFormat::LUMA,
+            data: data.into(),
+        }
+    }
+
+    #[test]
+    fn square() {
+        let img = mask(&[".....", ".###.", ".###.", ".###.", "....."]);
+        let contours = find_contours(&img);
+        assert_eq!(contours.len(), 1);
+        let contour = &contours[0];
+        assert_eq!(
+            contour.points,
+            [
+                [1, 1],
+                [1, 2],
+                [1, 3],
+                [2, 3],
+                [3, 3],
+                [3, 2],
+                [3, 1],
+                [2, 1]
+            ]
+        );
+        assert_eq!(
+            contour.blob,
+            Blob {
+                min_x: 1,
+                max_x: 4,
+                min_y: 1,
+                max_y: 4,
+                pixels: 9,
+                moments: blob::sum_moments(1..4, 1..4, |_, _| true),
+            }
+        );
+        assert_eq!(contour.perimeter(), 8.0);
+        assert_eq!(contour.area(), 4.0);
+        let simplified = contour.simplify(0.5);
+        assert_eq!(simplified.points, [[1, 1], [1, 3], [3, 3], [3, 1]]);
+        assert_eq!(simplified.area(), 4.0);
+    }
+
+    #[test]
+    fn single_pixel() {
+        let img = mask(&["...", ".#.", "..."]);
+        let contours = find_contours(&img);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].points, [[1, 1]]);
+        assert_eq!(contours[0].perimeter(), 0.0);
+        assert_eq!(contours[0].simplify(1.0).points, [[1, 1]]);
+    }
+
+    #[test]
+    fn line() {
+        let img = mask(&["####"]);
+        let contours = find_contours(&img);
+        assert_eq!(
+            contours[0].points,
+            [[0, 0], [1, 0], [2, 0], [3, 0], [2, 0], [1, 0]]
+        );
+        assert_eq!(contours[0].perimeter(), 6.0);
+        assert_eq!(contours[0].simplify(0.5).points, [[0, 0], [3, 0]]);
+    }
+
+    #[test]
+    fn holes_and_regions() {
+        let img = mask(&[
+            "#####...", //
+            "#...#...", //
+            "#...#..#", //
+            "#####..#", //
+            "......#.", //
+        ]);
+        let contours = find_contours(&img);
+        assert_eq!(contours.len(), 2);
+        assert_eq!(contours[0].blob.pixels, 14);
+        assert_eq!(contours[0].points.len(), 14);
+        // the diagonal makes these a single region
+        assert_eq!(contours[1].blob.pixels, 3);
+        assert_eq!(contours[1].points, [[7, 2], [7, 3], [6, 4], [7, 3]]);
+    }
+
+    #[test]
+    fn l_shape() {
+        let img = mask(&[
+            "##....", //
+            "##....", //
+            "##....", //
+            "######", //
+            "######", //
+        ]);
+        let contours = find_contours(&img);
+        let simplified = contours[0].simplify(0.5);
+        // the boundary cuts diagonally across the inside corner
+        assert_eq!(
+            simplified.points,
+            [[0, 0], [0, 4], [5, 4], [5, 3], [2, 3], [1, 2], [1, 0]]
+        );
+    }
+
+    #[test]
+    fn rotated_rect() {
+        // a 40x20 rectangle rotated by about 30 degrees
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        let mut img = Buffer::monochrome(80, 80, PixelFormat::LUMA, &[0]);
+        for y in 0..80 {
+            for x in 0..80 {
+                let (dx, dy) = (x as f64 - 40.0, y as f64 - 40.0);
+                let u = dx * cos + dy * sin;
+                let v = -dx * sin + dy * cos;
+                if u.abs() <= 20.0 && v.abs() <= 10.0 {
+                    img.set_pixel(x, y, &[255]);
+                }
+            }
+        }
+        let contours = find_contours(&img);
+        assert_eq!(contours.len(), 1);
+        assert_eq!(contours[0].simplify(2.0).points.len(), 4);
+        let area = contours[0].area();
+        assert!((area - 800.0).abs() < 80.0, "area: {area}");
+    }
+
+    #[test]
+    fn matches_blobs() {
+        let img = Buffer::decode_png_data(blob::FERRIS).unwrap();
+        let mut buf = Buffer::empty_rgb();
+        swizzle(img.borrow(), &mut buf, &[2]);
+        let mut blobs = BlobsIterator::from_buffer(&buf).collect::<Vec<_>>();
+        let mut contours = find_contours(&buf)
+            .into_iter()
+            .map(|c| c.blob)
+            .collect::<Vec<_>>();
+        let key = |b: &Blob| (b.min_y, b.min_x, b.max_y, b.max_x, b.pixels);
+        blobs.sort_by_key(key);
+        contours.sort_by_key(key);
+        assert_eq!(blobs, contours);
+    }
+
+    #[test]
+    fn draw() {
+        let img = mask(&[".....", ".###.", ".###.", ".###.", "....."]);
+        let contour = find_contours(&img).remove(0);
+        let mut canvas = Buffer::monochrome(5, 5, PixelFormat::LUMA, &[0]);
+        contour.draw(&[255], &mut canvas);
+        assert_eq!(canvas, mask(&[".....", ".###.", ".#.#.", ".###.", "....."]));
+    }
+}
+mod shape {
+    use super::*;
+    use crate::buffer::*;
+    use crate::draw::Drawable;
+
+    #[test]
+    fn hull() {
+        let points = [
+            [0, 0],
+            [0, 4],
+            [5, 4],
+            [5, 3],
+            [2, 3],
+            [1, 2],
+            [1, 0],
+            [2, 2],
+        ];
+        assert_eq!(
+            convex_hull(&points),
+            [[0, 0], [0, 4], [5, 4], [5, 3], [1, 0]]
+        );
+        // collinear and repeated points
+        assert_eq!(
+            convex_hull(&[[0, 0], [2, 2], [1, 1], [2, 2]]),
+            [[0, 0], [2, 2]]
+        );
+        assert_eq!(convex_hull(&[[3, 3]]), [[3, 3]]);
+        assert!(convex_hull(&[]).is_empty());
+        assert_close(
+            polygon_area(&[[0, 0], [0, 4], [5, 4], [5, 3], [1, 0]]),
+            14.0,
+        );
+        assert_close(polygon_perimeter(&[[0, 0], [0, 3], [4, 3]]), 12.0);
+    }
+
+    #[test]
+    fn rotated_rect() {
+        let rect = RotatedRect {
+            center: [50.0, 40.0],
+            width: 40.0,
+            height: 20.0,
+            angle: 30.0,
+        };
+        let corners = rect.corners().map(|c| c.map(|v| v.round() as u32)).to_vec();
+        let found = min_area_rect(&convex_hull(&corners));
+        assert!((found.center[0] - 50.0).abs() < 1.0, "{found:?}");
+        assert!((found.center[1] - 40.0).abs() < 1.0, "{found:?}");
+        assert!((found.width - 40.0).abs() < 1.0, "{found:?}");
+        assert!((found.height - 20.0).abs() < 1.0, "{found:?}");
+        assert!((found.angle - 30.0).abs() < 2.0, "{found:?}");
+        let line = min_area_rect(&[[0, 5], [10, 5]]);
+        assert_close(line.width, 10.0);
+        assert_close(line.height, 0.0);
+        assert_close(line.angle, 0.0);
+        assert_eq!(line.aspect(), f64::INFINITY);
+        // tall rectangles are measured along their long side
+        let tall = min_area_rect(&[[0, 0], [0, 10], [2, 10], [2, 0]]);
+        assert_close(tall.width, 10.0);
+        assert_close(tall.height, 2.0);
+        assert_close(tall.angle.abs(), 90.0);
+    }
+
+    #[test]
+    fn enclosing_circle() {
+        let circle = min_enclosing_circle(&[[0, 0], [4, 0], [4, 4], [0, 4], [2, 2], [1, 3]]);
+        assert_close(circle.center[0], 2.0);
+        assert_close(circle.center[1], 2.0);
+        assert_close(circle.radius, 8f64.sqrt());
+        // an obtuse triangle's circle goes through just the longest side
+        let circle = min_enclosing_circle(&[[0, 0], [10, 0], [5, 1]]);
+        assert_close(circle.center[0], 5.0);
+        assert_close(circle.center[1], 0.0);
+        assert_close(circle.radius, 5.0);
+        let circle = min_enclosing_circle(&[[0, 0], [3, 3], [6, 6]]);
+        assert_close(circle.radius, 18f64.sqrt());
+        // every point is inside, for something bigger
+        let points = (0..200u32)
+            .map(|i| [(i * 37) % 101, (i * 59) % 83])
+            .collect::<Vec<_>>();
+        let circle = min_enclosing_circle(&points);
+        for p in &points {
+            let [x, y] = p.map(f64::from);
+            assert!((x - circle.center[0]).hypot(y - circle.center[1]) <= circle.radius + 1e-6);
+        }
+    }
+
+    #[test]
+    fn descriptors() {
+        let square = Contour {
+            points: vec![[1, 1], [1, 3], [3, 3], [3, 1]],
+            blob: Blob {
+                min_x: 1,
+                max_x: 4,
+                min_y: 1,
+                max_y: 4,
+                pixels: 9,
+                moments: blob::sum_moments(1..4, 1..4, |_, _| true),
+            },
+        };
+        assert_close(square.solidity(), 1.0);
+        assert_close(square.convexity(), 1.0);
+        let rect = square.min_area_rect();
+        assert_close(rect.area(), 4.0);
+        let l = Contour {
+            points: vec![[0, 0], [0, 4], [5, 4], [5, 3], [2, 3], [1, 2], [1, 0]],
+            ..square
+        };
+        assert_close(l.solidity(), 8.5 / 14.0);
+        assert!(l.convexity() < 1.0);
+        assert_close(fill_ratio(0.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn draw() {
+        let mut canvas = Buffer::monochrome(7, 7, PixelFormat::LUMA, &[0]);
+        let circle = Circle {
+            center: [3.0, 3.0],
+            radius: 2.0,
+        };
+        circle.draw(&[255], &mut canvas);
+        for [x, y] in [[5, 3], [1, 3], [3, 5], [3, 1]] {
+            assert_eq!(canvas.pixel(x, y), Some(&[255][..]));
+        }
+        assert_eq!(canvas.pixel(3, 3), Some(&[0][..]));
+        let mut canvas = Buffer::monochrome(5, 5, PixelFormat::LUMA, &[0]);
+        let rect = RotatedRect {
+            center: [2.0, 2.0],
+            width: 2.0,
+            height: 2.0,
+            angle: 0.0,
+        };
+        rect.draw(&[255], &mut canvas);
+        let lit = canvas.data.iter().filter(|&&v| v == 255).count();
+        assert_eq!(lit, 8);
+        assert_eq!(canvas.pixel(2, 2), Some(&[0][..]));
+    }
+}
+mod edges {
+    use super::*;
+    use crate::buffer::*;
+
+    fn edges(img: &Buffer, low: f32, high: f32) -> Vec<u8> {
+        let mut out = Buffer::empty_rgb();
+        canny(img.borrow(), &mut out, low, high, GradientKernel::Sobel);
+        out.assert_sized_correctly();
+        assert_eq!(out.format, PixelFormat::LUMA);
+        out.data.into_owned()
+    }
+
+    #[test]
+    fn step() {
+        let img = luma(6, 3, |x, _| if x < 3 { 0 } else { 200 });
+        for kernel in [GradientKernel::Sobel, GradientKernel::Scharr] {
+            let mut out = Buffer::empty_rgb();
+            sobel(img.borrow(), &mut out, kernel);
+            assert_eq!(out.format, PixelFormat::LUMA);
+            assert_eq!(&*out.data, [[0, 0, 200, 200, 0, 0]; 3].as_flattened());
+        }
+    }
+
+    #[test]
+    fn gradients() {
+        let img = luma(3, 4, |_, y| if y < 2 { 50 } else { 10 });
+        let grads = Gradients::new(img.borrow(), GradientKernel::Scharr);
+        assert_eq!(grads.dx, [0; 12]);
+        assert_eq!(grads.dy[3..9], [-640; 6]);
+        assert_eq!(grads.magnitude(1, 1), Some(40.0));
+        assert_eq!(grads.magnitude(1, 0), Some(0.0));
+        assert_eq!(grads.magnitude(3, 0), None);
+        assert_close(
+            grads.direction(1, 2).unwrap() as f64,
+            -std::f64::consts::FRAC_PI_2,
+        );
+        // color images are converted to luma first
+        let rgb = img.convert(PixelFormat::RGB);
+        assert_eq!(Gradients::new(rgb.borrow(), GradientKernel::Scharr), grads);
+    }
+
+    #[test]
+    fn square() {
+        let img = luma(12, 12, |x, y| {
+            if (3..8).contains(&x) && (3..8).contains(&y) {
+                255
+            } else {
+                0
+            }
+        });
+        let out = edges(&img, 50.0, 100.0);
+        let edge = |x: u32, y: u32| out[(y * 12 + x) as usize] == 255;
+        // edges are one pixel wide, on one side or the other of each side of the square
+        for y in 4..7 {
+            let row = (0..12).filter(|&x| edge(x, y)).collect::<Vec<_>>();
+            assert_eq!(row.len(), 2, "row {y}: {row:?}");
+            assert!((2..=3).contains(&row[0]) && (7..=8).contains(&row[1]));
+        }
+        for x in 4..7 {
+            let col = (0..12).filter(|&y| edge(x, y)).collect::<Vec<_>>();
+            assert_eq!(col.len(), 2, "column {x}: {col:?}");
+        }
+        // and nothing is far from the square
+        for y in 0..12 {
+            for x in 0..12 {
+                if edge(x, y) {
+                    assert!((2..=8).contains(&x) && (2..=8).contains(&y));
+                    assert!(!((4..7).contains(&x) && (4..7).contains(&y)));
+                }
+            }
+        }
+        assert_eq!(edges(&luma(12, 12, |_, _| 100), 0.0, 0.0), [0; 144]);
+    }
+
+    #[test]
+    fn hysteresis() {
+        // a vertical edge that's strong at the top and weak at the bottom
+        let img = luma(8, 10, |x, y| match (x < 4, y < 3) {
+            (true, _) => 0,
+            (false, true) => 200,
+            (false, false) => 60,
+        });
+        // rows that have an edge along the vertical one
+        let rows = |out: &[u8]| {
+            (0..10)
+                .filter(|&y| out[y * 8 + 3] == 255 || out[y * 8 + 4] == 255)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(&edges(&img, 40.0, 150.0)), (0..10).collect::<Vec<_>>());
+        // without the high threshold being met somewhere, nothing is kept
+        assert_eq!(edges(&img, 40.0, 250.0), [0; 80]);
+        // and when the weak part is below the low threshold, only the strong part is kept
+        assert_eq!(rows(&edges(&img, 100.0, 150.0)), [0, 1, 2, 3]);
+    }
+}
+mod hough {
+    use super::*;
+    use crate::buffer::*;
+    use crate::draw::Line;
+
+    /// Make a binary image, with 255 where `f` is true and 0 elsewhere.
+    fn binary(width: u32, height: u32, f: impl Fn(u32, u32) -> bool) -> Buffer<'static> {
+        luma(width, height, |x, y| if f(x, y) { 255 } else { 0 })
+    }
+
+    /// Get the endpoints of lines, with the endpoints of each one in order.
+    fn endpoints(lines: Vec<Line>) -> Vec<[[u32; 2]; 2]> {
+        lines
+            .into_iter()
+            .map(|Line { x0, y0, x1, y1 }| {
+                let mut ends = [[x0, y0], [x1, y1]];
+                ends.sort();
+                ends
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lines() {
+        let img = binary(20, 16, |x, y| y == 5 || x == 12);
+        let mut lines = endpoints(hough_lines(img.borrow(), &HoughLineParams::new(12)));
+        lines.sort();
+        assert_eq!(lines, [[[0, 5], [19, 5]], [[12, 0], [12, 15]]]);
+        // the horizontal line has more pixels on it
+        let params = HoughLineParams {
+            max_lines: 1,
+            ..HoughLineParams::new(12)
+        };
+        assert_eq!(
+            endpoints(hough_lines(img.borrow(), &params)),
+            [[[0, 5], [19, 5]]]
+        );
+        assert_eq!(hough_lines(img.borrow(), &HoughLineParams::new(21)), []);
+    }
+
+    #[test]
+    fn diagonal() {
+        let img = binary(16, 16, |x, y| x == y);
+        let lines = endpoints(hough_lines(img.borrow(), &HoughLineParams::new(10)));
+        assert_eq!(lines, [[[0, 0], [15, 15]]]);
+    }
+
+    #[test]
+    fn segments() {
+        let img = binary(20, 10, |x, y| {
+            y == 4 && ((2..8).contains(&x) || (11..17).contains(&x))
+        });
+        let segments = |max_gap: f64, min_length: f64| {
+            let params = HoughLineParams {
+                max_gap: Some(max_gap),
+                min_length,
+                ..HoughLineParams::new(10)
+            };
+            let mut lines = endpoints(hough_lines(img.borrow(), &params));
+            lines.sort();
+            lines
+        };
+        assert_eq!(segments(2.0, 0.0), [[[2, 4], [7, 4]], [[11, 4], [16, 4]]]);
+        assert_eq!(segments(3.0, 0.0), [[[2, 4], [16, 4]]]);
+        assert!(segments(2.0, 6.0).is_empty());
+    }
+
+    #[test]
+    fn circles() {
+        // a bright disk and a dark disk, with a square that shouldn't be detected
+        let img = binary(90, 60, |x, y| {
+            let (x, y) = (x as f64, y as f64);
+            (x - 20.0).hypot(y - 30.0) <= 12.0
+                || ((40.0..65.0).contains(&x) && (18.0..43.0).contains(&y))
+                || (x >= 66.0 && (x - 78.0).hypot(y - 20.0) > 9.0)
+        });
+        let params = HoughCircleParams {
+            min_radius: 6.0,
+            max_radius: 16.0,
+            threshold: 10,
+            min_coverage: 0.5,
+            min_distance: None,
+            low: 20.0,
+            high: 60.0,
+            kernel: GradientKernel::Sobel,
+            max_circles: usize::MAX,
+        };
+        let mut circles = hough_circles(img.borrow(), &params);
+        circles.sort_by(|a, b| a.center[0].total_cmp(&b.center[0]));
+        assert_eq!(circles.len(), 2, "{circles:?}");
+        assert_eq!(circles[0].center, [20.0, 30.0]);
+        assert!((circles[0].radius - 12.0).abs() < 0.5, "{circles:?}");
+        assert_eq!(circles[1].center, [78.0, 20.0]);
+        assert!((circles[1].radius - 9.0).abs() < 0.5, "{circles:?}");
+        // huge radii are limited to the image, rather than allocating for every radius up to them
+        let at_most = |max_radius| {
+            let params = HoughCircleParams {
+                max_radius,
+                ..params
+            };
+            hough_circles(img.borrow(), &params)
+        };
+        assert_eq!(at_most(1e12), at_most(200.0));
+        // radii outside the range aren't detected
+        let params = HoughCircleParams {
+            min_radius: 13.0,
+            ..params
+        };
+        assert_eq!(hough_circles(img.borrow(), &params), []);
+        // without a minimum coverage, every circle still has a real radius
+        let params = HoughCircleParams {
+            threshold: 1,
+            min_coverage: 0.0,
+            ..params
+        };
+        let circles = hough_circles(img.borrow(), &params);
+        assert!(!circles.is_empty());
+        assert!(circles.iter().all(|c| c.radius.is_finite()), "{circles:?}");
+    }
+}
+mod threshold {
+    use super::*;
+    use crate::buffer::*;
+
+    /// Get the pixels that are set in a mask.
+    fn set(mask: &Buffer) -> Vec<[u32; 2]> {
+        assert_eq!(mask.format, PixelFormat::LUMA);
+        mask.assert_sized_correctly();
+        (0..mask.height)
+            .flat_map(|y| (0..mask.width).map(move |x| [x, y]))
+            .filter(|&[x, y]| mask.pixel(x, y) == Some(&[255]))
+            .collect()
+    }
+
+    #[test]
+    fn otsu() {
+        // two noisy classes, around 60 and 180
+        let img = luma(16, 16, |x, y| {
+            let noise = ((x * 7 + y * 13) % 21) as u8;
+            if x < 6 { 50 + noise } else { 170 + noise }
+        });
+        let level = otsu_level(img.borrow());
+        assert!((70..170).contains(&level), "{level}");
+        let mut mask = Buffer::empty_rgb();
+        assert_eq!(otsu_threshold(img.borrow(), &mut mask, false), level);
+        assert!(set(&mask).iter().all(|&[x, _]| x >= 6));
+        assert_eq!(set(&mask).len(), 10 * 16);
+        otsu_threshold(img.borrow(), &mut mask, true);
+        assert_eq!(set(&mask).len(), 6 * 16);
+        // color images are converted first
+        let rgb = img.convert(PixelFormat::RGB);
+        assert_eq!(otsu_level(rgb.borrow()), level);
+        // a uniform image is all one class, whatever its brightness
+        let img = luma(4, 4, |_, _| 200);
+        assert_eq!(otsu_level(img.borrow()), 200);
+        assert_eq!(otsu_threshold(img.borrow(), &mut mask, false), 200);
+        assert!(set(&mask).is_empty());
+        assert_eq!(otsu_level(luma(0, 0, |_, _| 0).borrow()), 0);
+    }
+
+    #[test]
+    fn fixed() {
+        let img = luma(4, 1, |x, _| x as u8 * 50);
+        let mut mask = Buffer::empty_rgb();
+        threshold(img.borrow(), &mut mask, 50, false);
+        assert_eq!(&*mask.data, [0, 0, 255, 255]);
+        threshold(img.borrow(), &mut mask, 50, true);
+        assert_eq!(&*mask.data, [255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn adaptive() {
+        // bright spots on a background that gets brighter across the image, which no global threshold can separate
+        let spots = [[4, 4], [12, 10], [24, 6]];
+        let img = luma(32, 16, |x, y| {
+            let spot = if spots.contains(&[x, y]) { 40 } else { 0 };
+            (x * 6) as u8 + spot
+        });
+        for method in [AdaptiveMethod::Mean, AdaptiveMethod::Gaussian] {
+            let mut mask = Buffer::empty_rgb();
+            adaptive_threshold(img.borrow(), &mut mask, method, 7, 15, false);
+            let mut found = set(&mask);
+            found.sort_by_key(|&[x, y]| (y, x));
+            assert_eq!(found, [[4, 4], [24, 6], [12, 10]], "{method:?}");
+        }
+        // and dark spots with the inverted mask
+        let img = luma(32, 16, |x, y| {
+            let spot = if spots.contains(&[x, y]) { 40 } else { 0 };
+            (x * 6) as u8 + 50 - spot
+        });
+        let mut mask = Buffer::empty_rgb();
+        adaptive_threshold(img.borrow(), &mut mask, AdaptiveMethod::Mean, 7, 15, true);
+        assert_eq!(set(&mask).len(), 3);
+    }
+}
+mod morph {
+    use super::contour::mask;
+    use super::*;
+    use crate::buffer::*;
+
+    /// A noisy multi-channel image.
+    fn noise(width: u32, height: u32) -> Buffer<'static> {
+        let data = (0..(width * height * 3))
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect::<Vec<u8>>();
+        Buffer {
+            width,
+            height,
+            format: PixelFormat::RGB,
+            data: data.into(),
+        }
+    }
+
+    /// Erode or dilate by checking every pixel in the structuring element.
+    fn brute_force(
+        src: &Buffer,
+        shape: ElementShape,
+        width: usize,
+        height: usize,
+        max: bool,
+    ) -> Vec<u8> {
+        let (rx, ry) = ((width / 2) as isize, (height / 2) as isize);
+        let mut out = Vec::new();
+        for y in 0..src.height as isize {
+            for x in 0..src.width as isize {
+                for c in 0..src.format.pixel_size() {
+                    let vals = (-ry..=ry)
+                        .flat_map(|dy| (-rx..=rx).map(move |dx| (dx, dy)))
+                        .filter(|&(dx, dy)| shape.contains(width, height, dx, dy))
+                        .filter_map(|(dx, dy)| {
+                            src.pixel((x + dx).try_into().ok()?, (y + dy).try_into().ok()?)
+                        })
+                        .map(|px| px[c]);
+                    out.push(if max { vals.max() } else { vals.min() }.unwrap());
+                }
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn shapes() {
+        let rows = |shape: ElementShape, width: usize, height: usize| {
+            let (rx, ry) = ((width / 2) as isize, (height / 2) as isize);
+            (-ry..=ry)
+                .map(|dy| {
+                    (-rx..=rx)
+                        .map(|dx| {
+                            if shape.contains(width, height, dx, dy) {
+                                '#'
+                            } else {
+                                '.'
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(ElementShape::Rect, 3, 1), ["###"]);
+        assert_eq!(rows(ElementShape::Cross, 3, 3), [".#.", "###", ".#."]);
+        assert_eq!(
+            rows(ElementShape::Ellipse, 5, 5),
+            ["..#..", "#####", "#####", "#####", "..#.."]
+        );
+        assert_eq!(
+            rows(ElementShape::Ellipse, 7, 5),
+            ["...#...", "#######", "#######", "#######", "...#..."]
+        );
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let img = noise(13, 10);
+        let sizes = [(1, 1), (3, 3), (5, 3), (1, 5), (7, 9), (15, 1), (21, 21)];
+        for shape in [
+            ElementShape::Rect,
+            ElementShape::Cross,
+            ElementShape::Ellipse,
+        ] {
+            for (width, height) in sizes {
+                let mut out = Buffer::empty_rgb();
+                erode(img.borrow(), &mut out, shape, width, height);
+                out.assert_sized_correctly();
+                assert_eq!(out.format, PixelFormat::RGB);
+                assert_eq!(
+                    &*out.data,
+                    brute_force(&img, shape, width, height, false),
+                    "erode {shape:?} {width}x{height}"
+                );
+                dilate(img.borrow(), &mut out, shape, width, height);
+                assert_eq!(
+                    &*out.data,
+                    brute_force(&img, shape, width, height, true),
+                    "dilate {shape:?} {width}x{height}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn matches_percentile() {
+        let img = noise(17, 11);
+        for (width, height) in [(3, 3), (5, 7), (9, 1)] {
+            let mut fast = Buffer::empty_rgb();
+            let mut slow = Buffer::empty_rgb();
+            erode(img.borrow(), &mut fast, ElementShape::Rect, width, height);
+            percentile_filter(img.borrow(), &mut slow, width, height, 0);
+            assert_eq!(fast.data, slow.data);
+            dilate(img.borrow(), &mut fast, ElementShape::Rect, width, height);
+            percentile_filter(img.borrow(), &mut slow, width, height, width * height - 1);
+            assert_eq!(fast.data, slow.data);
+        }
+    }
+
+    #[test]
+    fn compound() {
+        let img = mask(&[
+            "............",
+            "............",
+            "..####......",
+            "..####...#..",
+            "..####......",
+            "..##.#......",
+            "............",
+            "............",
+        ]);
+        let run = |op| {
+            let mut out = Buffer::empty_rgb();
+            morphology(img.borrow(), &mut out, op, ElementShape::Rect, 3, 3);
+            out
+        };
+        // opening removes the speck and the notched corner
+        assert_eq!(
+            run(MorphOp::Open),
+            mask(&[
+                "............",
+                "............",
+                "..####......",
+                "..####......",
+                "..####......",
+                "............",
+                "............",
+                "............",
+            ])
+        );
+        // closing fills the notch
+        assert_eq!(
+            run(MorphOp::Close),
+            mask(&[
+                "............",
+                "............",
+                "..####......",
+                "..####...#..",
+                "..####......",
+                "..####......",
+                "............",
+                "............",
+            ])
+        );
+        // the top hat is what the opening removed
+        assert_eq!(
+            run(MorphOp::TopHat),
+            mask(&[
+                "............",
+                "............",
+                "............",
+                ".........#..",
+                "............",
+                "..##.#......",
+                "............",
+                "............",
+            ])
+        );
+        let gradient = run(MorphOp::Gradient);
+        let mut eroded = Buffer::empty_rgb();
+        let mut dilated = Buffer::empty_rgb();
+        erode(img.borrow(), &mut eroded, ElementShape::Rect, 3, 3);
+        dilate(img.borrow(), &mut dilated, ElementShape::Rect, 3, 3);
+        let expected = dilated
+            .data
+            .iter()
+            .zip(&*eroded.data)
+            .map(|(d, e)| d - e)
+            .collect::<Vec<_>>();
+        assert_eq!(&*gradient.data, expected);
+    }
+}
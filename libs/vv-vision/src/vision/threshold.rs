@@ -0,0 +1,130 @@
+use super::{box_blur, gaussian_blur};
+use crate::broadcast::broadcast_pool;
+use crate::buffer::{Buffer, PixelFormat};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How to compute the local threshold for [`adaptive_threshold`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum AdaptiveMethod {
+    /// Use the mean of the window around each pixel.
+    #[default]
+    Mean,
+    /// Use a Gaussian-weighted mean of the window around each pixel, so closer pixels count for more.
+    Gaussian,
+}
+
+/// Compute a threshold for an image with Otsu's method.
+///
+/// This picks the threshold that best splits the brightness histogram into two classes, by maximizing the variance between them.
+/// Images that aren't already [`LUMA`](PixelFormat::LUMA) are converted to it first. Pixels brighter than the threshold are in the
+/// brighter class. If every pixel is the same brightness, that brightness is the threshold.
+pub fn otsu_level(img: Buffer<'_>) -> u8 {
+    let img = img.convert_cow(PixelFormat::LUMA);
+    let mut hist = [0u64; 256];
+    for &px in &*img.data {
+        hist[px as usize] += 1;
+    }
+    let total = img.data.len() as f64;
+    let sum = hist
+        .iter()
+        .enumerate()
+        .map(|(i, &n)| i as f64 * n as f64)
+        .sum::<f64>();
+    // if there's only one level, nothing splits the histogram, so that level puts everything in the darker class
+    let mut best = (hist.iter().position(|&n| n > 0).unwrap_or(0), 0.0);
+    let mut count = 0.0;
+    let mut partial = 0.0;
+    for (level, &n) in hist.iter().enumerate() {
+        count += n as f64;
+        partial += level as f64 * n as f64;
+        if count == 0.0 || count == total {
+            continue;
+        }
+        let mean_dark = partial / count;
+        let mean_bright = (sum - partial) / (total - count);
+        let diff = mean_dark - mean_bright;
+        let variance = count * (total - count) * diff * diff;
+        if variance > best.1 {
+            best = (level, variance);
+        }
+    }
+    best.0 as u8
+}
+
+/// Threshold an image at a fixed level.
+///
+/// The destination image will have the same dimensions as the source, with a [`LUMA`](PixelFormat::LUMA) format. Pixels brighter
+/// than the level will have a value of 255, and others will have a value of 0, or the other way around if `invert` is set.
+pub fn threshold(src: Buffer<'_>, dst: &mut Buffer<'_>, level: u8, invert: bool) {
+    let src = src.convert_cow(PixelFormat::LUMA);
+    dst.width = src.width;
+    dst.height = src.height;
+    dst.format = PixelFormat::LUMA;
+    broadcast_pool().install(|| {
+        dst.resize_data()
+            .par_iter_mut()
+            .zip(&*src.data)
+            .for_each(|(out, &px)| *out = if (px > level) != invert { 255 } else { 0 });
+    });
+}
+
+/// Threshold an image with a level chosen by [Otsu's method](otsu_level), returning the level.
+///
+/// The output is the same as from [`threshold`].
+pub fn otsu_threshold(src: Buffer<'_>, dst: &mut Buffer<'_>, invert: bool) -> u8 {
+    let src = src.convert_cow(PixelFormat::LUMA);
+    let level = otsu_level(src.borrow());
+    threshold(src, dst, level, invert);
+    level
+}
+
+/// Threshold each pixel of an image against the brightness of the pixels around it.
+///
+/// A pixel is in the mask if it's more than `offset` brighter than the mean of the `size`x`size` window around it, or more than
+/// `offset` darker if `invert` is set. Since the threshold follows the local brightness, this handles uneven lighting much better
+/// than a global threshold. The size must be an odd number.
+///
+/// The output is a [`LUMA`](PixelFormat::LUMA) image, like from [`threshold`].
+pub fn adaptive_threshold(
+    src: Buffer<'_>,
+    dst: &mut Buffer<'_>,
+    method: AdaptiveMethod,
+    size: usize,
+    offset: i16,
+    invert: bool,
+) {
+    assert!(size & 1 == 1, "Window size must be an odd number");
+    let src = src.convert_cow(PixelFormat::LUMA);
+    let mut local = src.clone_static();
+    let mut aux = Buffer::empty_rgb();
+    match method {
+        AdaptiveMethod::Mean => box_blur(&mut local, &mut aux, size, size),
+        AdaptiveMethod::Gaussian => {
+            // the same sigma that OpenCV picks for a kernel size
+            let sigma = 0.3 * ((size as f32 - 1.0) * 0.5 - 1.0) + 0.8;
+            gaussian_blur(&mut local, &mut aux, sigma, size, size);
+        }
+    }
+    dst.width = src.width;
+    dst.height = src.height;
+    dst.format = PixelFormat::LUMA;
+    broadcast_pool().install(|| {
+        dst.resize_data()
+            .par_iter_mut()
+            .zip(&*src.data)
+            .zip(&*local.data)
+            .for_each(|((out, &px), &mean)| {
+                let diff = px as i16 - mean as i16;
+                let set = if invert {
+                    -diff > offset
+                } else {
+                    diff > offset
+                };
+                *out = if set { 255 } else { 0 };
+            });
+    });
+}
@@ -3,3 +3,6 @@ pub mod serialized;
 pub use vv_camera as camera;
 pub use vv_pipelines::*;
 pub use vv_vision::*;
+
+#[cfg(test)]
+mod tests;
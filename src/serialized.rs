@@ -1,15 +1,22 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-#[cfg(feature = "ntable")]
 use std::time::Duration;
 use vv_camera::CameraConfig;
+use vv_camera::group::CameraGroupConfig;
 use vv_pipelines::pipeline::serialized::{ComponentChannel, SerializedGraph};
 use vv_vision::vision_debug::DefaultDebug;
 
 fn default_running() -> usize {
     rayon::current_num_threads().div_ceil(2)
 }
+#[cfg(feature = "serde")]
+fn default_true() -> bool {
+    true
+}
+fn default_hotplug_interval() -> Duration {
+    Duration::from_secs(1)
+}
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -17,6 +24,15 @@ pub struct RunConfig {
     #[cfg_attr(feature = "serde", serde(default = "default_running"))]
     pub max_running: usize,
     pub num_threads: Option<usize>,
+    /// Whether to watch for cameras being plugged in and unplugged.
+    #[cfg_attr(feature = "serde", serde(default = "default_true"))]
+    pub hotplug: bool,
+    /// How often to check for cameras being plugged in and unplugged.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "default_hotplug_interval", with = "humantime_serde")
+    )]
+    pub hotplug_interval: Duration,
 }
 
 impl Default for RunConfig {
@@ -24,6 +40,8 @@ impl Default for RunConfig {
         Self {
             max_running: default_running(),
             num_threads: None,
+            hotplug: true,
+            hotplug_interval: default_hotplug_interval(),
         }
     }
 }
@@ -157,6 +175,20 @@ pub struct CameraWithOutputs {
     pub outputs: Vec<ComponentChannel>,
 }
 
+/// A camera group, along with the components that its frames are sent to.
+///
+/// Components that take named inputs get each camera's frame on the channel with the camera's name, and components that take a primary
+/// input get a list of the frames, in the same order as the cameras.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GroupWithOutputs {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub group: CameraGroupConfig,
+    pub output: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub outputs: Vec<String>,
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ConfigFile {
     #[cfg_attr(feature = "serde", serde(default))]
@@ -167,6 +199,8 @@ pub struct ConfigFile {
     pub ntable: Option<NtConfig>,
     #[cfg_attr(feature = "serde", serde(alias = "camera"))]
     pub cameras: HashMap<String, CameraWithOutputs>,
+    #[cfg_attr(feature = "serde", serde(default, alias = "group"))]
+    pub groups: HashMap<String, GroupWithOutputs>,
     #[cfg_attr(feature = "serde", serde(alias = "component"))]
     pub components: SerializedGraph,
 }
@@ -0,0 +1,265 @@
+//! End-to-end tests that run pipelines on synthetic scenes and compare the results with the ground truth.
+#![cfg(feature = "components")]
+
+use crate::camera::background::FrameQuerier;
+use crate::camera::synthetic::*;
+use crate::camera::{Camera, CameraFactory, FrameContext};
+use crate::components::utils::ChannelComponent;
+use crate::components::vision::BlobsComponent;
+use crate::pipeline::prelude::*;
+use crate::vision::Blob;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+
+/// Run a pipeline on frames from a synthetic camera, returning what reached the channel for each frame.
+fn run_frames<T>(
+    config: &SyntheticCameraConfig,
+    frames: usize,
+    graph: PipelineGraph,
+    input: GraphComponentId,
+    rx: &Receiver<(Arc<T>, Option<GroundTruth>)>,
+) -> Vec<Vec<(Arc<T>, GroundTruth)>> {
+    let (resolver, runner) = graph.compile().unwrap();
+    let input = resolver.get(input).unwrap();
+    let camera = Camera::new("synthetic".to_string(), config.build_camera().unwrap());
+    let (meta, querier) = camera.split();
+    let mut querier = FrameQuerier::Direct(querier);
+    (0..frames)
+        .map(|_| {
+            querier.load_frame(&meta).unwrap();
+            let context = Arc::new(FrameContext::new(&meta, &querier));
+            let frame = querier.get_frame().into_static();
+            // the scope waits for every component to finish
+            rayon::scope(|scope| {
+                runner
+                    .run(
+                        RunParams::new(input)
+                            .with_args(ComponentArgs::single(frame))
+                            .with_context(context),
+                        scope,
+                    )
+                    .unwrap();
+            });
+            rx.try_iter()
+                .map(|(value, truth)| (value, truth.expect("missing ground truth")))
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn blobs_match_ground_truth() {
+    let config = SyntheticCameraConfig {
+        width: 160,
+        height: 120,
+        background_color: [0, 0, 0],
+        shapes: vec![
+            Shape::Rect {
+                center: Trajectory::Waypoints {
+                    waypoints: vec![[30.0, 30.0], [60.0, 80.0]],
+                    frames: NonZeroU32::new(5).unwrap(),
+                    mode: TrajectoryMode::Bounce,
+                },
+                width: 20.0,
+                height: 12.0,
+                angle: 0.0,
+                color: [255, 255, 0],
+            },
+            Shape::Circle {
+                center: Trajectory::Waypoints {
+                    waypoints: vec![[120.0, 30.0], [120.0, 90.0], [100.0, 60.0]],
+                    frames: NonZeroU32::new(4).unwrap(),
+                    mode: TrajectoryMode::Loop,
+                },
+                radius: 10.5,
+                color: [0, 128, 255],
+            },
+        ],
+        render_intrinsics: None,
+        blur: 0.0,
+        noise: 0.0,
+        seed: 0,
+    };
+    let mut graph = PipelineGraph::new();
+    let blobs = graph
+        .add_named_component(Arc::new(BlobsComponent::default()), "blobs")
+        .unwrap();
+    let (channel, rx) = ChannelComponent::<Vec<Blob>, GroundTruthTag>::new(None);
+    let channel = graph
+        .add_named_component(Arc::new(channel), "channel")
+        .unwrap();
+    graph.add_dependency((blobs, "vec"), channel).unwrap();
+    let results = run_frames(&config, 12, graph, blobs, &rx);
+    for (i, frame) in results.into_iter().enumerate() {
+        let [(blobs, truth)] = &frame[..] else {
+            panic!("expected one result for frame {i}, got {}", frame.len());
+        };
+        assert_eq!(truth.frame, i as u64);
+        let mut blobs = (**blobs).clone();
+        blobs.sort_by_key(|b| b.min_x);
+        assert_eq!(blobs.len(), truth.objects.len(), "frame {i}");
+        for (blob, object) in blobs.iter().zip(&truth.objects) {
+            let found = [blob.min_x, blob.min_y, blob.max_x, blob.max_y].map(f64::from);
+            for (found, expected) in found.into_iter().zip(object.bounds) {
+                assert!(
+                    (found - expected).abs() <= 1.0,
+                    "frame {i}: blob {found:?} doesn't match {object:?}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn blobs_filter_by_shape() {
+    let config = SyntheticCameraConfig {
+        width: 160,
+        height: 120,
+        background_color: [0, 0, 0],
+        shapes: vec![
+            Shape::Rect {
+                center: Trajectory::Fixed([50.0, 60.0]),
+                width: 40.0,
+                height: 14.0,
+                angle: 30.0,
+                color: [255, 255, 255],
+            },
+            Shape::Circle {
+                center: Trajectory::Fixed([120.0, 60.0]),
+                radius: 14.0,
+                color: [255, 255, 255],
+            },
+        ],
+        render_intrinsics: None,
+        blur: 0.0,
+        noise: 0.0,
+        seed: 0,
+    };
+    let rects = BlobsComponent {
+        min_rect_fill: 0.9,
+        min_rect_aspect: 2.0,
+        ..Default::default()
+    };
+    let circles = BlobsComponent {
+        min_circle_fill: 0.8,
+        min_solidity: 0.95,
+        ..Default::default()
+    };
+    // the rotated rectangle only fills about 40% of its bounding box, and the circle fills about 78%
+    let filled = BlobsComponent {
+        min_fill: 0.6,
+        ..Default::default()
+    };
+    for (component, expected) in [(rects, 0), (circles, 1), (filled, 1)] {
+        let mut graph = PipelineGraph::new();
+        let blobs = graph
+            .add_named_component(Arc::new(component), "blobs")
+            .unwrap();
+        let (channel, rx) = ChannelComponent::<Vec<Blob>, GroundTruthTag>::new(None);
+        let channel = graph
+            .add_named_component(Arc::new(channel), "channel")
+            .unwrap();
+        graph.add_dependency((blobs, "vec"), channel).unwrap();
+        let results = run_frames(&config, 1, graph, blobs, &rx);
+        let [(blobs, truth)] = &results[0][..] else {
+            panic!("expected one result, got {}", results[0].len());
+        };
+        let [blob] = &blobs[..] else {
+            panic!("expected one blob, got {blobs:?}");
+        };
+        let object = &truth.objects[expected];
+        let found = [blob.min_x, blob.min_y, blob.max_x, blob.max_y].map(f64::from);
+        for (found, expected) in found.into_iter().zip(object.bounds) {
+            assert!(
+                (found - expected).abs() <= 1.0,
+                "blob {blob:?} doesn't match {object:?}"
+            );
+        }
+    }
+}
+
+#[cfg(feature = "apriltag")]
+#[test]
+fn apriltag_pose_matches_ground_truth() {
+    use crate::components::apriltag::{AprilTagFactory, DetectPoseComponent};
+    use vv_apriltag::{DetectorConfig, PoseEstimation};
+
+    let config = SyntheticCameraConfig {
+        width: 640,
+        height: 480,
+        background_color: [100, 100, 100],
+        shapes: vec![Shape::Tag {
+            family: "tag36h11".to_string(),
+            id: 5,
+            size: 0.2,
+            pose: Trajectory::Waypoints {
+                waypoints: vec![
+                    TagPose {
+                        translation: [-0.2, 0.05, 1.2],
+                        rotation: [0.0, 20.0, 0.0],
+                    },
+                    TagPose {
+                        translation: [0.2, -0.05, 1.8],
+                        rotation: [-15.0, -10.0, 30.0],
+                    },
+                ],
+                frames: NonZeroU32::new(4).unwrap(),
+                mode: TrajectoryMode::Bounce,
+            },
+        }],
+        render_intrinsics: None,
+        blur: 0.8,
+        noise: 3.0,
+        seed: 1,
+    };
+    let mut graph = PipelineGraph::new();
+    let detector = AprilTagFactory {
+        config: DetectorConfig {
+            families: vec!["tag36h11".parse().unwrap()],
+            ..Default::default()
+        },
+    };
+    let detect = graph
+        .add_named_component(detector.build().into(), "apriltag")
+        .unwrap();
+    let pose = graph
+        .add_named_component(
+            Arc::new(DetectPoseComponent::Infer { tag_size: 0.2 }),
+            "pose",
+        )
+        .unwrap();
+    let (channel, rx) = ChannelComponent::<PoseEstimation, GroundTruthTag>::new(None);
+    let channel = graph
+        .add_named_component(Arc::new(channel), "channel")
+        .unwrap();
+    graph.add_dependency(detect, pose).unwrap();
+    graph.add_dependency(pose, channel).unwrap();
+    let results = run_frames(&config, 8, graph, detect, &rx);
+    for (i, frame) in results.into_iter().enumerate() {
+        let [(estimate, truth)] = &frame[..] else {
+            panic!("expected one tag in frame {i}, got {}", frame.len());
+        };
+        let ObjectKind::Tag {
+            translation,
+            rotation,
+            ..
+        } = truth.objects[0].kind
+        else {
+            unreachable!()
+        };
+        let error = (estimate.pose.translation - translation).abs();
+        assert!(
+            error < 0.02 * translation.z(),
+            "frame {i}: translation {:?} is off by {error}m from {translation:?}",
+            estimate.pose.translation
+        );
+        // the angle between the two rotations, from the trace of R_est^T R_true
+        let [a0, a1, a2, a3, a4, a5, a6, a7, a8] = estimate.pose.rotation.0;
+        let [b0, b1, b2, b3, b4, b5, b6, b7, b8] = rotation.0;
+        let trace =
+            a0 * b0 + a1 * b1 + a2 * b2 + a3 * b3 + a4 * b4 + a5 * b5 + a6 * b6 + a7 * b7 + a8 * b8;
+        let angle = ((trace - 1.0) / 2.0).clamp(-1.0, 1.0).acos().to_degrees();
+        assert!(angle < 3.0, "frame {i}: rotation is off by {angle} degrees");
+    }
+}